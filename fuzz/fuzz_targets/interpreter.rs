@@ -0,0 +1,59 @@
+// Runs an arbitrary memory image and machine state for a bounded number of frames with arbitrary key
+// presses, checking the machine never panics and its state stays valid after every frame.
+
+#![no_main]
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use rust_chip8_emulator::chip8::Chip8;
+use rust_chip8_emulator::quirks::Quirks;
+
+#[derive(Debug, Arbitrary)]
+struct Input {
+    memory: Vec<u8>, // written from address 0, fonts included
+    pc: u16,
+    i_register: u16,
+    registers: [u8; 16],
+    quirks: [bool; 7],
+    speed: u8,
+    seed: u64,
+    frames: u8,
+    keys: Vec<(u8, u8, bool)>, // frame, key, pressed
+}
+
+fuzz_target!(|input: Input| {
+    let mut chip8 = Chip8::headless();
+    let [shift, memory_increment_by_x, memory_leave_i_unchanged, wrap, jump, vblank, logic] = input.quirks;
+    chip8.set_quirks(Quirks { shift, memory_increment_by_x, memory_leave_i_unchanged, wrap, jump, vblank, logic });
+    chip8.set_speed(input.speed as usize % 64 + 1);
+    chip8.set_seed(input.seed);
+    let len = input.memory.len().min(chip8.memory().len());
+    chip8.write_memory(0, &input.memory[..len]);
+    chip8.set_pc(input.pc);
+    chip8.set_i_register(input.i_register);
+    for (x, &value) in input.registers.iter().enumerate() {
+        chip8.set_register(x, value);
+    }
+
+    for frame in 0..input.frames as u16 % 120 {
+        for &(_, key, pressed) in input.keys.iter().filter(|(at, _, _)| *at as u16 == frame) {
+            if pressed {
+                chip8.press_key(key);
+            } else {
+                chip8.release_key(key);
+            }
+        }
+        let cycle = chip8.cycle();
+        let fault = chip8.fault();
+        chip8.step_frame();
+
+        assert!((chip8.pc() as usize) < chip8.memory().len(), "pc {:#X} is outside memory", chip8.pc());
+        assert!(chip8.stack().len() <= 16);
+        assert!(chip8.cycle() > cycle && chip8.cycle() <= cycle + chip8.speed() as u64);
+        //a fault stops the machine where it is
+        if fault.is_some() {
+            assert_eq!(chip8.fault(), fault);
+            assert!(chip8.is_halted());
+        }
+    }
+});
@@ -0,0 +1,24 @@
+// Loads arbitrary bytes as a ROM, the way a file from disk would be, and runs it for a few seconds of
+// frames under every quirk preset.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rust_chip8_emulator::chip8::Chip8;
+use rust_chip8_emulator::quirks::Quirks;
+
+fuzz_target!(|rom: &[u8]| {
+    for preset in Quirks::PRESETS {
+        let mut chip8 = Chip8::headless();
+        if chip8.load_rom_bytes(rom, "fuzz").is_err() {
+            return;
+        }
+        chip8.set_quirks(Quirks::preset(preset).unwrap());
+        chip8.set_seed(0);
+        chip8.reset();
+        for _ in 0..180 {
+            chip8.step_frame();
+        }
+        assert!((chip8.pc() as usize) < chip8.memory().len());
+    }
+});
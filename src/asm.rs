@@ -0,0 +1,170 @@
+// Assembler for the mnemonics disasm prints, one instruction per line:
+//
+//     loop:   LD V0, 0x05      ; comments start with a semicolon
+//             DRW V1, V2, 5
+//             JP loop
+//     sprite: DB 0xF0, 0x90, 0xF0
+//
+// Numbers are decimal, 0x or # hex, or 0b binary, and labels can stand in for any number. DB and DW
+// emit bytes and big-endian words. Programs are assembled for 0x200.
+
+use std::collections::HashMap;
+use std::fmt;
+
+const START: u16 = 0x200;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsmError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for AsmError {}
+
+struct Statement<'a> {
+    line: usize,
+    mnemonic: String,
+    operands: Vec<&'a str>,
+}
+
+pub fn assemble(source: &str) -> Result<Vec<u8>, AsmError> {
+    //first pass places labels, second encodes with every label known
+    let mut labels = HashMap::new();
+    let mut statements = Vec::new();
+    let mut address = START as usize;
+
+    for (index, line) in source.lines().enumerate() {
+        let line_number = index + 1;
+        let mut code = line.split(';').next().unwrap_or("").trim();
+
+        while let Some((label, rest)) = code.split_once(':').filter(|(label, _)| is_identifier(label.trim())) {
+            if labels.insert(label.trim().to_lowercase(), address).is_some() {
+                return Err(AsmError { line: line_number, message: format!("label \"{}\" is defined twice", label.trim()) });
+            }
+            code = rest.trim();
+        }
+        if code.is_empty() {
+            continue;
+        }
+
+        let (mnemonic, operands) = code.split_once(char::is_whitespace).unwrap_or((code, ""));
+        let mnemonic = mnemonic.to_uppercase();
+        let operands: Vec<&str> = operands.split(',').map(str::trim).filter(|operand| !operand.is_empty()).collect();
+        address += match mnemonic.as_str() {
+            "DB" => operands.len(),
+            "DW" => operands.len() * 2,
+            _ => 2,
+        };
+        statements.push(Statement { line: line_number, mnemonic, operands });
+    }
+
+    let mut rom = Vec::new();
+    for statement in &statements {
+        let error = |message: String| AsmError { line: statement.line, message };
+        match statement.mnemonic.as_str() {
+            "DB" => {
+                for operand in &statement.operands {
+                    rom.push(number(operand, &labels, 0xFF).map_err(error)? as u8);
+                }
+            }
+            "DW" => {
+                for operand in &statement.operands {
+                    rom.extend(number(operand, &labels, 0xFFFF).map_err(error)?.to_be_bytes());
+                }
+            }
+            _ => rom.extend(encode(&statement.mnemonic, &statement.operands, &labels).map_err(error)?.to_be_bytes()),
+        }
+    }
+    Ok(rom)
+}
+
+fn encode(mnemonic: &str, operands: &[&str], labels: &HashMap<String, usize>) -> Result<u16, String> {
+    let upper: Vec<String> = operands.iter().map(|operand| operand.to_uppercase()).collect();
+    let args: Vec<&str> = upper.iter().map(String::as_str).collect();
+    let register = |operand: &str| parse_register(operand).ok_or_else(|| format!("expected a register V0-VF, got \"{}\"", operand));
+    let address = |operand: &str| number(operand, labels, 0xFFF);
+    let byte = |operand: &str| number(operand, labels, 0xFF);
+    let xy = |opcode: u16, x: &str, y: &str| -> Result<u16, String> { Ok(opcode | register(x)? << 8 | register(y)? << 4) };
+    let xnn = |opcode: u16, x: &str, nn: &str| -> Result<u16, String> { Ok(opcode | register(x)? << 8 | byte(nn)?) };
+
+    let opcode = match (mnemonic, args.as_slice()) {
+        ("CLS", []) => 0x00E0,
+        ("RET", []) => 0x00EE,
+        ("SYS", [nnn]) => address(nnn)?,
+        ("JP", ["V0", nnn]) => 0xB000 | address(nnn)?,
+        ("JP", [nnn]) => 0x1000 | address(nnn)?,
+        ("CALL", [nnn]) => 0x2000 | address(nnn)?,
+        ("SE", [x, y]) if parse_register(y).is_some() => xy(0x5000, x, y)?,
+        ("SE", [x, nn]) => xnn(0x3000, x, nn)?,
+        ("SNE", [x, y]) if parse_register(y).is_some() => xy(0x9000, x, y)?,
+        ("SNE", [x, nn]) => xnn(0x4000, x, nn)?,
+        ("LD", ["I", nnn]) => 0xA000 | address(nnn)?,
+        ("LD", ["DT", x]) => 0xF015 | register(x)? << 8,
+        ("LD", ["ST", x]) => 0xF018 | register(x)? << 8,
+        ("LD", ["F", x]) => 0xF029 | register(x)? << 8,
+        ("LD", ["B", x]) => 0xF033 | register(x)? << 8,
+        ("LD", ["[I]", x]) => 0xF055 | register(x)? << 8,
+        ("LD", ["R", x]) => 0xF075 | register(x)? << 8,
+        ("LD", [x, "DT"]) => 0xF007 | register(x)? << 8,
+        ("LD", [x, "K"]) => 0xF00A | register(x)? << 8,
+        ("LD", [x, "[I]"]) => 0xF065 | register(x)? << 8,
+        ("LD", [x, "R"]) => 0xF085 | register(x)? << 8,
+        ("LD", [x, y]) if parse_register(y).is_some() => xy(0x8000, x, y)?,
+        ("LD", [x, nn]) => xnn(0x6000, x, nn)?,
+        ("ADD", ["I", x]) => 0xF01E | register(x)? << 8,
+        ("ADD", [x, y]) if parse_register(y).is_some() => xy(0x8004, x, y)?,
+        ("ADD", [x, nn]) => xnn(0x7000, x, nn)?,
+        ("OR", [x, y]) => xy(0x8001, x, y)?,
+        ("AND", [x, y]) => xy(0x8002, x, y)?,
+        ("XOR", [x, y]) => xy(0x8003, x, y)?,
+        ("SUB", [x, y]) => xy(0x8005, x, y)?,
+        ("SHR", [x]) => xy(0x8006, x, x)?,
+        ("SHR", [x, y]) => xy(0x8006, x, y)?,
+        ("SUBN", [x, y]) => xy(0x8007, x, y)?,
+        ("SHL", [x]) => xy(0x800E, x, x)?,
+        ("SHL", [x, y]) => xy(0x800E, x, y)?,
+        ("RND", [x, nn]) => xnn(0xC000, x, nn)?,
+        ("DRW", [x, y, n]) => xy(0xD000, x, y)? | number(n, labels, 0xF)?,
+        ("SKP", [x]) => 0xE09E | register(x)? << 8,
+        ("SKNP", [x]) => 0xE0A1 | register(x)? << 8,
+        _ => return Err(format!("unknown instruction \"{}\"", format!("{} {}", mnemonic, operands.join(", ")).trim_end())),
+    };
+    Ok(opcode)
+}
+
+fn parse_register(operand: &str) -> Option<u16> {
+    let digit = operand.strip_prefix(['V', 'v'])?;
+    if digit.len() != 1 {
+        return None;
+    }
+    u16::from_str_radix(digit, 16).ok()
+}
+
+// a literal or label no bigger than max
+fn number(operand: &str, labels: &HashMap<String, usize>, max: u16) -> Result<u16, String> {
+    let lower = operand.to_lowercase();
+    let value = if let Some(hex) = lower.strip_prefix("0x").or(lower.strip_prefix('#')) {
+        usize::from_str_radix(hex, 16).ok()
+    } else if let Some(binary) = lower.strip_prefix("0b") {
+        usize::from_str_radix(binary, 2).ok()
+    } else if lower.starts_with(|c: char| c.is_ascii_digit()) {
+        lower.parse().ok()
+    } else {
+        Some(*labels.get(&lower).ok_or_else(|| format!("unknown label \"{}\"", lower))?)
+    };
+    match value {
+        Some(value) if value <= max as usize => Ok(value as u16),
+        Some(value) => Err(format!("{} is too big, the most this operand takes is 0x{:X}", value, max)),
+        None => Err(format!("invalid number \"{}\"", operand)),
+    }
+}
+
+fn is_identifier(text: &str) -> bool {
+    text.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') && text.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
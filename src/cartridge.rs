@@ -0,0 +1,108 @@
+// Octo cartridges: GIF images that carry a program's Octo source and its options in the low two bits of
+// every pixel's color index. Four pixels make a byte, high bits first, and the bytes are a 32-bit
+// big-endian length followed by that much JSON:
+//
+//     {"program": ": main ...", "options": {"tickrate": 20, "fillColor": "#FFCC00", "shiftQuirks": false, ...}}
+
+use crate::asm::AsmError;
+use crate::config::Settings;
+use crate::display::Palette;
+use crate::octo;
+use crate::quirks::Quirks;
+use serde::Deserialize;
+use std::fmt;
+use std::io::Read;
+
+#[derive(Debug)]
+pub enum CartridgeError {
+    Gif(gif::DecodingError),
+    Payload(String),
+    Json(serde_json::Error),
+    Compile(AsmError),
+}
+
+impl fmt::Display for CartridgeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CartridgeError::Gif(e) => write!(f, "{}", e),
+            CartridgeError::Payload(message) => write!(f, "not an Octo cartridge: {}", message),
+            CartridgeError::Json(e) => write!(f, "not an Octo cartridge: {}", e),
+            CartridgeError::Compile(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for CartridgeError {}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Options {
+    pub tickrate: Option<usize>, // instructions per frame
+    pub fill_color: Option<String>,
+    pub background_color: Option<String>,
+    pub shift_quirks: Option<bool>,
+    pub load_store_quirks: Option<bool>,
+    pub clip_quirks: Option<bool>,
+    pub jump_quirks: Option<bool>,
+    pub logic_quirks: Option<bool>,
+    pub v_blank_quirks: Option<bool>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Cartridge {
+    pub program: String,
+    #[serde(default)]
+    pub options: Options,
+}
+
+impl Cartridge {
+    pub fn decode(reader: impl Read) -> Result<Self, CartridgeError> {
+        let mut options = gif::DecodeOptions::new();
+        options.set_color_output(gif::ColorOutput::Indexed);
+        let mut decoder = options.read_info(reader).map_err(CartridgeError::Gif)?;
+
+        let mut bytes = Vec::new();
+        while let Some(frame) = decoder.read_next_frame().map_err(CartridgeError::Gif)? {
+            bytes.extend(frame.buffer.chunks_exact(4).map(|pixels| pixels.iter().fold(0, |byte, pixel| byte << 2 | pixel & 3)));
+        }
+
+        let Some((length, payload)) = bytes.split_first_chunk::<4>() else {
+            return Err(CartridgeError::Payload(String::from("the image is too small to hold a program")));
+        };
+        let length = u32::from_be_bytes(*length) as usize;
+        let json = payload.get(..length).ok_or_else(|| {
+            CartridgeError::Payload(format!("it should hold {} bytes but there is only room for {}", length, payload.len()))
+        })?;
+        serde_json::from_slice(json).map_err(CartridgeError::Json)
+    }
+
+    pub fn compile(&self) -> Result<Vec<u8>, CartridgeError> {
+        octo::compile(&self.program).map_err(CartridgeError::Compile)
+    }
+
+    // the options as settings; colors that aren't #RRGGBB are left out
+    pub fn settings(&self) -> Settings {
+        let options = &self.options;
+        //Octo's quirk options default to off, which is how Octo itself runs
+        let quirks = Quirks {
+            shift: options.shift_quirks.unwrap_or(false),
+            memory_increment_by_x: false,
+            memory_leave_i_unchanged: options.load_store_quirks.unwrap_or(false),
+            wrap: !options.clip_quirks.unwrap_or(false),
+            jump: options.jump_quirks.unwrap_or(false),
+            vblank: options.v_blank_quirks.unwrap_or(false),
+            logic: options.logic_quirks.unwrap_or(false),
+        };
+
+        let palette = match (&options.background_color, &options.fill_color) {
+            (Some(background), Some(fill)) => Some(format!("{},{}", background, fill)).filter(|palette| palette.parse::<Palette>().is_ok()),
+            _ => None,
+        };
+        Settings {
+            quirks: Some(quirks.to_string()),
+            ips: options.tickrate.filter(|&tickrate| tickrate > 0).and_then(|tickrate| tickrate.checked_mul(60)),
+            palette,
+            ..Settings::default()
+        }
+    }
+}
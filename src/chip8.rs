@@ -1,19 +1,62 @@
-use crate::display::Display;
+use crate::display::{Display, DEFAULT_SCALE};
+use crate::flags::{self, NUM_FLAGS};
+use crate::instruction::Instruction;
+#[cfg(feature = "jit")]
+use crate::jit::{self, Jit};
+use crate::keymap::{self, Hotkey, Keymap, KeymapFile, KEYPAD_LAYOUT};
+use crate::keypad::{Keypad, NUM_KEYS};
+use crate::metadata::{self, RomInfo};
+use crate::movie::{self, Movie, MovieError, MovieEvent};
+use crate::quirks::Quirks;
+use crate::recompiler::Program;
+use crate::rom::{self, RomError};
+use crate::screenshot::{self, ImageFormat};
 use crate::timers::Timers;
-use crate::stack::Stack;
-use std::fs::File;
+use crate::stack::{Stack, StackError};
+use crate::tas::{self, Tas};
+use crate::terminal::Terminal;
+use crate::video::{self, VideoFormat, VideoRecorder};
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
 use std::io::{self, Read};
 use std::time::{Duration, Instant};
 use std::thread::sleep;
-use rand::Rng;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use minifb::Key;
 
 
 const NUM_ADRESSES: usize = 4096;
 const NUM_REGISTERS: usize = 16;
-const TICK_RATE: f64 = 1.0 / 60.0;
-const INSTRUCTIONS_PER_FRAME: usize = 600 / 60;
-const NUM_KEYS: usize = 16;
+pub const DEFAULT_FRAME_RATE: f64 = 60.0;
+pub const INSTRUCTIONS_PER_FRAME: usize = 600 / 60;
+
+// everything needed to put the machine back where it was, kept in memory for quick save and load
+#[derive(Debug, Clone)]
+pub struct SaveState {
+    memory: [u8; NUM_ADRESSES],
+    frame_buffer: [[bool; 64]; 32],
+    pc: u16,
+    i_register: u16,
+    stack: Stack,
+    timers: Timers,
+    variable_registers: [u8; NUM_REGISTERS],
+    frame: u64,
+    cycle: u64,
+    rng: StdRng,
+    keypad: Keypad,
+    flags: [u8; NUM_FLAGS],
+    fault: Option<StackError>,
+}
+
+// the user's own settings, put back when a movie that replaced them stops replaying
+#[derive(Debug, Clone)]
+struct LiveSettings {
+    quirks: Quirks,
+    instructions_per_frame: usize,
+    seed: u64,
+    flags: [u8; NUM_FLAGS],
+}
 
 #[derive(Debug)]
 pub struct Chip8 {
@@ -24,20 +67,95 @@ pub struct Chip8 {
     stack: Stack,
     timers: Timers,
     variable_registers: [u8; NUM_REGISTERS],
-    key_states: [bool; NUM_KEYS],
+    keypad: Keypad,
+    cycle: u64, // instructions executed, the clock keypad events are scheduled against
+    host_keys_down: Vec<Key>,
+    keys_polled: Instant, // when update_keys last took the window's key events
+    rom_name: String,
+    paused: bool,
+    frame: u64,
+    recorder: Option<VideoRecorder>,
+    keymap: Keymap,
+    keymap_path: Option<PathBuf>,
+    rom: Vec<u8>,
+    quick_save: Option<SaveState>,
+    rebinding: Option<usize>, // position in KEYPAD_LAYOUT while rebinding keys in the window
+    keypad_before_rebind: [Vec<Key>; NUM_KEYS], // bindings put back when rebinding is cancelled
+    quit_held: bool, // Quit keys still down from rebinding, which don't quit until they're released
+    quirks: Quirks,
+    instructions_per_frame: usize,
+    frame_rate: f64, // frames per second the window and terminal loops are paced to
+    seed: u64,
+    rng: StdRng,
+    rom_sha1: String,
+    rom_info: Option<RomInfo>, // what the ROM database knows about the loaded ROM
+    movie: Option<Movie>,      // movie being recorded
+    replay_end: Option<u64>,   // frame a movie being played back ends on, live input is ignored until then
+    before_replay: Option<LiveSettings>,
+    tas: Option<Tas>,
+    flags: [u8; NUM_FLAGS], // FX75/FX85 user flags, kept across runs
+    flags_dir: Option<PathBuf>,
+    flags_dirty: bool,
+    fault: Option<StackError>, // set when the program breaks the stack, it stops on that instruction
+    decoded: Vec<Option<Instruction>>, // instruction cache by address, emptied where memory is written
+    cache_instructions: bool,
+    #[cfg(feature = "jit")]
+    jit: Option<Jit>, // compiled code, when the JIT is on
+    recompiled: Option<&'static Program>, // a ROM translated to Rust ahead of time, see src/recompiler.rs
+}
+
+impl Default for Chip8 {
+    fn default() -> Self {
+        Chip8::new(Display::default())
+    }
 }
 
 impl Chip8 {
-    pub fn default() -> Self {
+    pub fn new(display: Display) -> Self {
+        let seed = rand::random();
         let mut chip8 = Chip8 {
             memory: [0; NUM_ADRESSES],
-            display: Display::default(),
+            display,
             pc: 0x200,
             i_register: 0,
             stack: Stack::default(),
             timers: Timers::default(),
             variable_registers: [0; NUM_REGISTERS],
-            key_states: [false; NUM_KEYS]
+            keypad: Keypad::default(),
+            cycle: 0,
+            host_keys_down: Vec::new(),
+            keys_polled: Instant::now(),
+            rom_name: String::new(),
+            paused: false,
+            frame: 0,
+            recorder: None,
+            keymap: Keymap::default(),
+            keymap_path: None,
+            rom: Vec::new(),
+            quick_save: None,
+            rebinding: None,
+            keypad_before_rebind: Default::default(),
+            quit_held: false,
+            quirks: Quirks::default(),
+            instructions_per_frame: INSTRUCTIONS_PER_FRAME,
+            frame_rate: DEFAULT_FRAME_RATE,
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+            rom_sha1: String::new(),
+            rom_info: None,
+            movie: None,
+            replay_end: None,
+            before_replay: None,
+            tas: None,
+            flags: [0; NUM_FLAGS],
+            flags_dir: flags::default_dir(),
+            flags_dirty: false,
+            fault: None,
+            decoded: vec![None; NUM_ADRESSES],
+            cache_instructions: true,
+            #[cfg(feature = "jit")]
+            jit: None,
+            recompiled: None,
         };
         
         chip8.load_fonts();
@@ -45,34 +163,955 @@ impl Chip8 {
         chip8
     }
 
+    // headless machines don't keep flags, so runs are the same every time
+    pub fn headless() -> Self {
+        let mut chip8 = Chip8::new(Display::headless(DEFAULT_SCALE));
+        chip8.set_flags_dir(None);
+        chip8
+    }
+
+    pub fn display(&self) -> &Display {
+        &self.display
+    }
+
+    pub fn display_mut(&mut self) -> &mut Display {
+        &mut self.display
+    }
+
+    // number of frames emulated since the machine was created
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    // path is where bindings made with the rebind hotkey are saved
+    pub fn set_keymap(&mut self, keymap: Keymap, path: Option<PathBuf>) {
+        self.keymap = keymap;
+        self.keymap_path = path;
+    }
+
+    pub fn keymap(&self) -> &Keymap {
+        &self.keymap
+    }
+
+    pub fn rom_name(&self) -> &str {
+        &self.rom_name
+    }
+
+    // lowercase hex SHA-1 of the loaded ROM
+    pub fn rom_sha1(&self) -> &str {
+        &self.rom_sha1
+    }
+
+    pub fn rom_info(&self) -> Option<&RomInfo> {
+        self.rom_info.as_ref()
+    }
+
+    pub fn quirks(&self) -> Quirks {
+        self.quirks
+    }
+
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        self.quirks = quirks;
+    }
+
+    // instructions executed per 60 Hz frame
+    pub fn speed(&self) -> usize {
+        self.instructions_per_frame
+    }
+
+    pub fn set_speed(&mut self, instructions_per_frame: usize) {
+        self.instructions_per_frame = instructions_per_frame.max(1);
+    }
+
+    pub fn frame_rate(&self) -> f64 {
+        self.frame_rate
+    }
+
+    pub fn set_frame_rate(&mut self, frame_rate: f64) {
+        self.frame_rate = frame_rate;
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    // CXNN draws from an RNG seeded with this, so runs with the same seed and input are identical
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    pub fn flags(&self) -> [u8; NUM_FLAGS] {
+        self.flags
+    }
+
+    // where flags are kept between runs, None to not keep them; set it before loading the ROM
+    pub fn set_flags_dir(&mut self, dir: Option<PathBuf>) {
+        self.flags_dir = dir;
+    }
+
+    // writes the flags if FX75 changed them since they were loaded
+    pub fn save_flags(&mut self) -> io::Result<()> {
+        let Some(dir) = &self.flags_dir else { return Ok(()) };
+        if self.flags_dirty {
+            flags::save(dir, &self.rom_sha1, &self.flags)?;
+            self.flags_dirty = false;
+        }
+        Ok(())
+    }
+
+    // restarts the loaded ROM from a clean machine
+    pub fn reset(&mut self) {
+        self.memory = [0; NUM_ADRESSES];
+        self.display.clear();
+        self.pc = 0x200;
+        self.i_register = 0;
+        self.stack = Stack::default();
+        self.timers = Timers::default();
+        self.variable_registers = [0; NUM_REGISTERS];
+        self.keypad.clear();
+        self.frame = 0;
+        self.cycle = 0;
+        self.fault = None;
+        self.rng = StdRng::seed_from_u64(self.seed);
+        self.load_fonts();
+        self.memory[rom::START_ADDRESS..rom::START_ADDRESS + self.rom.len()].copy_from_slice(&self.rom);
+        self.decoded.fill(None);
+        self.clear_jit();
+    }
+
+    pub fn save_state(&self) -> SaveState {
+        SaveState {
+            memory: self.memory,
+            frame_buffer: self.display.frame_buffer,
+            pc: self.pc,
+            i_register: self.i_register,
+            stack: self.stack.clone(),
+            timers: self.timers.clone(),
+            variable_registers: self.variable_registers,
+            frame: self.frame,
+            cycle: self.cycle,
+            rng: self.rng.clone(),
+            keypad: self.keypad.clone(),
+            flags: self.flags,
+            fault: self.fault,
+        }
+    }
+
+    pub fn load_state(&mut self, state: &SaveState) {
+        self.memory = state.memory;
+        self.decoded.fill(None);
+        self.clear_jit();
+        self.display.frame_buffer = state.frame_buffer;
+        self.pc = state.pc;
+        self.i_register = state.i_register;
+        self.stack = state.stack.clone();
+        self.timers = state.timers.clone();
+        self.variable_registers = state.variable_registers;
+        self.frame = state.frame;
+        self.cycle = state.cycle;
+        self.rng = state.rng.clone();
+        self.keypad = state.keypad.clone();
+        self.fault = state.fault;
+        if self.flags != state.flags {
+            self.flags = state.flags;
+            self.flags_dirty = true;
+        }
+    }
+
     pub fn run(&mut self) {
         let mut last_tick = Instant::now();
+        self.update_title();
 
-        while self.display.is_open() && !self.display.is_key_down(minifb::Key::Escape) {            
-            for _ in 0..INSTRUCTIONS_PER_FRAME {
-                let opcode = self.fetch_opcode();
-                self.execute_opcode(opcode);
+        while self.display.is_open() && (self.rebinding.is_some() || !self.quit_requested()) {
+            if !self.paused && self.rebinding.is_none() {
+                if self.is_tas() {
+                    let live = self.keymap.keypad_states(&self.host_keys_down);
+                    self.tas_advance(Some(live));
+                } else {
+                    let faulted = self.fault.is_some();
+                    self.step_frame();
+                    self.capture_frame();
+                    if !faulted && self.fault.is_some() {
+                        self.update_title();
+                    }
+                }
             }
             self.update_keys();
-            self.timers.decrement_timers();
-            self.display.render();
+            self.handle_hotkeys();
+            if let Err(e) = self.display.render() {
+                println!("Failed to draw the window: {}", e);
+                break;
+            }
+
+            //ensure while loop runs at the frame rate, 60 hz by default
+            let time_elapsed = last_tick.elapsed();
+            let target_duration = Duration::from_secs_f64(1.0 / self.frame_rate);
+            if time_elapsed < target_duration {
+                sleep(target_duration - time_elapsed);
+            }
+            last_tick = Instant::now();
+        }
+
+        if let Err(e) = self.stop_recording() {
+            println!("Failed to finish recording: {}", e);
+        }
+        if let Err(e) = self.save_flags() {
+            println!("Failed to save flags: {}", e);
+        }
+    }
+
+    // same loop as run, but drawing to and reading keys from the terminal instead of a window
+    pub fn run_in_terminal(&mut self, terminal: &mut Terminal) -> io::Result<()> {
+        let mut last_tick = Instant::now();
+
+        terminal.set_keymap(self.keymap.clone());
+
+        loop {
+            terminal.poll_input()?;
+            let hotkeys = terminal.take_hotkeys();
+            if hotkeys.contains(&Hotkey::Quit) {
+                break;
+            }
+            for hotkey in hotkeys {
+                self.handle_machine_hotkey(hotkey);
+            }
+            if self.is_tas() {
+                if !self.paused {
+                    self.tas_advance(Some(terminal.key_states()));
+                }
+            } else {
+                if !self.is_replaying() {
+                    self.keypad.set_states(terminal.key_states(), self.cycle);
+                }
+                if !self.paused {
+                    self.step_frame();
+                }
+            }
+            terminal.render(&self.display, &self.status())?;
 
-            //ensure while loop runs at 60 hz
             let time_elapsed = last_tick.elapsed();
-            let target_duration = Duration::from_secs_f64(TICK_RATE);
+            let target_duration = Duration::from_secs_f64(1.0 / self.frame_rate);
             if time_elapsed < target_duration {
                 sleep(target_duration - time_elapsed);
             }
             last_tick = Instant::now();
         }
+
+        self.save_flags()
+    }
+
+    // runs frames as fast as possible without a window, saving a screenshot after every frame listed in screenshot_frames
+    pub fn run_headless(&mut self, frames: u64, screenshot_frames: &[u64], format: ImageFormat) -> io::Result<Vec<PathBuf>> {
+        let mut saved = Vec::new();
+        for _ in 0..frames {
+            self.step_frame();
+            if screenshot_frames.contains(&self.frame) {
+                saved.push(self.save_screenshot(format)?);
+            }
+        }
+        Ok(saved)
+    }
+
+    // headless capture of the frames in range, numbered like frame() so the first emulated frame is 1
+    pub fn record_headless(&mut self, range: RangeInclusive<u64>, path: &Path, format: VideoFormat) -> io::Result<u64> {
+        while self.frame + 1 < *range.start() {
+            self.step_frame();
+        }
+        let mut recorder = VideoRecorder::create(path, format, &self.display, video::DEFAULT_VIDEO_SCALE)?;
+        while self.frame < *range.end() {
+            self.step_frame();
+            recorder.write_frame(&self.display)?;
+        }
+        let frames = recorder.frames();
+        recorder.finish()?;
+        Ok(frames)
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recorder.is_some()
+    }
+
+    pub fn start_recording(&mut self, path: &Path, format: VideoFormat) -> io::Result<()> {
+        self.stop_recording()?;
+        self.recorder = Some(VideoRecorder::create(path, format, &self.display, video::DEFAULT_VIDEO_SCALE)?);
+        Ok(())
+    }
+
+    // returns the number of frames written, or 0 if nothing was being recorded
+    pub fn stop_recording(&mut self) -> io::Result<u64> {
+        match self.recorder.take() {
+            Some(recorder) => {
+                let frames = recorder.frames();
+                recorder.finish()?;
+                Ok(frames)
+            }
+            None => Ok(0),
+        }
+    }
+
+    fn capture_frame(&mut self) {
+        if let Some(recorder) = self.recorder.as_mut() {
+            if let Err(e) = recorder.write_frame(&self.display) {
+                println!("Recording stopped: {}", e);
+                self.recorder = None;
+            }
+        }
+    }
+
+    fn toggle_recording(&mut self, format: VideoFormat) {
+        if self.is_recording() {
+            match self.stop_recording() {
+                Ok(frames) => println!("Stopped recording after {} frames", frames),
+                Err(e) => println!("Failed to finish recording: {}", e),
+            }
+            return;
+        }
+
+        let started = screenshot::timestamped_path(Path::new(video::RECORDING_DIR), &self.rom_name, format.extension())
+            .and_then(|path| self.start_recording(&path, format).map(|_| path));
+        match started {
+            Ok(path) => println!("Recording to {}", path.display()),
+            Err(e) => println!("Failed to start recording: {}", e),
+        }
+    }
+
+    pub fn keypad(&self) -> &Keypad {
+        &self.keypad
+    }
+
+    // queues a key change for the next instruction, for headless runs and scripted input
+    pub fn press_key(&mut self, key: u8) {
+        self.keypad.push(key, true, self.cycle);
+    }
+
+    pub fn release_key(&mut self, key: u8) {
+        self.keypad.push(key, false, self.cycle);
     }
 
-    pub fn load_rom(&mut self, path: &str) -> io::Result<()>{
-        let mut file = File::open(path)?;
-        file.read(&mut self.memory[0x200..])?;
+    pub fn cycle(&self) -> u64 {
+        self.cycle
+    }
+
+    // runs one instruction without applying queued key events or touching the timers, and returns it
+    pub fn step(&mut self) -> Instruction {
+        self.cycle += 1;
+        if self.fault.is_some() {
+            return Instruction::decode(self.opcode_at(self.pc));
+        }
+        let instruction = self.fetch();
+        self.execute(instruction);
+        instruction
+    }
+
+    // decoding every instruction each time it runs instead of once per address, to compare against
+    pub fn set_instruction_cache(&mut self, enabled: bool) {
+        self.cache_instructions = enabled;
+        self.decoded.fill(None);
+    }
+
+    // runs the blocks of a ROM recompiled to Rust wherever pc is on one, see src/recompiler.rs
+    pub fn set_recompiled(&mut self, program: Option<&'static Program>) {
+        self.recompiled = program;
+    }
+
+    // runs the program as native code where it can, compiling it as it goes, see src/jit.rs
+    #[cfg(feature = "jit")]
+    pub fn set_jit(&mut self, enabled: bool) -> Result<(), String> {
+        self.jit = if enabled { Some(Jit::new()?) } else { None };
         Ok(())
     }
 
+    // code the JIT failed to compile and left to the interpreter, 0 with the JIT off
+    #[cfg(feature = "jit")]
+    pub fn jit_failures(&self) -> u64 {
+        self.jit.as_ref().map_or(0, Jit::failures)
+    }
+
+    // runs compiled code from pc up to the end of the frame or the next key event, returning how many
+    // instructions it ran; None leaves the next instruction to the interpreter
+    #[cfg(feature = "jit")]
+    fn run_compiled(&mut self, budget: usize) -> Option<usize> {
+        if self.fault.is_some() {
+            return None;
+        }
+        let compiled = self.jit.as_mut()?.compiled(self.pc, &self.memory, self.quirks)?;
+        let limit = self.native_limit(budget);
+        let mut state = jit::State {
+            v: self.variable_registers,
+            i: self.i_register,
+            pc: self.pc,
+            delay_timer: self.timers.dt_register,
+            sound_timer: self.timers.st_register,
+        };
+        let instructions = compiled.run(&mut state, &self.memory, limit);
+        if instructions == 0 {
+            return None;
+        }
+        self.variable_registers = state.v;
+        self.i_register = state.i;
+        self.pc = state.pc;
+        self.timers.dt_register = state.delay_timer;
+        self.timers.st_register = state.sound_timer;
+        self.cycle += instructions as u64;
+        Some(instructions)
+    }
+
+    // runs the recompiled block at pc, returning how many instructions it ran and whether it drew
+    fn run_recompiled(&mut self, budget: usize) -> Option<(usize, bool)> {
+        let program = self.recompiled?;
+        if self.fault.is_some() || program.quirks != self.quirks {
+            return None;
+        }
+        let block = program.block(self.pc, &self.memory)?;
+        if block.instructions() > self.native_limit(budget) {
+            return None;
+        }
+        (block.run)(self);
+        self.cycle += block.instructions() as u64;
+        Some((block.instructions(), block.draws))
+    }
+
+    // instructions native code may run before the end of the frame or the next key event
+    fn native_limit(&self, budget: usize) -> usize {
+        self.keypad.pending().next().map_or(budget, |event| budget.min(event.cycle.saturating_sub(self.cycle) as usize))
+    }
+
+    fn clear_jit(&mut self) {
+        #[cfg(feature = "jit")]
+        if let Some(jit) = self.jit.as_mut() {
+            jit.clear();
+        }
+    }
+
+    pub fn step_frame(&mut self) {
+        let mut executed = 0;
+        while executed < self.instructions_per_frame {
+            if self.keypad.is_due(self.cycle) {
+                let applied = self.keypad.apply_until(self.cycle);
+                if let Some(movie) = self.movie.as_mut() {
+                    for event in applied {
+                        movie.events.push(MovieEvent { frame: self.frame, cycle: self.cycle, key: event.key, pressed: event.pressed });
+                    }
+                }
+            }
+            if let Some((instructions, draws)) = self.run_recompiled(self.instructions_per_frame - executed) {
+                executed += instructions;
+                if self.quirks.vblank && draws {
+                    break;
+                }
+                continue;
+            }
+            #[cfg(feature = "jit")]
+            if let Some(instructions) = self.run_compiled(self.instructions_per_frame - executed) {
+                executed += instructions;
+                continue;
+            }
+            let instruction = self.step();
+            executed += 1;
+
+            //with the vblank quirk a draw ends the frame, like the VIP waiting for the display interrupt
+            if self.quirks.vblank && matches!(instruction, Instruction::Draw(..)) {
+                break;
+            }
+        }
+        self.timers.decrement_timers();
+        self.frame += 1;
+        if self.replay_end.is_some_and(|end| self.frame >= end) {
+            self.stop_replay();
+        }
+    }
+
+    // whether the program is stuck on a 1NNN jump to its own address, which is how most ROMs end, or has
+    // stopped on a stack fault
+    pub fn is_halted(&self) -> bool {
+        let opcode = self.opcode_at(self.pc);
+        self.fault.is_some() || opcode & 0xF000 == 0x1000 && opcode & 0x0FFF == self.pc
+    }
+
+    pub fn fault(&self) -> Option<StackError> {
+        self.fault
+    }
+
+    // machine state, so tests and tools can set up a program without a ROM or a window
+    pub fn registers(&self) -> [u8; NUM_REGISTERS] {
+        self.variable_registers
+    }
+
+    pub fn register(&self, x: usize) -> u8 {
+        self.variable_registers[x]
+    }
+
+    pub fn set_register(&mut self, x: usize, value: u8) {
+        self.variable_registers[x] = value;
+    }
+
+    pub fn i_register(&self) -> u16 {
+        self.i_register
+    }
+
+    pub fn set_i_register(&mut self, address: u16) {
+        self.i_register = address;
+    }
+
+    pub fn pc(&self) -> u16 {
+        self.pc
+    }
+
+    pub fn set_pc(&mut self, address: u16) {
+        self.pc = address & 0x0FFF;
+    }
+
+    pub fn memory(&self) -> &[u8] {
+        &self.memory
+    }
+
+    pub fn write_memory(&mut self, address: usize, bytes: &[u8]) {
+        self.memory[address..address + bytes.len()].copy_from_slice(bytes);
+        self.decoded.fill(None);
+        self.clear_jit();
+    }
+
+    // return addresses, innermost last
+    pub fn stack(&self) -> &[u16] {
+        &self.stack.stack[..self.stack.stack_pointer]
+    }
+
+    pub fn delay_timer(&self) -> u8 {
+        self.timers.dt_register
+    }
+
+    pub fn sound_timer(&self) -> u8 {
+        self.timers.st_register
+    }
+
+    // applies queued keypad events up to the current cycle, which step_frame does before each instruction
+    pub fn apply_keys(&mut self) {
+        self.keypad.apply_until(self.cycle);
+    }
+
+    // restarts the ROM and records every keypad change from power-on until stop_movie
+    pub fn start_movie(&mut self) {
+        self.stop_replay();
+        self.reset();
+        self.movie = Some(Movie {
+            rom_sha1: self.rom_sha1.clone(),
+            rom_name: self.rom_name.clone(),
+            seed: self.seed,
+            speed: self.instructions_per_frame,
+            quirks: self.quirks,
+            flags: self.flags,
+            frames: 0,
+            events: Vec::new(),
+        });
+    }
+
+    pub fn stop_movie(&mut self) -> Option<Movie> {
+        let mut movie = self.movie.take()?;
+        movie.frames = self.frame;
+        Some(movie)
+    }
+
+    pub fn is_recording_movie(&self) -> bool {
+        self.movie.is_some()
+    }
+
+    pub fn is_replaying(&self) -> bool {
+        self.replay_end.is_some()
+    }
+
+    // ends a replay early or once it's over, the machine keeps running from where the movie left it with
+    // the settings and flags it had before the movie started
+    pub fn stop_replay(&mut self) {
+        self.replay_end = None;
+        if let Some(live) = self.before_replay.take() {
+            self.keypad.clear();
+            self.quirks = live.quirks;
+            self.instructions_per_frame = live.instructions_per_frame;
+            self.seed = live.seed;
+            self.rng = StdRng::seed_from_u64(live.seed);
+            self.flags = live.flags;
+        }
+    }
+
+    // restarts the ROM with the movie's settings and flags and queues all of its input, which is
+    // applied on exactly the recorded cycles while live input is ignored; a recording has to be stopped first
+    pub fn play_movie(&mut self, movie: &Movie) -> Result<(), MovieError> {
+        if movie.rom_sha1 != self.rom_sha1 {
+            return Err(MovieError::RomMismatch { expected: movie.rom_sha1.clone(), found: self.rom_sha1.clone() });
+        }
+        if self.movie.is_some() {
+            return Err(MovieError::Recording);
+        }
+        self.stop_replay();
+        //the live flags are kept before the movie's replace them
+        self.save_flags()?;
+        self.before_replay = Some(LiveSettings {
+            quirks: self.quirks,
+            instructions_per_frame: self.instructions_per_frame,
+            seed: self.seed,
+            flags: self.flags,
+        });
+        self.quirks = movie.quirks;
+        self.instructions_per_frame = movie.speed;
+        self.seed = movie.seed;
+        self.flags = movie.flags;
+        self.reset();
+        for event in &movie.events {
+            self.keypad.push(event.key, event.pressed, event.cycle);
+        }
+        self.replay_end = Some(movie.frames);
+        Ok(())
+    }
+
+    // TAS mode restarts the ROM paused with a movie recording, then input is logged per frame and can be
+    // edited, rewound and re-recorded from any frame
+    pub fn start_tas(&mut self) {
+        self.start_movie();
+        self.tas = Some(Tas::new(self.save_state()));
+        self.paused = true;
+    }
+
+    // leaves TAS mode where it is, the movie keeps recording
+    pub fn stop_tas(&mut self) -> Option<Tas> {
+        self.tas.take()
+    }
+
+    pub fn is_tas(&self) -> bool {
+        self.tas.is_some()
+    }
+
+    pub fn tas(&self) -> Option<&Tas> {
+        self.tas.as_ref()
+    }
+
+    // emulates one frame with its logged input; past the end of the log live input is recorded if given,
+    // otherwise the last input stays held
+    pub fn tas_advance(&mut self, live: Option<[bool; NUM_KEYS]>) {
+        let Some(mut tas) = self.tas.take() else { return };
+        if let Some(live) = live.filter(|_| self.frame >= tas.len()) {
+            tas.set_input(self.frame, tas::input_bits(live));
+        }
+        self.tas_step(&mut tas);
+        self.capture_frame();
+        self.tas = Some(tas);
+    }
+
+    // rewinds or fast-forwards to the start of frame, dropping movie input recorded after it
+    pub fn tas_seek(&mut self, frame: u64) {
+        let Some(mut tas) = self.tas.take() else { return };
+        let (keyframe, state) = tas.keyframe_before(frame);
+        let state = state.clone();
+        self.load_state(&state);
+        if let Some(movie) = self.movie.as_mut() {
+            movie.events.retain(|event| event.frame < keyframe);
+        }
+        while self.frame < frame {
+            self.tas_step(&mut tas);
+        }
+        self.tas = Some(tas);
+    }
+
+    // flips a key in the input of the frame about to be emulated
+    pub fn tas_toggle_key(&mut self, key: u8) {
+        if let Some(tas) = self.tas.as_mut() {
+            tas.toggle_key(self.frame, key);
+        }
+    }
+
+    // forgets the input from the current frame on, so advancing records new input
+    pub fn tas_truncate(&mut self) {
+        if let Some(tas) = self.tas.as_mut() {
+            tas.truncate(self.frame);
+        }
+    }
+
+    fn tas_step(&mut self, tas: &mut Tas) {
+        let input = tas.input(self.frame);
+        tas.set_input(self.frame, input);
+        self.keypad.set_states(tas::input_states(input), self.cycle);
+        self.step_frame();
+        tas.add_keyframe(self.frame, || self.save_state());
+    }
+
+    fn toggle_movie(&mut self) {
+        let Some(movie) = self.stop_movie() else {
+            self.start_movie();
+            println!("Recording input movie from power-on");
+            return;
+        };
+        let saved = screenshot::timestamped_path(Path::new(movie::MOVIE_DIR), &self.rom_name, movie::MOVIE_EXTENSION)
+            .and_then(|path| movie.save(&path).map(|_| path));
+        match saved {
+            Ok(path) => println!("Saved {} frames of input to {}", movie.frames, path.display()),
+            Err(e) => println!("Failed to save movie: {}", e),
+        }
+    }
+
+    fn replay_latest_movie(&mut self) {
+        //a movie being recorded is saved first, so it's the one that gets replayed
+        if self.is_recording_movie() {
+            self.toggle_movie();
+        }
+        let Some(path) = movie::latest(Path::new(movie::MOVIE_DIR), &self.rom_name) else {
+            println!("No movies saved for {}", self.rom_name);
+            return;
+        };
+        match Movie::load(&path).and_then(|movie| self.play_movie(&movie)) {
+            Ok(()) => println!("Replaying {}", path.display()),
+            Err(e) => println!("Failed to replay {}: {}", path.display(), e),
+        }
+    }
+
+    pub fn save_screenshot(&self, format: ImageFormat) -> io::Result<PathBuf> {
+        screenshot::save(&self.display, Path::new(screenshot::SCREENSHOT_DIR), &self.rom_name, format)
+    }
+
+    // loads a ROM file, Octo source or cartridge, or a program in an archive, named after its file stem;
+    // on error the machine is left as it was
+    pub fn load_rom(&mut self, path: impl AsRef<Path>) -> Result<(), RomError> {
+        let file = rom::load(path.as_ref())?;
+        file.settings.validate().map_err(RomError::Settings)?;
+        self.load_rom_bytes(&file.data, &rom::name(&file.path))?;
+        //the settings the file asks for are only used when the database doesn't know the ROM
+        if self.rom_info.is_none() {
+            file.settings.apply(self).map_err(RomError::Settings)?;
+            self.reset();
+        }
+        Ok(())
+    }
+
+    pub fn load_rom_from_reader(&mut self, reader: impl Read, name: &str) -> Result<(), RomError> {
+        let rom = rom::read(reader)?;
+        self.load_rom_bytes(&rom, name)
+    }
+
+    // the machine restarts with the ROM at 0x200
+    pub fn load_rom_bytes(&mut self, rom: &[u8], name: &str) -> Result<(), RomError> {
+        rom::validate(rom)?;
+        self.stop_replay();
+        if let Err(e) = self.save_flags() {
+            println!("Failed to save flags: {}", e);
+        }
+        self.rom = rom.to_vec();
+        self.rom_name = name.to_string();
+        self.rom_sha1 = metadata::sha1_hex(rom);
+        self.flags = match &self.flags_dir {
+            Some(dir) => flags::load(dir, &self.rom_sha1).unwrap_or_else(|e| {
+                println!("Failed to load flags: {}", e);
+                [0; NUM_FLAGS]
+            }),
+            None => [0; NUM_FLAGS],
+        };
+
+        //ROMs the database knows get the platform, speed and colors they were written for
+        self.rom_info = metadata::Database::current().lookup(&self.rom_sha1);
+        if let Some(info) = &self.rom_info {
+            self.quirks = info.quirks;
+            self.instructions_per_frame = info.speed.max(1);
+            if let Some(palette) = info.palette {
+                self.display.palette = palette;
+            }
+        }
+        self.reset();
+        Ok(())
+    }
+
+    fn hotkey_down(&self, hotkey: Hotkey) -> bool {
+        self.keymap.hotkey_keys(hotkey).iter().any(|&key| self.display.is_key_down(key))
+    }
+
+    //Escape both cancels rebinding and quits by default, so a key held when rebinding ends has to be let go first
+    fn quit_requested(&mut self) -> bool {
+        let down = self.hotkey_down(Hotkey::Quit);
+        self.quit_held &= down;
+        down && !self.quit_held
+    }
+
+    fn hotkey_pressed(&self, hotkey: Hotkey) -> bool {
+        self.keymap.hotkey_keys(hotkey).iter().any(|&key| self.display.is_key_pressed(key))
+    }
+
+    // hotkeys that only touch the machine, shared by the window and the terminal
+    fn handle_machine_hotkey(&mut self, hotkey: Hotkey) {
+        match hotkey {
+            Hotkey::Pause => self.paused = !self.paused,
+            Hotkey::Reset if self.is_tas() => self.tas_seek(0),
+            Hotkey::Reset => self.reset(),
+            Hotkey::SaveState => self.quick_save = Some(self.save_state()),
+            //in TAS mode a quick load seeks, so the movie and input log stay in step with the machine
+            Hotkey::LoadState if self.is_tas() => {
+                if let Some(frame) = self.quick_save.as_ref().map(|state| state.frame) {
+                    self.tas_seek(frame);
+                }
+            }
+            Hotkey::LoadState => {
+                if let Some(state) = self.quick_save.take() {
+                    self.load_state(&state);
+                    self.quick_save = Some(state);
+                }
+            }
+            Hotkey::Tas if self.is_tas() => {
+                self.stop_tas();
+                self.paused = false;
+            }
+            Hotkey::Tas => self.start_tas(),
+            Hotkey::FrameAdvance if self.is_tas() => {
+                self.paused = true;
+                self.tas_advance(None);
+            }
+            Hotkey::FrameBack if self.is_tas() => {
+                self.paused = true;
+                self.tas_seek(self.frame.saturating_sub(1));
+            }
+            Hotkey::TruncateInput => self.tas_truncate(),
+            _ => {}
+        }
+    }
+
+    fn handle_hotkeys(&mut self) {
+        if self.rebinding.is_some() {
+            if let Some(&key) = self.display.get_keys_pressed().first() {
+                self.rebind_key(key);
+                self.quit_held = self.rebinding.is_none();
+            }
+            self.update_title();
+            return;
+        }
+
+        for hotkey in [
+            Hotkey::Pause,
+            Hotkey::Reset,
+            Hotkey::SaveState,
+            Hotkey::LoadState,
+            Hotkey::Tas,
+            Hotkey::FrameAdvance,
+            Hotkey::FrameBack,
+            Hotkey::TruncateInput,
+        ] {
+            if self.hotkey_pressed(hotkey) {
+                self.handle_machine_hotkey(hotkey);
+            }
+        }
+        if self.hotkey_pressed(Hotkey::Rebind) {
+            self.start_rebinding();
+        }
+        if self.hotkey_pressed(Hotkey::CycleFilter) {
+            self.display.cycle_filter();
+        }
+        if self.hotkey_pressed(Hotkey::IntegerScale) {
+            self.display.toggle_integer_scale();
+        }
+        if self.hotkey_pressed(Hotkey::Fullscreen) {
+            self.display.toggle_fullscreen();
+        }
+        let shift = self.display.is_key_down(Key::LeftShift) || self.display.is_key_down(Key::RightShift);
+        if self.hotkey_pressed(Hotkey::Movie) {
+            if shift {
+                self.replay_latest_movie();
+            } else {
+                self.toggle_movie();
+            }
+        }
+        if self.hotkey_pressed(Hotkey::Record) {
+            self.toggle_recording(if shift { VideoFormat::Y4m } else { VideoFormat::Gif });
+        }
+        if self.hotkey_pressed(Hotkey::Screenshot) {
+            //shift takes the raw 1-bit frame instead of what the window shows
+            let format = if shift {
+                ImageFormat::Pbm
+            } else {
+                ImageFormat::Png
+            };
+            match self.save_screenshot(format) {
+                Ok(path) => println!("Saved screenshot to {}", path.display()),
+                Err(e) => println!("Failed to save screenshot: {}", e),
+            }
+        }
+        self.update_title();
+    }
+
+    pub fn start_rebinding(&mut self) {
+        self.keypad_before_rebind = self.keymap.keypad.clone();
+        self.rebinding = Some(0);
+    }
+
+    pub fn is_rebinding(&self) -> bool {
+        self.rebinding.is_some()
+    }
+
+    // walks through KEYPAD_LAYOUT taking one host key per CHIP-8 key, Backspace keeps the current
+    // binding and Escape cancels and puts back the old bindings, when done the keypad is saved as the global binding
+    pub fn rebind_key(&mut self, key: Key) {
+        let Some(position) = self.rebinding else { return };
+
+        match key {
+            Key::Escape => {
+                self.keymap.keypad = std::mem::take(&mut self.keypad_before_rebind);
+                self.rebinding = None;
+                return;
+            }
+            Key::Backspace => {}
+            _ => self.keymap.keypad[KEYPAD_LAYOUT[position]] = vec![key],
+        }
+
+        if position + 1 < KEYPAD_LAYOUT.len() {
+            self.rebinding = Some(position + 1);
+            return;
+        }
+
+        self.rebinding = None;
+        let Some(path) = self.keymap_path.clone().or_else(KeymapFile::default_path) else { return };
+        let saved = KeymapFile::load(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|mut file| {
+                file.set_keypad(&self.keymap);
+                file.save(&path).map_err(|e| e.to_string())
+            });
+        match saved {
+            Ok(()) => println!("Saved key bindings to {}", path.display()),
+            Err(e) => println!("Failed to save key bindings: {}", e),
+        }
+    }
+
+    fn update_title(&mut self) {
+        let title = match self.rebinding {
+            Some(position) => {
+                let chip8_key = KEYPAD_LAYOUT[position];
+                let current = self.keymap.keypad[chip8_key].iter().map(|&key| keymap::key_name(key)).collect::<Vec<_>>().join("/");
+                format!("Press a key for CHIP-8 key {:X} (now {}), Backspace keeps it, Esc cancels", chip8_key, current)
+            }
+            None => format!("CHIP-8 - {} {}", self.status(), self.display.filter.name()),
+        };
+        self.display.set_title(&title);
+    }
+
+    // ROM name and emulation state, shared by the window title and the terminal status line
+    fn status(&self) -> String {
+        let rom_name = match &self.rom_info {
+            Some(info) => &info.title,
+            None if self.rom_name.is_empty() => "No ROM",
+            None => &self.rom_name,
+        };
+        let mut status = match self.fault {
+            Some(fault) => format!("Stopped on a {} at {:03X}", fault, self.pc),
+            None => String::from(if self.paused { "Paused" } else { "Running" }),
+        };
+        if let Some(tas) = self.tas() {
+            status.push_str(&format!(", TAS frame {}/{} input {}", self.frame, tas.len(), tas::input_name(tas.input(self.frame))));
+        }
+        if self.is_recording() {
+            status.push_str(", REC");
+        }
+        if self.is_recording_movie() {
+            status.push_str(", recording input");
+        }
+        if self.is_replaying() {
+            status.push_str(", replaying input");
+        }
+        format!("{} [{}]", rom_name, status)
+    }
+
     fn load_fonts(&mut self) {
         let fonts: [u8; 80] = [0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
                                 0x20, 0x60, 0x20, 0x20, 0x70, // 1
@@ -94,148 +1133,175 @@ impl Chip8 {
         self.memory[0x050..0x0A0].copy_from_slice(&fonts);
     }
 
-    fn execute_opcode(&mut self, opcode: u16) {
-        let vx = (opcode & 0x0F00) >> 8;
-        let vy = (opcode & 0x00F0) >> 4;
-        let n = opcode & 0x000F;
-        let nn = (opcode & 0x00FF) as u8;
-        let address = opcode & 0x0FFF;
-        
-        match opcode & 0xF000 {
-            0x0000 => {
-                match opcode {
-                    0x00E0 => self.display.clear(), // clear display
-                    0x00EE => self.pc = self.stack.pop(), // return from subroutine
-                    _ => {},
+    // runs instruction as if it had just been fetched, so pc is already past it
+    pub fn execute(&mut self, instruction: Instruction) {
+        let v = &mut self.variable_registers;
+        match instruction {
+            Instruction::Clear => self.display.clear(), // clear display
+            Instruction::Return => match self.stack.pop() { // return from subroutine
+                Ok(address) => self.pc = address,
+                Err(e) => self.stop(e),
+            }
+            Instruction::Jump(address) => self.pc = address, // set pc to NNN
+            Instruction::Call(address) => match self.stack.push(self.pc) { // call subroutine at NNN
+                Ok(()) => self.pc = address,
+                Err(e) => self.stop(e),
+            }
+            Instruction::SkipIfEqual(x, nn) => if v[x as usize] == nn { self.skip() }, // skips next instruction if VX == NN
+            Instruction::SkipIfNotEqual(x, nn) => if v[x as usize] != nn { self.skip() }, // skips next instruction if VX != NN
+            Instruction::SkipIfRegistersEqual(x, y) => if v[x as usize] == v[y as usize] { self.skip() }, // skips next instruction if VX == VY
+            Instruction::Load(x, nn) => v[x as usize] = nn, // sets VX to NN
+            Instruction::Add(x, nn) => v[x as usize] = v[x as usize].wrapping_add(nn), // adds NN to VX
+            Instruction::Copy(x, y) => v[x as usize] = v[y as usize], // sets VX to value of VY
+            Instruction::Or(x, y) => { // sets VX to VX OR VY
+                v[x as usize] |= v[y as usize];
+                if self.quirks.logic { v[15] = 0; }
+            }
+            Instruction::And(x, y) => { // sets VX to VX AND VY
+                v[x as usize] &= v[y as usize];
+                if self.quirks.logic { v[15] = 0; }
+            }
+            Instruction::Xor(x, y) => { // sets VX to VX XOR VY
+                v[x as usize] ^= v[y as usize];
+                if self.quirks.logic { v[15] = 0; }
+            }
+            Instruction::AddRegisters(x, y) => { // adds VY to VX. Makes VF 1 or 0 based on if it overflows or doesn't, respectively
+                let (sum, carry) = v[x as usize].overflowing_add(v[y as usize]);
+                v[x as usize] = sum;
+                v[15] = carry as u8; //flag goes last so it wins when X is F
+            }
+            Instruction::Subtract(x, y) => { // subtracts VY from VX. Makes VF 0 or 1 based on if it underflows or doesn't, respectively
+                let (difference, borrow) = v[x as usize].overflowing_sub(v[y as usize]);
+                v[x as usize] = difference;
+                v[15] = !borrow as u8;
+            }
+            Instruction::ShiftRight(x, y) => { // shifts VX right (VY without the shift quirk), stores least significant bit in VF
+                if !self.quirks.shift { v[x as usize] = v[y as usize]; }
+                let lsb = v[x as usize] & 1;
+                v[x as usize] >>= 1;
+                v[15] = lsb;
+            }
+            Instruction::SubtractFrom(x, y) => { // sets VX to VY - VX. Makes VF 0 or 1 based on if it underflows or doesn't, respectively
+                let (difference, borrow) = v[y as usize].overflowing_sub(v[x as usize]);
+                v[x as usize] = difference;
+                v[15] = !borrow as u8;
+            }
+            Instruction::ShiftLeft(x, y) => { // shifts VX to left (VY without the shift quirk), stores most significant bit in VF
+                if !self.quirks.shift { v[x as usize] = v[y as usize]; }
+                let msb = v[x as usize] >> 7;
+                v[x as usize] <<= 1;
+                v[15] = msb;
+            }
+            Instruction::SkipIfRegistersNotEqual(x, y) => if v[x as usize] != v[y as usize] { self.skip() }, // skips next instruction if VX != VY
+            Instruction::SetIndex(address) => self.i_register = address, // sets index register to address
+            Instruction::JumpWithOffset(address, x) => { //jumps to address NNN + V0, or XNN + VX with the jump quirk
+                let offset_register = if self.quirks.jump { x as usize } else { 0 };
+                self.pc = (address + v[offset_register] as u16) & 0x0FFF;
+            }
+            Instruction::Random(x, nn) => {
+                let random_num: u8 = self.rng.random();
+                v[x as usize] = nn & random_num;
+            }
+            Instruction::Draw(x, y, n) => { //drawing sprite on display
+                let x_cord = v[x as usize] % 64;
+                let y_cord = v[y as usize] % 32;
+                v[15] = 0;
+                self.draw_sprite_to_display(n as u16, x_cord, y_cord);
+            }
+            Instruction::SkipIfKey(x) => if self.keypad.is_pressed(v[x as usize]) { self.skip() }, //if key in VX (lowest nibble) currently held down, skip next instruction
+            Instruction::SkipIfNotKey(x) => if !self.keypad.is_pressed(v[x as usize]) { self.skip() }, //if key in VX (lowest nibble) not held down, skip next instruction
+            Instruction::ReadDelay(x) => v[x as usize] = self.timers.dt_register, // sets VX to value of delay timer
+            Instruction::WaitForKey(x) => { // waits for a key to be pressed and released, then stores it in VX
+                match self.keypad.wait_for_key() {
+                    Some(key) => v[x as usize] = key,
+                    None => self.pc = self.pc.wrapping_sub(2) & 0x0FFF,
                 }
             }
-            0x1000 => self.pc = address,  // set pc to NNN
-            0x2000 => { // call subroutine at NNN 
-                self.stack.push(self.pc);
-                self.pc = address;
-            }
-            0x3000 => if self.variable_registers[vx as usize] == nn {self.pc += 2}, // skips next instruction if VX == NN
-            0x4000 => if self.variable_registers[vx as usize] != nn {self.pc += 2}, // skips next instruction if VX != NN
-            0x5000 => if self.variable_registers[vx as usize] == self.variable_registers[vy as usize] {self.pc += 2}, // skips next instruction if VX == VY
-            0x6000 => self.variable_registers[vx as usize] = nn, // sets VX to NN
-            0x7000 => self.variable_registers[vx as usize] = self.variable_registers[vx as usize].wrapping_add(nn), // adds NN to VX
-            0x8000 => {
-                match n {
-                    0x0000 => self.variable_registers[vx as usize] = self.variable_registers[vy as usize], // sets VX to value of VY
-                    0x0001 => self.variable_registers[vx as usize] |= self.variable_registers[vy as usize], // sets VX to VX OR VY
-                    0x0002 => self.variable_registers[vx as usize] &= self.variable_registers[vy as usize], // sets VX to VX AND VY
-                    0x0003 => self.variable_registers[vx as usize] ^= self.variable_registers[vy as usize], // sets VX to VX XOR VY
-                    0x0004 => { // adds VY to VX. Makes VF 1 or 0 based on if it overflows or doesn't, respectively
-                        if (self.variable_registers[vx as usize] as u16) + (self.variable_registers[vy as usize] as u16) > 255 {
-                            self.variable_registers[15] = 1;
-                        } else {
-                            self.variable_registers[15] = 0;
-                        }
-                        self.variable_registers[vx as usize] = self.variable_registers[vx as usize].wrapping_add(self.variable_registers[vy as usize]);
-                    } 
-                    0x0005 => { // subtracts VY from VX. Makes VF 0 or 1 based on if it underflows or doesn't, respectively
-                        if self.variable_registers[vx as usize] < self.variable_registers[vy as usize] {
-                            self.variable_registers[15] = 0;
-                        } else {
-                            self.variable_registers[15] = 1;
-                        }
-                        self.variable_registers[vx as usize] = self.variable_registers[vx as usize].wrapping_sub(self.variable_registers[vy as usize]);
-                    }
-                    0x0006 => { // shifts VX right, stores least significant bit in VF
-                        let lsb = self.variable_registers[vx as usize] & 1;
-                        self.variable_registers[vx as usize] = self.variable_registers[vx as usize] >> 1;
-                        self.variable_registers[15] = lsb;
-                    }
-                    0x0007 => { // sets VX to VY - VX. Makes VF 0 or 1 based on if it underflows or doesn't, respectively
-                        if self.variable_registers[vy as usize] < self.variable_registers[vx as usize] {
-                            self.variable_registers[15] = 0;
-                        } else {
-                            self.variable_registers[15] = 1;
-                        }
-                        self.variable_registers[vx as usize] = self.variable_registers[vy as usize].wrapping_sub(self.variable_registers[vx as usize]);
-                    }
-                    0x000E => { // shifts VX to left, stores most significant bit in VF
-                        let msb = self.variable_registers[vx as usize] >> 7;
-                        self.variable_registers[vx as usize] = self.variable_registers[vx as usize] << 1;
-                        self.variable_registers[15] = msb;
-                    }
-                    _ => ()
+            Instruction::SetDelay(x) => self.timers.dt_register = v[x as usize], // sets delay timer to VX
+            Instruction::SetSound(x) => self.timers.st_register = v[x as usize], // sets sound timer to VX
+            Instruction::AddToIndex(x) => self.i_register = self.i_register.wrapping_add(v[x as usize] as u16), // adds VX to I
+            Instruction::Character(x) => self.point_i_to_character(x as u16), // index register set to address of character in VX
+            Instruction::Decimal(x) => { // stores digits of decimal conversion of value in VX in i, i + 1, i + 2 in mem,
+                let mut num = v[x as usize];
+                for i in (0..=2).rev() {
+                    self.write_byte(self.i_register.wrapping_add(i), num % 10);
+                    num /= 10;
                 }
             }
-            0xA000 => self.i_register = address, // sets index register to address
-            0xB000 => self.pc = address + (self.variable_registers[0] as u16), //jumps to address NNN + V0
-            0xC000 => {
-                let mut rng = rand::rng();
-                let random_num: u8 = rng.random();
-                self.variable_registers[vx as usize] = nn & random_num;
-            }
-            0xD000 => { //drawing sprite on display
-                let x_cord = self.variable_registers[vx as usize] % 64;
-                let y_cord = self.variable_registers[vy as usize] % 32;
-                self.variable_registers[15] = 0;
-                self.draw_sprite_to_display(n, x_cord, y_cord);
-            }
-            0xE000 => {
-                let key = self.variable_registers[vx as usize];
-                let key_pressed = self.key_states[key as usize];
-                match opcode & 0x00FF {
-                    0x009E => if key_pressed {self.pc += 2}, //if key in VX (lowest nibble) currently held down, skip next instruction
-                    0x00A1 => if !key_pressed {self.pc += 2} //if key in VX (lowest nibble) not held down, skip next instruction
-                    _ => {}
+            Instruction::StoreRegisters(x) => { // stores registers into memory up till VX
+                for i in 0..=x as u16 {
+                    self.write_byte(self.i_register.wrapping_add(i), self.variable_registers[i as usize]);
                 }
+                self.advance_i_after_memory_op(x as u16);
             }
-            0xF000 => {
-                match opcode & 0x00FF {
-                    0x0007 => self.variable_registers[vx as usize] = self.timers.dt_register, // sets VX to value of delay timer
-                    0x000A => { // key press awaited then stored in VX
-                        let mut key_pressed = false;
-                        let mut key = 0;
-                        for i in 0..NUM_KEYS {
-                            if self.key_states[i] {
-                                key_pressed = true;
-                                key = i;
-                                break;
-                            }
-                        }
-                        if key_pressed {
-                            self.variable_registers[vx as usize] = key as u8;
-                        } else {
-                            self.pc -= 2;
-                        }
-                    }
-                    0x0015 => self.timers.dt_register = self.variable_registers[vx as usize], // sets delay timer to VX
-                    0x0018 => self.timers.st_register = self.variable_registers[vx as usize], // sets sound timer to VX
-                    0x001E => self.i_register = self.i_register.wrapping_add(self.variable_registers[vx as usize] as u16), // adds VX to I
-                    0x0029 => self.point_i_to_character(vx), // index register set to address of character in VX
-                    0x0033 => { // stores digits of decimal conversion of value in VX in i, i + 1, i + 2 in mem, 
-                        let mut num = self.variable_registers[vx as usize];
-                        for i in (0..=2).rev() {
-                            self.memory[self.i_register as usize + i] = num % 10;
-                            num /= 10;
-                        }
-                    }
-                    0x0055 => { // stores registers into memory up till VX
-                        for i in 0..=vx {
-                            self.memory[(self.i_register + i) as usize] = self.variable_registers[i as usize];
-                        }
-                    }
-                    0x0065 => { // loads registers from memory up till VX
-                        for i in 0..=vx {
-                            self.variable_registers[i as usize] = self.memory[(self.i_register + i) as usize];
-                        }   
-                    }
-                    _ => {}
+            Instruction::LoadRegisters(x) => { // loads registers from memory up till VX
+                for i in 0..=x as u16 {
+                    v[i as usize] = self.memory[self.i_register.wrapping_add(i) as usize % NUM_ADRESSES];
                 }
+                self.advance_i_after_memory_op(x as u16);
             }
-            _ => {}
+            Instruction::SaveFlags(x) => { // stores registers into the user flags up till VX
+                self.flags[..=x as usize].copy_from_slice(&v[..=x as usize]);
+                //a replay's flags came from the movie, so they never go back to the file
+                self.flags_dirty |= !self.is_replaying();
+            }
+            Instruction::LoadFlags(x) => { // loads registers from the user flags up till VX
+                v[..=x as usize].copy_from_slice(&self.flags[..=x as usize]);
+            }
+            Instruction::Unknown(_) => {}
         }
     }
 
-    fn fetch_opcode(&mut self) -> u16 {
-        let high_byte = self.memory[self.pc as usize];
-        let low_byte = self.memory[(self.pc + 1) as usize];
-        self.pc += 2;
-        ((high_byte as u16) << 8) | (low_byte as u16)
+    // memory writes drop the decoded instructions that overlap the byte, so self-modifying code runs as written
+    fn write_byte(&mut self, address: u16, value: u8) {
+        let address = address as usize % NUM_ADRESSES;
+        self.memory[address] = value;
+        self.decoded[address] = None;
+        self.decoded[(address + NUM_ADRESSES - 1) % NUM_ADRESSES] = None;
+        #[cfg(feature = "jit")]
+        if let Some(jit) = self.jit.as_mut() {
+            jit.invalidate(address as u16);
+        }
+    }
+
+    fn advance_i_after_memory_op(&mut self, vx: u16) {
+        if self.quirks.memory_leave_i_unchanged {
+            return;
+        }
+        let increment = if self.quirks.memory_increment_by_x { vx } else { vx + 1 };
+        self.i_register = self.i_register.wrapping_add(increment);
+    }
+
+    //addresses past the end of memory wrap around to 0, so pc always stays within it
+    fn fetch(&mut self) -> Instruction {
+        let pc = self.pc as usize;
+        let instruction = match self.decoded[pc] {
+            Some(instruction) => instruction,
+            None => {
+                let instruction = Instruction::decode(self.opcode_at(self.pc));
+                if self.cache_instructions {
+                    self.decoded[pc] = Some(instruction);
+                }
+                instruction
+            }
+        };
+        self.pc = (self.pc + 2) & 0x0FFF;
+        instruction
+    }
+
+    fn opcode_at(&self, address: u16) -> u16 {
+        let address = address as usize % NUM_ADRESSES;
+        u16::from_be_bytes([self.memory[address], self.memory[(address + 1) % NUM_ADRESSES]])
+    }
+
+    fn skip(&mut self) {
+        self.pc = (self.pc + 2) & 0x0FFF;
+    }
+
+    // leaves pc on the faulting instruction
+    fn stop(&mut self, fault: StackError) {
+        self.pc = self.pc.wrapping_sub(2) & 0x0FFF;
+        self.fault = Some(fault);
     }
 
     fn point_i_to_character(&mut self, vx: u16) {
@@ -262,16 +1328,22 @@ impl Chip8 {
 
     fn draw_sprite_to_display(&mut self, n: u16, x_cord: u8, y_cord: u8) {
         let mut x = x_cord as usize;
-        let mut y = y_cord as usize;
 
         for row in 0..n {
+            let mut y = y_cord as usize + row as usize;
             if y >= 32 {
-                break;
+                if !self.quirks.wrap {
+                    break;
+                }
+                y %= 32;
             }
-            let sprite_byte = self.memory[(self.i_register + row) as usize];
+            let sprite_byte = self.memory[self.i_register.wrapping_add(row) as usize % NUM_ADRESSES];
             for i in 0..8 {
                 if x >= 64 {
-                    break;
+                    if !self.quirks.wrap {
+                        break;
+                    }
+                    x %= 64;
                 }
                 let pixel_bit = (sprite_byte >> (7 - i)) & 1;
                 if pixel_bit == 1 && self.display.frame_buffer[y][x] {
@@ -283,32 +1355,39 @@ impl Chip8 {
                 x += 1;
             }
             x = x_cord as usize;
-            y += 1;
         }
     }
 
+    // turns the window's host key transitions into keypad events. They arrive once per frame with the time
+    // the window saw them, and each lands as far into the next frame's cycles as it was into the last
+    // frame's wall time, so taps and quick sequences keep their spacing
     fn update_keys(&mut self) {
-        self.key_states = [false; 16];
-        let keys = self.display.window.get_keys();
-        for key in keys {
-            match key {
-                Key::Key1 => self.key_states[0x1] = true,
-                Key::Key2 => self.key_states[0x2] = true,
-                Key::Key3 => self.key_states[0x3] = true,
-                Key::Key4 => self.key_states[0xC] = true,
-                Key::Q    => self.key_states[0x4] = true,
-                Key::W    => self.key_states[0x5] = true,
-                Key::E    => self.key_states[0x6] = true,
-                Key::R    => self.key_states[0xD] = true,
-                Key::A    => self.key_states[0x7] = true,
-                Key::S    => self.key_states[0x8] = true,
-                Key::D    => self.key_states[0x9] = true,
-                Key::F    => self.key_states[0xE] = true,
-                Key::Z    => self.key_states[0xA] = true,
-                Key::X    => self.key_states[0x0] = true,
-                Key::C    => self.key_states[0xB] = true,
-                Key::V    => self.key_states[0xF] = true,
-                _ => {}
+        let frame_start = std::mem::replace(&mut self.keys_polled, Instant::now());
+        let frame_length = 1.0 / self.frame_rate;
+        let last_offset = self.instructions_per_frame.max(1) as u64 - 1;
+        for (key, down, time) in self.display.take_key_events() {
+            self.host_keys_down.retain(|&held| held != key);
+            if down {
+                self.host_keys_down.push(key);
+            }
+
+            if self.is_replaying() {
+                continue;
+            }
+            //in TAS mode pressing a keypad key while paused edits the input of the next frame
+            if self.is_tas() {
+                if let Some(chip8_key) = self.keymap.keypad_index(key).filter(|_| down && self.paused) {
+                    self.tas_toggle_key(chip8_key as u8);
+                }
+                continue;
+            }
+            let into_frame = time.saturating_duration_since(frame_start).as_secs_f64() / frame_length;
+            let offset = ((into_frame * self.instructions_per_frame as f64) as u64).min(last_offset);
+            let states = self.keymap.keypad_states(&self.host_keys_down);
+            for (chip8_key, &pressed) in states.iter().enumerate() {
+                if self.keymap.keypad[chip8_key].contains(&key) && self.keypad.queued_state(chip8_key as u8) != pressed {
+                    self.keypad.push(chip8_key as u8, pressed, self.cycle + offset);
+                }
             }
         }
     }
@@ -0,0 +1,455 @@
+// Command-line interface. Without arguments the binary shows the interactive game menu, with a ROM path
+// it boots that ROM, and the subcommands cover disassembly, assembly, ROM info, headless checks and
+// recompiling to Rust.
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use rust_chip8_emulator::asm;
+use rust_chip8_emulator::chip8::Chip8;
+use rust_chip8_emulator::disasm;
+use rust_chip8_emulator::config::{ConfigFile, Settings};
+use rust_chip8_emulator::display::{Display, DEFAULT_SCALE};
+use rust_chip8_emulator::flags;
+use rust_chip8_emulator::keymap::{Keymap, KeymapFile};
+use rust_chip8_emulator::metadata::{self, Database};
+use rust_chip8_emulator::movie::Movie;
+use rust_chip8_emulator::recompiler;
+use rust_chip8_emulator::rom::{self, RomFile};
+use rust_chip8_emulator::screenshot::ImageFormat;
+use rust_chip8_emulator::terminal::{Terminal, TerminalMode};
+use rust_chip8_emulator::testing::{self, Comparison, TestRun};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::Instant;
+
+#[derive(Debug, Parser)]
+#[command(version, about = "A CHIP-8 emulator. Run without arguments for the game menu.", args_conflicts_with_subcommands = true)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    #[command(flatten)]
+    pub run: RunArgs,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    #[command(about = "Run a ROM in a window, in the terminal or headless (the default when given a ROM)")]
+    Run(RunArgs),
+    #[command(about = "Print a ROM as assembly that asm accepts")]
+    Disasm {
+        rom: PathBuf,
+    },
+    #[command(about = "Assemble a source file into a ROM")]
+    Asm {
+        source: PathBuf,
+        #[arg(short, long, help = "Where to write the ROM [default: the source with a .ch8 extension]")]
+        output: Option<PathBuf>,
+    },
+    #[command(about = "Show a ROM's SHA-1 and what the ROM database knows about it")]
+    Info {
+        rom: PathBuf,
+        #[arg(long, value_name = "DIR", help = "chip-8-database directory to use instead of the bundled copy")]
+        database: Option<PathBuf>,
+    },
+    #[command(about = "Run a ROM headless and check the hash of the final screen")]
+    Test(TestArgs),
+    #[command(about = "Run a ROM headless as fast as it goes, with and without the instruction cache")]
+    Bench(BenchArgs),
+    #[command(about = "Translate a ROM into a Rust module with a function per basic block")]
+    Recompile(RecompileArgs),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum TerminalArg {
+    Halfblocks,
+    Braille,
+}
+
+// settings that change how the machine runs, shared by run and test
+#[derive(Debug, Args)]
+pub struct MachineArgs {
+    #[arg(long, value_name = "PRESET", help = "Quirk preset: default, chip8, schip or xochip")]
+    pub platform: Option<String>,
+    #[arg(long, value_name = "QUIRKS", help = "Quirk overrides, like \"shift=0 vblank=1\"")]
+    pub quirks: Option<String>,
+    #[arg(long, help = "Instructions per second")]
+    pub ips: Option<usize>,
+    #[arg(long, help = "Seed for the CXNN random number generator [default: random, 0 for test]")]
+    pub seed: Option<u64>,
+    #[arg(long, value_name = "FILE", help = "Settings file [default: config.toml in the config directory]")]
+    pub config: Option<PathBuf>,
+    #[arg(long, conflicts_with = "config", help = "Ignore the settings file")]
+    pub no_config: bool,
+    #[arg(long, value_name = "DIR", help = "chip-8-database directory to use instead of the bundled copy")]
+    pub database: Option<PathBuf>,
+    #[cfg(feature = "jit")]
+    #[arg(long, help = "Compile the ROM's code to native code as it runs")]
+    pub jit: bool,
+}
+
+impl MachineArgs {
+    fn settings(&self) -> Settings {
+        Settings {
+            platform: self.platform.clone(),
+            quirks: self.quirks.clone(),
+            ips: self.ips,
+            seed: self.seed,
+            ..Settings::default()
+        }
+    }
+
+    // also installs the ROM database the options or the file ask for, so call it before reading the ROM
+    fn config(&self) -> Result<ConfigFile, String> {
+        let config = if self.no_config {
+            ConfigFile::default()
+        } else {
+            match self.config.clone().or_else(ConfigFile::default_path) {
+                Some(path) => ConfigFile::load(&path).map_err(|e| format!("{}: {}", path.display(), e))?,
+                None => ConfigFile::default(),
+            }
+        };
+        use_database(self.database.as_deref().or(config.database.as_deref()))?;
+        Ok(config)
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct RunArgs {
+    #[arg(help = "ROM to boot")]
+    pub rom: Option<PathBuf>,
+    #[command(flatten)]
+    pub machine: MachineArgs,
+    #[arg(long, help = "Window pixels per CHIP-8 pixel")]
+    pub scale: Option<usize>,
+    #[arg(long, help = "Frames per second, 60 on real hardware")]
+    pub frame_rate: Option<f64>,
+    #[arg(long, value_name = "BG,FG", help = "Colors as hex RGB, like 000000,33ff66")]
+    pub palette: Option<String>,
+    #[arg(long, value_name = "FILE", help = "Key bindings file [default: keymap.toml in the config directory]")]
+    pub keymap: Option<PathBuf>,
+    #[arg(long, value_name = "MODE", num_args = 0..=1, default_missing_value = "halfblocks", help = "Play in this terminal")]
+    pub terminal: Option<TerminalArg>,
+    #[arg(long, value_name = "MOVIE", help = "Play back an input movie")]
+    pub replay: Option<PathBuf>,
+    #[arg(long, help = "Run without a window for --frames frames")]
+    pub headless: bool,
+    #[arg(long, default_value_t = 600, help = "Frames to run headless")]
+    pub frames: u64,
+    #[arg(long, value_name = "FRAMES", value_delimiter = ',', help = "Save a screenshot after these frames when headless")]
+    pub screenshot_at: Vec<u64>,
+    #[arg(long, help = "Save headless screenshots as PBM instead of PNG")]
+    pub pbm: bool,
+    #[arg(long, help = "Clear the user flags (FX75/FX85) the ROM saved in earlier runs, like high scores")]
+    pub reset_flags: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct TestArgs {
+    pub rom: PathBuf,
+    #[command(flatten)]
+    pub machine: MachineArgs,
+    #[arg(long, default_value_t = 600, help = "Frames to run")]
+    pub frames: u64,
+    #[arg(long, help = "Stop early once the program jumps to its own address")]
+    pub until_halt: bool,
+    #[arg(long, value_name = "MOVIE", help = "Input movie to feed in")]
+    pub movie: Option<PathBuf>,
+    #[arg(long, value_name = "SCRIPT", help = "Key presses to feed in, like \"60:5+ 90:5-\" to hold 5 from frame 60 to 90")]
+    pub keys: Option<String>,
+    #[arg(long, value_name = "SHA1", help = "Expected SHA-1 of the final screen, printed when left out")]
+    pub expect: Option<String>,
+    #[arg(long, value_name = "FILE", help = "Snapshot of the final screen to compare with, .txt or .png")]
+    pub snapshot: Option<PathBuf>,
+    #[arg(long, requires = "snapshot", help = "Write the snapshot instead of comparing with it")]
+    pub update: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct BenchArgs {
+    pub rom: PathBuf,
+    #[command(flatten)]
+    pub machine: MachineArgs,
+    #[arg(long, default_value_t = 10_000, help = "Frames to run, at --ips (60000 unless given) instructions per second")]
+    pub frames: u64,
+}
+
+#[derive(Debug, Args)]
+pub struct RecompileArgs {
+    pub rom: PathBuf,
+    #[arg(short, long, help = "Where to write the module [default: print it]")]
+    pub output: Option<PathBuf>,
+    #[command(flatten)]
+    pub machine: MachineArgs,
+}
+
+pub fn execute(command: Command) -> Result<ExitCode, String> {
+    match command {
+        Command::Run(args) => run(args),
+        Command::Disasm { rom } => {
+            let rom = read_rom(&rom)?;
+            print!("{}", disasm::listing(&rom.data, 0x200));
+            Ok(ExitCode::SUCCESS)
+        }
+        Command::Asm { source, output } => {
+            let text = fs::read_to_string(&source).map_err(|e| format!("{}: {}", source.display(), e))?;
+            let rom = asm::assemble(&text).map_err(|e| format!("{}: {}", source.display(), e))?;
+            let output = output.unwrap_or_else(|| source.with_extension("ch8"));
+            fs::write(&output, &rom).map_err(|e| format!("{}: {}", output.display(), e))?;
+            println!("Wrote {} bytes to {}", rom.len(), output.display());
+            Ok(ExitCode::SUCCESS)
+        }
+        Command::Info { rom, database } => {
+            use_database(database.as_deref())?;
+            info(&read_rom(&rom)?.data);
+            Ok(ExitCode::SUCCESS)
+        }
+        Command::Test(args) => test(args),
+        Command::Bench(args) => bench(args),
+        Command::Recompile(args) => recompile(args),
+    }
+}
+
+fn run(args: RunArgs) -> Result<ExitCode, String> {
+    let Some(path) = &args.rom else { return Err(String::from("no ROM given")) };
+    let rom = read_rom(path)?;
+    let mut settings = rom_settings(&args.machine.config()?, &rom);
+    settings.merge(&Settings {
+        frame_rate: args.frame_rate,
+        scale: args.scale,
+        palette: args.palette.clone(),
+        keymap: args.keymap.clone(),
+        ..args.machine.settings()
+    });
+    settings.validate()?;
+
+    let scale = settings.scale.unwrap_or(DEFAULT_SCALE);
+    let display = if args.headless || args.terminal.is_some() { Display::headless(scale) } else { Display::new(scale) };
+    let mut chip8 = Chip8::new(display);
+    if args.reset_flags {
+        reset_flags(&rom)?;
+    }
+    if args.headless {
+        chip8.set_flags_dir(None);
+    }
+    boot(&mut chip8, &rom, &settings)?;
+    #[cfg(feature = "jit")]
+    chip8.set_jit(args.machine.jit)?;
+    let (keymap, keymap_path) = load_keymap(settings.keymap.as_deref(), chip8.rom_sha1())?;
+    chip8.set_keymap(keymap, keymap_path);
+    if let Some(path) = &args.replay {
+        let movie = Movie::load(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        chip8.play_movie(&movie).map_err(|e| format!("{}: {}", path.display(), e))?;
+    }
+
+    if args.headless {
+        let format = if args.pbm { ImageFormat::Pbm } else { ImageFormat::Png };
+        let saved = chip8.run_headless(args.frames, &args.screenshot_at, format).map_err(|e| e.to_string())?;
+        for path in saved {
+            println!("Saved screenshot to {}", path.display());
+        }
+        println!("Ran {} frames, screen {}", chip8.frame(), screen_hash(&chip8));
+    } else if let Some(terminal) = args.terminal {
+        let mode = match terminal {
+            TerminalArg::Halfblocks => TerminalMode::HalfBlocks,
+            TerminalArg::Braille => TerminalMode::Braille,
+        };
+        Terminal::new(mode)
+            .and_then(|mut terminal| chip8.run_in_terminal(&mut terminal))
+            .map_err(|e| format!("terminal error: {}", e))?;
+    } else {
+        chip8.run();
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn test(args: TestArgs) -> Result<ExitCode, String> {
+    let rom = read_rom(&args.rom)?;
+    let mut settings = rom_settings(&args.machine.config()?, &rom);
+    settings.merge(&args.machine.settings());
+    //the same ROM and input give the same screen every time unless another seed is asked for
+    settings.seed.get_or_insert(0);
+    let mut chip8 = Chip8::headless();
+    boot(&mut chip8, &rom, &settings)?;
+    #[cfg(feature = "jit")]
+    chip8.set_jit(args.machine.jit)?;
+    if let Some(path) = &args.movie {
+        let movie = Movie::load(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        chip8.play_movie(&movie).map_err(|e| format!("{}: {}", path.display(), e))?;
+    }
+    let keys = testing::parse_keys(args.keys.as_deref().unwrap_or_default())?;
+    let run = TestRun { frames: args.frames, keys, until_halt: args.until_halt };
+    let result = run.run(&mut chip8);
+    let name = args.rom.display();
+
+    if let Some(path) = &args.snapshot {
+        let comparison = testing::compare(&result.snapshot, path, args.update).map_err(|e| format!("{}: {}", path.display(), e))?;
+        match comparison {
+            Comparison::Match => {}
+            Comparison::Updated => println!("Wrote {} after {} frames", path.display(), result.frames),
+            Comparison::Missing => {
+                println!("FAIL {}: there is no snapshot at {}, run with --update to write it", name, path.display());
+                return Ok(ExitCode::FAILURE);
+            }
+            Comparison::Mismatch { pixels } => {
+                println!("FAIL {}: {} pixels differ from {} after {} frames, the screen was:", name, pixels, path.display(), result.frames);
+                print!("{}", result.snapshot);
+                return Ok(ExitCode::FAILURE);
+            }
+        }
+    }
+
+    let hash = screen_hash(&chip8);
+    if let Some(expected) = &args.expect {
+        if !expected.eq_ignore_ascii_case(&hash) {
+            println!("FAIL {}: screen after {} frames is {}, expected {}", name, result.frames, hash, expected);
+            return Ok(ExitCode::FAILURE);
+        }
+    }
+    if args.expect.is_none() && args.snapshot.is_none() {
+        println!("{}", hash);
+    } else if !args.update {
+        println!("ok {}", name);
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn bench(args: BenchArgs) -> Result<ExitCode, String> {
+    let rom = read_rom(&args.rom)?;
+    let mut settings = rom_settings(&args.machine.config()?, &rom);
+    settings.merge(&args.machine.settings());
+    //a thousand instructions a frame so the time goes to running instructions, not to frame bookkeeping
+    settings.ips = Some(args.machine.ips.unwrap_or(60_000));
+    settings.seed.get_or_insert(0);
+
+    let (base_ips, base_hash) = time_frames(&rom, &settings, args.frames, "Without the instruction cache", |chip8| {
+        chip8.set_instruction_cache(false);
+        Ok(())
+    })?;
+    #[cfg_attr(not(feature = "jit"), allow(unused_mut))]
+    let mut results = vec![("the cache", time_frames(&rom, &settings, args.frames, "With the instruction cache", |_| Ok(()))?)];
+    //built with the jit feature the JIT is timed too, with the cache on but compared to the run without it
+    #[cfg(feature = "jit")]
+    results.push(("the JIT", time_frames(&rom, &settings, args.frames, "With the JIT", |chip8| chip8.set_jit(true))?));
+
+    for (name, (ips, hash)) in results {
+        if hash != base_hash {
+            println!("FAIL {}: the screen is different with {}", args.rom.display(), name);
+            return Ok(ExitCode::FAILURE);
+        }
+        println!("{:.2}x as fast with {}", ips / base_ips, name);
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+// runs frames of rom after setup, returning the instructions per second and a hash of the final screen
+fn time_frames(
+    rom: &RomFile,
+    settings: &Settings,
+    frames: u64,
+    name: &str,
+    setup: impl FnOnce(&mut Chip8) -> Result<(), String>,
+) -> Result<(f64, String), String> {
+    let mut chip8 = Chip8::headless();
+    boot(&mut chip8, rom, settings)?;
+    setup(&mut chip8)?;
+    let start = Instant::now();
+    for _ in 0..frames {
+        chip8.step_frame();
+    }
+    let seconds = start.elapsed().as_secs_f64();
+    let ips = chip8.cycle() as f64 / seconds;
+    println!("{}: {} instructions in {:.2} s, {:.1} million per second", name, chip8.cycle(), seconds, ips / 1e6);
+    Ok((ips, screen_hash(&chip8)))
+}
+
+// the translation is for the quirks the ROM would run with, so they are worked out the same way as for run
+fn recompile(args: RecompileArgs) -> Result<ExitCode, String> {
+    let rom = read_rom(&args.rom)?;
+    let mut settings = rom_settings(&args.machine.config()?, &rom);
+    settings.merge(&args.machine.settings());
+    let mut chip8 = Chip8::headless();
+    boot(&mut chip8, &rom, &settings)?;
+    let name = args.rom.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+    let source = recompiler::translate(&rom.data, &name, chip8.rom_sha1(), chip8.quirks());
+    match &args.output {
+        Some(output) => {
+            fs::write(output, &source).map_err(|e| format!("{}: {}", output.display(), e))?;
+            println!("Wrote {} to {}", args.rom.display(), output.display());
+        }
+        None => print!("{}", source),
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+pub fn read_rom(path: &Path) -> Result<RomFile, String> {
+    rom::load(path).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+// identifies ROMs with the chip-8-database in dir from now on, None keeps the bundled copy
+pub fn use_database(dir: Option<&Path>) -> Result<(), String> {
+    let Some(dir) = dir else { return Ok(()) };
+    Database::load(dir).and_then(Database::install).map_err(|e| format!("ROM database: {}", e))
+}
+
+// the settings from the config file for a ROM
+pub fn rom_settings(config: &ConfigFile, rom: &RomFile) -> Settings {
+    config.settings_for(&metadata::sha1_hex(&rom.data), metadata::identify(&rom.data).as_ref(), &rom.settings)
+}
+
+// loads rom and applies settings on top of what the ROM database picked
+pub fn boot(chip8: &mut Chip8, rom: &RomFile, settings: &Settings) -> Result<(), String> {
+    chip8.load_rom_bytes(&rom.data, &rom::name(&rom.path)).map_err(|e| format!("{}: {}", rom.path.display(), e))?;
+    settings.apply(chip8)?;
+    chip8.reset();
+    Ok(())
+}
+
+fn reset_flags(rom: &RomFile) -> Result<(), String> {
+    let Some(dir) = flags::default_dir() else { return Ok(()) };
+    let removed = flags::reset(&dir, &metadata::sha1_hex(&rom.data)).map_err(|e| format!("could not reset flags: {}", e))?;
+    if removed {
+        println!("Reset the flags of {}", rom.path.display());
+    }
+    Ok(())
+}
+
+// the keymap file given on the command line has to load, the default one falls back quietly
+fn load_keymap(path: Option<&Path>, rom_sha1: &str) -> Result<(Keymap, Option<PathBuf>), String> {
+    match path {
+        Some(path) => {
+            let keymap = KeymapFile::load(path)
+                .and_then(|file| file.keymap_for(rom_sha1))
+                .map_err(|e| format!("{}: {}", path.display(), e))?;
+            Ok((keymap, Some(path.to_path_buf())))
+        }
+        None => Ok(crate::load_keymap(None, rom_sha1)),
+    }
+}
+
+fn info(rom: &[u8]) {
+    let sha1 = metadata::sha1_hex(rom);
+    println!("SHA-1: {}", sha1);
+    println!("Size: {} bytes", rom.len());
+    let Some(info) = Database::current().lookup(&sha1) else {
+        println!("Not in the ROM database");
+        return;
+    };
+    println!("Title: {}", info.title);
+    if !info.authors.is_empty() {
+        println!("Authors: {}", info.authors.join(", "));
+    }
+    if !info.release.is_empty() {
+        println!("Released: {}", info.release);
+    }
+    println!("Description: {}", info.description);
+    println!("Platform: {} ({})", info.platform_name, info.platform);
+    println!("Speed: {} instructions per frame", info.speed);
+    println!("Quirks: {}", info.quirks);
+    println!("Controls: {}", info.key_hints(&Keymap::default()));
+}
+
+// SHA-1 of the frame buffer, one byte per pixel row by row, for comparing screens in scripts
+fn screen_hash(chip8: &Chip8) -> String {
+    let pixels: Vec<u8> = chip8.display().frame_buffer.iter().flatten().map(|&pixel| pixel as u8).collect();
+    metadata::sha1_hex(&pixels)
+}
@@ -0,0 +1,210 @@
+// config.toml in the config directory: global defaults, plus sections for single ROMs keyed by SHA-1.
+//
+//     database = "/home/me/chip-8-database/database" # instead of the bundled copy, see src/metadata.rs
+//
+//     [defaults]
+//     ips = 600
+//     scale = 12
+//     palette = "000000,33ff66"
+//
+//     [roms.f13766c14aeb02ad8d4d103cb5eadd282d20cddc] # brix
+//     platform = "chip8"
+//     ips = 900
+//
+// Settings are layered: built-in defaults, then [defaults], then what the file asks for (the platform a
+// .sc8 or .xo8 extension hints at, or an Octo cartridge's options), then what the ROM database says
+// about the ROM, then its [roms] section, then command-line options.
+
+use crate::chip8::Chip8;
+use crate::display::Palette;
+use crate::filters::Filter;
+use crate::metadata::RomInfo;
+use crate::quirks::Quirks;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(io::Error),
+    Parse(toml::de::Error),
+    Invalid { section: String, message: String },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(e) => write!(f, "{}", e),
+            ConfigError::Parse(e) => write!(f, "{}", e),
+            ConfigError::Invalid { section, message } => write!(f, "in [{}]: {}", section, message),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Settings {
+    pub platform: Option<String>,   // quirk preset, see Quirks::PRESETS
+    pub quirks: Option<String>,     // overrides on top of the preset, like "shift=0 vblank=1"
+    pub ips: Option<usize>,         // instructions per second
+    pub frame_rate: Option<f64>,    // frames per second, 60 on real hardware
+    pub seed: Option<u64>,
+    pub scale: Option<usize>,
+    pub palette: Option<String>,    // "background,foreground" in hex RGB
+    pub filter: Option<String>,
+    pub integer_scale: Option<bool>,
+    pub keymap: Option<PathBuf>,
+}
+
+impl Settings {
+    // fields set in other win
+    pub fn merge(&mut self, other: &Settings) {
+        fn take<T: Clone>(field: &mut Option<T>, other: &Option<T>) {
+            if other.is_some() {
+                field.clone_from(other);
+            }
+        }
+        take(&mut self.platform, &other.platform);
+        take(&mut self.quirks, &other.quirks);
+        take(&mut self.ips, &other.ips);
+        take(&mut self.frame_rate, &other.frame_rate);
+        take(&mut self.seed, &other.seed);
+        take(&mut self.scale, &other.scale);
+        take(&mut self.palette, &other.palette);
+        take(&mut self.filter, &other.filter);
+        take(&mut self.integer_scale, &other.integer_scale);
+        take(&mut self.keymap, &other.keymap);
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(platform) = &self.platform {
+            if Quirks::preset(platform).is_none() {
+                return Err(format!("unknown platform \"{}\", use one of {}", platform, Quirks::PRESETS.join(", ")));
+            }
+        }
+        if let Some(quirks) = &self.quirks {
+            Quirks::default().apply(quirks)?;
+        }
+        if self.ips == Some(0) {
+            return Err(String::from("ips must be more than 0"));
+        }
+        if self.frame_rate.is_some_and(|frame_rate| !(1.0..=1000.0).contains(&frame_rate)) {
+            return Err(String::from("frame_rate must be between 1 and 1000"));
+        }
+        if self.scale.is_some_and(|scale| !(1..=64).contains(&scale)) {
+            return Err(String::from("scale must be between 1 and 64"));
+        }
+        if let Some(palette) = &self.palette {
+            palette.parse::<Palette>()?;
+        }
+        if let Some(filter) = &self.filter {
+            if Filter::from_name(filter).is_none() {
+                let names: Vec<&str> = Filter::ALL.iter().map(|filter| filter.name()).collect();
+                return Err(format!("unknown filter \"{}\", use one of {}", filter, names.join(", ")));
+            }
+        }
+        Ok(())
+    }
+
+    // applies everything but scale and keymap, which the frontend needs before the machine exists;
+    // call after load_rom so these win over the ROM database, then reset
+    pub fn apply(&self, chip8: &mut Chip8) -> Result<(), String> {
+        self.validate()?;
+        if let Some(quirks) = self.platform.as_deref().and_then(Quirks::preset) {
+            chip8.set_quirks(quirks);
+        }
+        if let Some(overrides) = &self.quirks {
+            let mut quirks = chip8.quirks();
+            quirks.apply(overrides)?;
+            chip8.set_quirks(quirks);
+        }
+        if let Some(frame_rate) = self.frame_rate {
+            chip8.set_frame_rate(frame_rate);
+        }
+        if let Some(ips) = self.ips {
+            chip8.set_speed((ips as f64 / chip8.frame_rate()).round() as usize);
+        }
+        if let Some(seed) = self.seed {
+            chip8.set_seed(seed);
+        }
+        if let Some(palette) = &self.palette {
+            chip8.display_mut().palette = palette.parse()?;
+        }
+        if let Some(filter) = self.filter.as_deref().and_then(Filter::from_name) {
+            chip8.display_mut().filter = filter;
+        }
+        if let Some(integer_scale) = self.integer_scale {
+            chip8.display_mut().integer_scale = integer_scale;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigFile {
+    pub database: Option<PathBuf>, // a chip-8-database checkout's database/ directory
+    #[serde(default)]
+    pub defaults: Settings,
+    #[serde(default)]
+    pub roms: BTreeMap<String, Settings>, // keyed by lowercase hex SHA-1
+}
+
+impl ConfigFile {
+    // <config dir>/rust-chip8-emulator/config.toml
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("rust-chip8-emulator").join("config.toml"))
+    }
+
+    // a missing file is the same as an empty one; anything invalid is reported with its section
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        let config: ConfigFile = match fs::read_to_string(path) {
+            Ok(contents) => toml::from_str(&contents).map_err(ConfigError::Parse)?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(ConfigFile::default()),
+            Err(e) => return Err(ConfigError::Io(e)),
+        };
+
+        let invalid = |section: String, message: String| ConfigError::Invalid { section, message };
+        config.defaults.validate().map_err(|message| invalid(String::from("defaults"), message))?;
+        for (sha1, settings) in &config.roms {
+            let section = format!("roms.{}", sha1);
+            if sha1.len() != 40 || !sha1.chars().all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c)) {
+                return Err(invalid(section, String::from("ROM sections are named by the lowercase hex SHA-1 of the ROM, see the info subcommand")));
+            }
+            settings.validate().map_err(|message| invalid(section, message))?;
+        }
+        Ok(config)
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let contents = toml::to_string_pretty(self).map_err(io::Error::other)?;
+        fs::write(path, contents)
+    }
+
+    // the layered settings for a ROM, before command-line options
+    pub fn settings_for(&self, sha1: &str, info: Option<&RomInfo>, file: &Settings) -> Settings {
+        let mut settings = self.defaults.clone();
+        settings.merge(file);
+        //the database knows better than global defaults how a ROM it lists should run
+        if let Some(info) = info {
+            settings.platform = None;
+            settings.quirks = None;
+            settings.ips = None;
+            if info.palette.is_some() {
+                settings.palette = None;
+            }
+        }
+        if let Some(rom) = self.roms.get(sha1) {
+            settings.merge(rom);
+        }
+        settings
+    }
+}
@@ -0,0 +1,83 @@
+// Disassembler using the common Cowgod mnemonics. Listings put the address and raw bytes in comments,
+// so feeding one back through asm::assemble gives the same ROM.
+
+use std::fmt::Write;
+
+pub fn disassemble(opcode: u16) -> String {
+    let x = (opcode & 0x0F00) >> 8;
+    let y = (opcode & 0x00F0) >> 4;
+    let n = opcode & 0x000F;
+    let nn = opcode & 0x00FF;
+    let nnn = opcode & 0x0FFF;
+
+    match opcode & 0xF000 {
+        0x0000 => match opcode {
+            0x00E0 => String::from("CLS"),
+            0x00EE => String::from("RET"),
+            _ => format!("SYS 0x{:03X}", nnn),
+        },
+        0x1000 => format!("JP 0x{:03X}", nnn),
+        0x2000 => format!("CALL 0x{:03X}", nnn),
+        0x3000 => format!("SE V{:X}, 0x{:02X}", x, nn),
+        0x4000 => format!("SNE V{:X}, 0x{:02X}", x, nn),
+        0x5000 if n == 0 => format!("SE V{:X}, V{:X}", x, y),
+        0x6000 => format!("LD V{:X}, 0x{:02X}", x, nn),
+        0x7000 => format!("ADD V{:X}, 0x{:02X}", x, nn),
+        0x8000 => match n {
+            0x0 => format!("LD V{:X}, V{:X}", x, y),
+            0x1 => format!("OR V{:X}, V{:X}", x, y),
+            0x2 => format!("AND V{:X}, V{:X}", x, y),
+            0x3 => format!("XOR V{:X}, V{:X}", x, y),
+            0x4 => format!("ADD V{:X}, V{:X}", x, y),
+            0x5 => format!("SUB V{:X}, V{:X}", x, y),
+            0x6 => format!("SHR V{:X}, V{:X}", x, y),
+            0x7 => format!("SUBN V{:X}, V{:X}", x, y),
+            0xE => format!("SHL V{:X}, V{:X}", x, y),
+            _ => data_word(opcode),
+        },
+        0x9000 if n == 0 => format!("SNE V{:X}, V{:X}", x, y),
+        0xA000 => format!("LD I, 0x{:03X}", nnn),
+        0xB000 => format!("JP V0, 0x{:03X}", nnn),
+        0xC000 => format!("RND V{:X}, 0x{:02X}", x, nn),
+        0xD000 => format!("DRW V{:X}, V{:X}, {}", x, y, n),
+        0xE000 => match nn {
+            0x9E => format!("SKP V{:X}", x),
+            0xA1 => format!("SKNP V{:X}", x),
+            _ => data_word(opcode),
+        },
+        0xF000 => match nn {
+            0x07 => format!("LD V{:X}, DT", x),
+            0x0A => format!("LD V{:X}, K", x),
+            0x15 => format!("LD DT, V{:X}", x),
+            0x18 => format!("LD ST, V{:X}", x),
+            0x1E => format!("ADD I, V{:X}", x),
+            0x29 => format!("LD F, V{:X}", x),
+            0x33 => format!("LD B, V{:X}", x),
+            0x55 => format!("LD [I], V{:X}", x),
+            0x65 => format!("LD V{:X}, [I]", x),
+            0x75 => format!("LD R, V{:X}", x),
+            0x85 => format!("LD V{:X}, R", x),
+            _ => data_word(opcode),
+        },
+        _ => data_word(opcode),
+    }
+}
+
+fn data_word(opcode: u16) -> String {
+    format!("DW 0x{:04X}", opcode)
+}
+
+// one instruction per line from start, an odd trailing byte becomes a DB
+pub fn listing(rom: &[u8], start: u16) -> String {
+    let mut listing = String::new();
+    for (index, bytes) in rom.chunks(2).enumerate() {
+        let address = start as usize + index * 2;
+        let (text, raw) = match bytes {
+            [high, low] => (disassemble(u16::from_be_bytes([*high, *low])), format!("{:02X}{:02X}", high, low)),
+            [byte] => (format!("DB 0x{:02X}", byte), format!("{:02X}", byte)),
+            _ => continue,
+        };
+        let _ = writeln!(listing, "{:<20} ; {:03X}: {}", text, address, raw);
+    }
+    listing
+}
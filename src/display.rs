@@ -1,61 +1,237 @@
-use minifb::{Key, Window, WindowOptions};
+use crate::filters::{self, Filter};
+use minifb::{InputCallback, Key, KeyRepeat, Scale, ScaleMode, Window, WindowOptions};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
 
-const WIDTH: usize = 64;
-const HEIGHT: usize = 32;
+pub const WIDTH: usize = 64;
+pub const HEIGHT: usize = 32;
+pub const DEFAULT_SCALE: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Palette {
+    pub background: u32,
+    pub foreground: u32,
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Palette {
+            background: 0x00000000,
+            foreground: 0xFFFFFFFF,
+        }
+    }
+}
+
+// parsed from "background,foreground" in hex RGB, like "000000,33ff66"
+impl FromStr for Palette {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || format!("invalid palette \"{}\", expected two hex colors like 000000,ffffff", s);
+        let (background, foreground) = s.split_once(',').ok_or_else(error)?;
+        let color = |hex: &str| {
+            let hex = hex.trim().trim_start_matches('#');
+            u32::from_str_radix(hex, 16).ok().filter(|_| hex.len() == 6)
+        };
+        Ok(Palette {
+            background: color(background).ok_or_else(error)?,
+            foreground: color(foreground).ok_or_else(error)?,
+        })
+    }
+}
+
+// host key transitions in the order the window reported them
+pub type HostKeyEvents = Arc<Mutex<Vec<(Key, bool, Instant)>>>;
+
+struct KeyEventCallback(HostKeyEvents);
+
+impl InputCallback for KeyEventCallback {
+    fn add_char(&mut self, _uni_char: u32) {}
+
+    fn set_key_state(&mut self, key: Key, state: bool) {
+        if let Ok(mut events) = self.0.lock() {
+            events.push((key, state, Instant::now()));
+        }
+    }
+}
 
 #[derive(Debug)]
 pub struct Display {
     pub frame_buffer: [[bool; WIDTH]; HEIGHT],
-    pub window: Window,
+    pub window: Option<Window>, // None when running headless
+    pub palette: Palette,
+    pub filter: Filter,
+    pub integer_scale: bool,
+    fullscreen: bool,
+    scale: usize,
+    title: String,
+    window_integer_scale: bool,  // integer_scale when the window was created, which fixes its scale mode
+    window_size: (usize, usize), // size of the window when last windowed, kept when it's replaced
+    key_events: HostKeyEvents,
+}
+
+impl Default for Display {
+    fn default() -> Self {
+        Display::new(DEFAULT_SCALE)
+    }
 }
 
 impl Display {
-    pub fn default() -> Self {
-        let mut display = Display { 
-            frame_buffer: [[false; WIDTH]; HEIGHT], 
-            window: Window::new(
-                "Idek",
-                WIDTH,
-                HEIGHT, 
-                WindowOptions {
-                    scale: minifb::Scale::X16,
-                    ..WindowOptions::default()
-                },
-            ).unwrap_or_else(|e| {
-                panic!("{}", e);
-            }),
-        };
+    // scale is the initial window size in screen pixels per CHIP-8 pixel
+    pub fn new(scale: usize) -> Self {
+        let title = String::from("CHIP-8");
+        let scale = scale.max(1);
+        let key_events = HostKeyEvents::default();
+        Display {
+            frame_buffer: [[false; WIDTH]; HEIGHT],
+            window: Some(create_window(&title, (WIDTH * scale, HEIGHT * scale), false, false, &key_events)),
+            palette: Palette::default(),
+            filter: Filter::Nearest,
+            integer_scale: false,
+            fullscreen: false,
+            scale,
+            title,
+            window_integer_scale: false,
+            window_size: (WIDTH * scale, HEIGHT * scale),
+            key_events,
+        }
+    }
 
-        display.window.set_target_fps(60);
+    // a display that keeps a frame buffer but never opens a window, for batch runs and tests
+    pub fn headless(scale: usize) -> Self {
+        Display {
+            frame_buffer: [[false; WIDTH]; HEIGHT],
+            window: None,
+            palette: Palette::default(),
+            filter: Filter::Nearest,
+            integer_scale: false,
+            fullscreen: false,
+            scale: scale.max(1),
+            title: String::from("CHIP-8"),
+            window_integer_scale: false,
+            window_size: (WIDTH * scale.max(1), HEIGHT * scale.max(1)),
+            key_events: HostKeyEvents::default(),
+        }
+    }
+
+    pub fn scale(&self) -> usize {
+        self.scale
+    }
 
-        display
+    // size of the frame buffer in CHIP-8 pixels
+    pub fn resolution(&self) -> (usize, usize) {
+        (WIDTH, HEIGHT)
     }
 
-    pub fn render(&mut self) {
-        let mut buffer: Vec<u32> = vec![0; WIDTH * HEIGHT];
+    // frame buffer converted to 1D ARGB pixels with the active palette, before any filter
+    pub fn pixels(&self) -> (Vec<u32>, usize, usize) {
+        let (width, height) = self.resolution();
+        let mut buffer: Vec<u32> = vec![0; width * height];
 
         //convert 2D bool array to 1D u32 vec
         let mut vec_index = 0;
-        for row in 0..HEIGHT {
-            for col in 0..WIDTH {
+        for row in 0..height {
+            for col in 0..width {
                 if self.frame_buffer[row][col] {
-                    buffer[vec_index] = 0xFFFFFFFF;
+                    buffer[vec_index] = self.palette.foreground;
                 } else {
-                    buffer[vec_index] = 0x00000000;
+                    buffer[vec_index] = self.palette.background;
                 }
                 vec_index += 1;
             }
         }
 
-        self.window.update_with_buffer(&buffer, WIDTH, HEIGHT).unwrap();
+        (buffer, width, height)
+    }
+
+    // what the window shows at its initial size: palette, then filter, then upscaled to the display scale
+    pub fn scaled_pixels(&self) -> (Vec<u32>, usize, usize) {
+        let (buffer, width, height) = self.pixels();
+        let target_width = width * self.scale;
+        let (buffer, width, height) = self.filter.apply(&buffer, width, height);
+        let factor = (target_width / width).max(1);
+        filters::nearest(&buffer, width, height, factor)
+    }
+
+    pub fn render(&mut self) -> Result<(), minifb::Error> {
+        if self.window.is_none() {
+            return Ok(());
+        }
+        //integer_scale can be set directly, and only a new window picks up the scale mode it needs
+        if self.integer_scale != self.window_integer_scale {
+            self.remember_window_size();
+            self.rebuild_window();
+        }
+
+        let (buffer, width, height) = self.pixels();
+        let (mut buffer, mut width, mut height) = self.filter.apply(&buffer, width, height);
+
+        if let Some(window) = self.window.as_mut() {
+            //in integer mode the window centers the buffer without scaling, so upscale it here by the largest whole factor that fits
+            if self.integer_scale {
+                let (window_width, window_height) = window.get_size();
+                let factor = (window_width / width).min(window_height / height).max(1);
+                (buffer, width, height) = filters::nearest(&buffer, width, height, factor);
+            }
+
+            window.update_with_buffer(&buffer, width, height)?;
+        }
+        Ok(())
     }
 
     pub fn is_open(&self) -> bool {
-        self.window.is_open()
+        self.window.as_ref().is_some_and(|window| window.is_open())
     }
 
     pub fn is_key_down(&self, key: Key) -> bool {
-        self.window.is_key_down(key)
+        self.window.as_ref().is_some_and(|window| window.is_key_down(key))
+    }
+
+    pub fn is_key_pressed(&self, key: Key) -> bool {
+        self.window.as_ref().is_some_and(|window| window.is_key_pressed(key, KeyRepeat::No))
+    }
+
+    pub fn get_keys(&self) -> Vec<Key> {
+        self.window.as_ref().map(|window| window.get_keys()).unwrap_or_default()
+    }
+
+    // every key press and release since the last call, including ones too short to see with get_keys
+    pub fn take_key_events(&mut self) -> Vec<(Key, bool, Instant)> {
+        self.key_events.lock().map(|mut events| std::mem::take(&mut *events)).unwrap_or_default()
+    }
+
+    pub fn get_keys_pressed(&self) -> Vec<Key> {
+        self.window.as_ref().map(|window| window.get_keys_pressed(KeyRepeat::No)).unwrap_or_default()
+    }
+
+    pub fn cycle_filter(&mut self) {
+        self.filter = self.filter.next();
+    }
+
+    pub fn is_fullscreen(&self) -> bool {
+        self.fullscreen
+    }
+
+    pub fn toggle_fullscreen(&mut self) {
+        self.remember_window_size();
+        self.fullscreen = !self.fullscreen;
+        self.rebuild_window();
+    }
+
+    pub fn toggle_integer_scale(&mut self) {
+        self.remember_window_size();
+        self.integer_scale = !self.integer_scale;
+        self.rebuild_window();
+    }
+
+    pub fn set_title(&mut self, title: &str) {
+        if self.title != title {
+            self.title = title.to_string();
+            if let Some(window) = self.window.as_mut() {
+                window.set_title(title);
+            }
+        }
     }
 
     pub fn clear(&mut self) {
@@ -65,4 +241,54 @@ impl Display {
             }
         }
     }
-}
\ No newline at end of file
+
+    //a window the user resized comes back at that size after going fullscreen or changing scale mode
+    fn remember_window_size(&mut self) {
+        if let Some(window) = self.window.as_ref().filter(|_| !self.fullscreen) {
+            self.window_size = window.get_size();
+        }
+    }
+
+    //minifb can't change scale mode, borders or size of an open window, so those changes replace it
+    fn rebuild_window(&mut self) {
+        if self.window.is_none() {
+            return;
+        }
+        self.window_integer_scale = self.integer_scale;
+        //drop the old window first so two windows never exist at once
+        self.window = None;
+        self.window = Some(create_window(&self.title, self.window_size, self.fullscreen, self.integer_scale, &self.key_events));
+    }
+}
+
+// size is the windowed size in screen pixels
+fn create_window(title: &str, size: (usize, usize), fullscreen: bool, integer_scale: bool, key_events: &HostKeyEvents) -> Window {
+    let scale_mode = if integer_scale { ScaleMode::Center } else { ScaleMode::AspectRatioStretch };
+    let options = if fullscreen {
+        WindowOptions {
+            borderless: true,
+            title: false,
+            topmost: true,
+            scale: Scale::FitScreen,
+            scale_mode,
+            ..WindowOptions::default()
+        }
+    } else {
+        WindowOptions {
+            resize: true,
+            scale_mode,
+            ..WindowOptions::default()
+        }
+    };
+
+    //fullscreen lets minifb pick the largest scale that fits the screen, windowed mode sizes the window directly
+    let (width, height) = if fullscreen { (WIDTH, HEIGHT) } else { size };
+
+    let mut window = Window::new(title, width, height, options).unwrap_or_else(|e| {
+        panic!("{}", e);
+    });
+    //the run loops pace frames to the configured frame rate, so the window mustn't wait as well
+    window.set_target_fps(0);
+    window.set_input_callback(Box::new(KeyEventCallback(key_events.clone())));
+    window
+}
@@ -0,0 +1,296 @@
+// CPU-side scaling filters applied to the ARGB frame buffer before it is handed to the window.
+// Every filter is a pure function from (pixels, width, height) to a new (pixels, width, height).
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Filter {
+    Nearest,
+    Scale2x,
+    Scale3x,
+    Epx,
+    Eagle,
+    Hq2x,
+    Scanlines,
+    CrtMask,
+}
+
+impl Filter {
+    pub const ALL: [Filter; 8] = [
+        Filter::Nearest,
+        Filter::Scale2x,
+        Filter::Scale3x,
+        Filter::Epx,
+        Filter::Eagle,
+        Filter::Hq2x,
+        Filter::Scanlines,
+        Filter::CrtMask,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Filter::Nearest => "Nearest",
+            Filter::Scale2x => "Scale2x",
+            Filter::Scale3x => "Scale3x",
+            Filter::Epx => "EPX",
+            Filter::Eagle => "Eagle",
+            Filter::Hq2x => "hq2x",
+            Filter::Scanlines => "Scanlines",
+            Filter::CrtMask => "CRT mask",
+        }
+    }
+
+    // case-insensitive, ignoring spaces, dashes and underscores, so "crt_mask" finds CRT mask
+    pub fn from_name(name: &str) -> Option<Filter> {
+        let simplify = |name: &str| name.chars().filter(|c| c.is_ascii_alphanumeric()).collect::<String>().to_lowercase();
+        Filter::ALL.into_iter().find(|filter| simplify(filter.name()) == simplify(name))
+    }
+
+    // cycles through the filters in the order of ALL, used by the runtime toggle hotkey
+    pub fn next(self) -> Filter {
+        let index = Filter::ALL.iter().position(|&f| f == self).unwrap_or(0);
+        Filter::ALL[(index + 1) % Filter::ALL.len()]
+    }
+
+    pub fn apply(self, pixels: &[u32], width: usize, height: usize) -> (Vec<u32>, usize, usize) {
+        match self {
+            Filter::Nearest => (pixels.to_vec(), width, height),
+            Filter::Scale2x => scale2x(pixels, width, height),
+            Filter::Scale3x => scale3x(pixels, width, height),
+            Filter::Epx => epx(pixels, width, height),
+            Filter::Eagle => eagle(pixels, width, height),
+            Filter::Hq2x => hq2x(pixels, width, height),
+            Filter::Scanlines => scanlines(pixels, width, height),
+            Filter::CrtMask => crt_mask(pixels, width, height),
+        }
+    }
+}
+
+// pixel lookup that clamps coordinates to the edge of the image
+fn get(pixels: &[u32], width: usize, height: usize, x: isize, y: isize) -> u32 {
+    let x = x.clamp(0, width as isize - 1) as usize;
+    let y = y.clamp(0, height as isize - 1) as usize;
+    pixels[y * width + x]
+}
+
+// weighted per-channel average of two ARGB colors
+fn blend(a: u32, b: u32, weight_a: u32, weight_b: u32) -> u32 {
+    let total = weight_a + weight_b;
+    let mut result = 0;
+    for shift in [0, 8, 16, 24] {
+        let channel_a = (a >> shift) & 0xFF;
+        let channel_b = (b >> shift) & 0xFF;
+        result |= ((channel_a * weight_a + channel_b * weight_b) / total) << shift;
+    }
+    result
+}
+
+// multiplies the red, green and blue channels by numerator / 255, alpha is left alone
+fn tint(color: u32, red: u32, green: u32, blue: u32) -> u32 {
+    let r = ((color >> 16) & 0xFF) * red / 255;
+    let g = ((color >> 8) & 0xFF) * green / 255;
+    let b = (color & 0xFF) * blue / 255;
+    (color & 0xFF000000) | (r << 16) | (g << 8) | b
+}
+
+pub fn nearest(pixels: &[u32], width: usize, height: usize, factor: usize) -> (Vec<u32>, usize, usize) {
+    let out_width = width * factor;
+    let out_height = height * factor;
+    let mut out = vec![0; out_width * out_height];
+    for y in 0..out_height {
+        for x in 0..out_width {
+            out[y * out_width + x] = pixels[(y / factor) * width + x / factor];
+        }
+    }
+    (out, out_width, out_height)
+}
+
+pub fn scale2x(pixels: &[u32], width: usize, height: usize) -> (Vec<u32>, usize, usize) {
+    let out_width = width * 2;
+    let mut out = vec![0; out_width * height * 2];
+    for y in 0..height {
+        for x in 0..width {
+            let (xi, yi) = (x as isize, y as isize);
+            let b = get(pixels, width, height, xi, yi - 1);
+            let d = get(pixels, width, height, xi - 1, yi);
+            let e = pixels[y * width + x];
+            let f = get(pixels, width, height, xi + 1, yi);
+            let h = get(pixels, width, height, xi, yi + 1);
+
+            let (mut e0, mut e1, mut e2, mut e3) = (e, e, e, e);
+            if b != h && d != f {
+                if d == b { e0 = d; }
+                if b == f { e1 = f; }
+                if d == h { e2 = d; }
+                if h == f { e3 = f; }
+            }
+
+            let top = (y * 2) * out_width + x * 2;
+            let bottom = top + out_width;
+            out[top] = e0;
+            out[top + 1] = e1;
+            out[bottom] = e2;
+            out[bottom + 1] = e3;
+        }
+    }
+    (out, out_width, height * 2)
+}
+
+pub fn scale3x(pixels: &[u32], width: usize, height: usize) -> (Vec<u32>, usize, usize) {
+    let out_width = width * 3;
+    let mut out = vec![0; out_width * height * 3];
+    for y in 0..height {
+        for x in 0..width {
+            let (xi, yi) = (x as isize, y as isize);
+            let a = get(pixels, width, height, xi - 1, yi - 1);
+            let b = get(pixels, width, height, xi, yi - 1);
+            let c = get(pixels, width, height, xi + 1, yi - 1);
+            let d = get(pixels, width, height, xi - 1, yi);
+            let e = pixels[y * width + x];
+            let f = get(pixels, width, height, xi + 1, yi);
+            let g = get(pixels, width, height, xi - 1, yi + 1);
+            let h = get(pixels, width, height, xi, yi + 1);
+            let i = get(pixels, width, height, xi + 1, yi + 1);
+
+            let mut block = [e; 9];
+            if b != h && d != f {
+                if d == b { block[0] = d; }
+                if (d == b && e != c) || (b == f && e != a) { block[1] = b; }
+                if b == f { block[2] = f; }
+                if (d == b && e != g) || (d == h && e != a) { block[3] = d; }
+                if (b == f && e != i) || (h == f && e != c) { block[5] = f; }
+                if d == h { block[6] = d; }
+                if (d == h && e != i) || (h == f && e != g) { block[7] = h; }
+                if h == f { block[8] = f; }
+            }
+
+            for (index, color) in block.iter().enumerate() {
+                let out_x = x * 3 + index % 3;
+                let out_y = y * 3 + index / 3;
+                out[out_y * out_width + out_x] = *color;
+            }
+        }
+    }
+    (out, out_width, height * 3)
+}
+
+// EPX as Eric Johnston described it, which produces the same output as Scale2x
+pub fn epx(pixels: &[u32], width: usize, height: usize) -> (Vec<u32>, usize, usize) {
+    let out_width = width * 2;
+    let mut out = vec![0; out_width * height * 2];
+    for y in 0..height {
+        for x in 0..width {
+            let (xi, yi) = (x as isize, y as isize);
+            let p = pixels[y * width + x];
+            let a = get(pixels, width, height, xi, yi - 1);
+            let b = get(pixels, width, height, xi + 1, yi);
+            let c = get(pixels, width, height, xi - 1, yi);
+            let d = get(pixels, width, height, xi, yi + 1);
+
+            let mut one = p;
+            let mut two = p;
+            let mut three = p;
+            let mut four = p;
+            if c == a && c != d && a != b { one = a; }
+            if a == b && a != c && b != d { two = b; }
+            if d == c && d != b && c != a { three = c; }
+            if b == d && b != a && d != c { four = d; }
+
+            let top = (y * 2) * out_width + x * 2;
+            let bottom = top + out_width;
+            out[top] = one;
+            out[top + 1] = two;
+            out[bottom] = three;
+            out[bottom + 1] = four;
+        }
+    }
+    (out, out_width, height * 2)
+}
+
+pub fn eagle(pixels: &[u32], width: usize, height: usize) -> (Vec<u32>, usize, usize) {
+    let out_width = width * 2;
+    let mut out = vec![0; out_width * height * 2];
+    for y in 0..height {
+        for x in 0..width {
+            let (xi, yi) = (x as isize, y as isize);
+            let s = get(pixels, width, height, xi - 1, yi - 1);
+            let t = get(pixels, width, height, xi, yi - 1);
+            let u = get(pixels, width, height, xi + 1, yi - 1);
+            let v = get(pixels, width, height, xi - 1, yi);
+            let c = pixels[y * width + x];
+            let w = get(pixels, width, height, xi + 1, yi);
+            let xx = get(pixels, width, height, xi - 1, yi + 1);
+            let yy = get(pixels, width, height, xi, yi + 1);
+            let z = get(pixels, width, height, xi + 1, yi + 1);
+
+            let top = (y * 2) * out_width + x * 2;
+            let bottom = top + out_width;
+            out[top] = if v == s && s == t { s } else { c };
+            out[top + 1] = if t == u && u == w { u } else { c };
+            out[bottom] = if v == xx && xx == yy { xx } else { c };
+            out[bottom + 1] = if w == z && z == yy { z } else { c };
+        }
+    }
+    (out, out_width, height * 2)
+}
+
+// a small hqNx-style 2x filter: the Scale2x edge rules decide where a corner changes,
+// but the corner is blended 3:1 with the center instead of replaced, smoothing diagonals
+pub fn hq2x(pixels: &[u32], width: usize, height: usize) -> (Vec<u32>, usize, usize) {
+    let out_width = width * 2;
+    let mut out = vec![0; out_width * height * 2];
+    for y in 0..height {
+        for x in 0..width {
+            let (xi, yi) = (x as isize, y as isize);
+            let b = get(pixels, width, height, xi, yi - 1);
+            let d = get(pixels, width, height, xi - 1, yi);
+            let e = pixels[y * width + x];
+            let f = get(pixels, width, height, xi + 1, yi);
+            let h = get(pixels, width, height, xi, yi + 1);
+
+            let (mut e0, mut e1, mut e2, mut e3) = (e, e, e, e);
+            if b != h && d != f {
+                if d == b { e0 = blend(d, e, 3, 1); }
+                if b == f { e1 = blend(f, e, 3, 1); }
+                if d == h { e2 = blend(d, e, 3, 1); }
+                if h == f { e3 = blend(f, e, 3, 1); }
+            }
+
+            let top = (y * 2) * out_width + x * 2;
+            let bottom = top + out_width;
+            out[top] = e0;
+            out[top + 1] = e1;
+            out[bottom] = e2;
+            out[bottom + 1] = e3;
+        }
+    }
+    (out, out_width, height * 2)
+}
+
+// doubles the image and darkens every second row to imitate the gaps between CRT scanlines
+pub fn scanlines(pixels: &[u32], width: usize, height: usize) -> (Vec<u32>, usize, usize) {
+    let (mut out, out_width, out_height) = nearest(pixels, width, height, 2);
+    for y in (1..out_height).step_by(2) {
+        for x in 0..out_width {
+            let index = y * out_width + x;
+            out[index] = tint(out[index], 128, 128, 128);
+        }
+    }
+    (out, out_width, out_height)
+}
+
+// triples the image and applies an aperture grille (one red, green and blue column per pixel)
+// with a dimmed bottom row for the scanline gap
+pub fn crt_mask(pixels: &[u32], width: usize, height: usize) -> (Vec<u32>, usize, usize) {
+    let (mut out, out_width, out_height) = nearest(pixels, width, height, 3);
+    for y in 0..out_height {
+        for x in 0..out_width {
+            let index = y * out_width + x;
+            let masked = match x % 3 {
+                0 => tint(out[index], 255, 160, 160),
+                1 => tint(out[index], 160, 255, 160),
+                _ => tint(out[index], 160, 160, 255),
+            };
+            out[index] = if y % 3 == 2 { tint(masked, 170, 170, 170) } else { masked };
+        }
+    }
+    (out, out_width, out_height)
+}
@@ -0,0 +1,45 @@
+// The RPL user flags that FX75 and FX85 save and load. The HP48 kept them across runs, which games use
+// for high scores, so they are kept per ROM as <data dir>/rust-chip8-emulator/flags/<sha1>.flags, one
+// byte per flag.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub const NUM_FLAGS: usize = 16; // SUPER-CHIP has 8, XO-CHIP 16
+
+pub fn default_dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("rust-chip8-emulator").join("flags"))
+}
+
+pub fn path(dir: &Path, rom_sha1: &str) -> PathBuf {
+    dir.join(format!("{}.flags", rom_sha1))
+}
+
+// a ROM without a flags file starts with every flag 0
+pub fn load(dir: &Path, rom_sha1: &str) -> io::Result<[u8; NUM_FLAGS]> {
+    let mut flags = [0; NUM_FLAGS];
+    match fs::read(path(dir, rom_sha1)) {
+        Ok(bytes) => {
+            let len = bytes.len().min(NUM_FLAGS);
+            flags[..len].copy_from_slice(&bytes[..len]);
+            Ok(flags)
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(flags),
+        Err(e) => Err(e),
+    }
+}
+
+pub fn save(dir: &Path, rom_sha1: &str, flags: &[u8; NUM_FLAGS]) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    fs::write(path(dir, rom_sha1), flags)
+}
+
+// returns whether there were flags to remove
+pub fn reset(dir: &Path, rom_sha1: &str) -> io::Result<bool> {
+    match fs::remove_file(path(dir, rom_sha1)) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(e),
+    }
+}
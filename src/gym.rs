@@ -0,0 +1,315 @@
+// Gym-style environments for training agents on CHIP-8 games. An Env is a headless Chip8 that takes an
+// action, holds its keys for a few frames and hands back the screen, the reward those frames earned and
+// whether the episode is over. Rewards and episode ends come from numbers the game keeps in memory, which
+// differ per ROM: pong keeps its score as FX33 digits at 0x2F2, the left player's points at 0x2F3 and the
+// right player's at 0x2F4, so
+//
+//     rewards: vec![Reward { counter: Counter::Byte(0x2F3), weight: 1.0 }, Reward { counter: Counter::Byte(0x2F4), weight: -1.0 }]
+//
+// rewards the left paddle for scoring and punishes it for being scored on. VecEnv steps many Envs at once
+// on worker threads.
+
+use crate::chip8::Chip8;
+use crate::config::Settings;
+use crate::display::{HEIGHT, WIDTH};
+use crate::keypad::NUM_KEYS;
+use std::ops::Range;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread::{self, JoinHandle};
+
+// observations are the screen a byte per pixel, 1 for lit, row by row from the top left
+pub const OBSERVATION_SIZE: usize = WIDTH * HEIGHT;
+
+// added to the seed for each episode, so episodes play out differently but the same every run
+const SEED_STEP: u64 = 0x9E37_79B9_7F4A_7C15;
+
+// a number the game keeps in memory
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Counter {
+    Byte(u16),
+    Word(u16),       // big-endian, like the rest of CHIP-8
+    Bcd(u16, usize), // a digit a byte from the address, most significant first, as FX33 writes them
+}
+
+impl Counter {
+    pub fn read(&self, memory: &[u8]) -> u32 {
+        let byte = |address: u16| memory[address as usize % memory.len()] as u32;
+        match *self {
+            Counter::Byte(address) => byte(address),
+            Counter::Word(address) => byte(address) << 8 | byte(address.wrapping_add(1)),
+            Counter::Bcd(address, digits) => (0..digits as u16).fold(0, |value, digit| value * 10 + byte(address.wrapping_add(digit))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Reward {
+    pub counter: Counter,
+    pub weight: f64, // the reward each time the counter goes up by one, negative for the opponent's score
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnvConfig {
+    pub settings: Settings,      // platform, quirks, ips and seed, on top of the ROM database
+    pub actions: Vec<Vec<u8>>,   // the keys held for each action; empty for nothing, then each key the database lists for the ROM, or all 16
+    pub frame_skip: usize,       // frames each action is held for
+    pub rewards: Vec<Reward>,
+    pub end: Vec<(Counter, u32)>, // the episode ends once any counter reaches its value
+    pub max_frames: u64,         // episodes are cut short after this many frames, 0 for no limit
+}
+
+impl Default for EnvConfig {
+    fn default() -> Self {
+        EnvConfig { settings: Settings::default(), actions: Vec::new(), frame_skip: 4, rewards: Vec::new(), end: Vec::new(), max_frames: 0 }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Step {
+    pub observation: Vec<u8>,
+    pub reward: f64,
+    pub terminated: bool, // the game is over: an end counter was reached or the program halted
+    pub truncated: bool,  // the episode ran max_frames
+}
+
+impl Step {
+    pub fn done(&self) -> bool {
+        self.terminated || self.truncated
+    }
+}
+
+pub struct Env {
+    chip8: Chip8,
+    config: EnvConfig,
+    actions: Vec<Vec<u8>>,
+    seed: u64,
+    episodes: u64,
+    scores: Vec<u32>, // the reward counters as of the last step
+}
+
+impl Env {
+    // boots the ROM with the config's settings; the machine is where the first reset leaves it, so stepping
+    // can start straight away
+    pub fn new(rom: &[u8], name: &str, config: EnvConfig) -> Result<Self, String> {
+        if config.frame_skip == 0 {
+            return Err(String::from("frame_skip must be more than 0"));
+        }
+        let mut chip8 = Chip8::headless();
+        chip8.load_rom_bytes(rom, name).map_err(|e| format!("{}: {}", name, e))?;
+        //seed 0 unless the settings say otherwise, like headless test runs
+        chip8.set_seed(0);
+        config.settings.apply(&mut chip8)?;
+        chip8.reset();
+
+        let mut actions = config.actions.clone();
+        if actions.is_empty() {
+            let keys: Vec<u8> = match chip8.rom_info().filter(|info| !info.keys.is_empty()) {
+                Some(info) => info.keys.values().copied().collect(),
+                None => (0..NUM_KEYS as u8).collect(),
+            };
+            actions.push(Vec::new());
+            actions.extend(keys.into_iter().map(|key| vec![key]));
+        }
+        if let Some(key) = actions.iter().flatten().find(|&&key| key as usize >= NUM_KEYS) {
+            return Err(format!("there is no key {:X}, keys go from 0 to F", key));
+        }
+
+        let seed = chip8.seed();
+        let scores = Vec::new();
+        let mut env = Env { chip8, config, actions, seed, episodes: 0, scores };
+        env.scores = env.read_scores();
+        Ok(env)
+    }
+
+    pub fn actions(&self) -> &[Vec<u8>] {
+        &self.actions
+    }
+
+    pub fn chip8(&self) -> &Chip8 {
+        &self.chip8
+    }
+
+    // starts a new episode from a clean machine, each seeded differently, and returns the first observation
+    pub fn reset(&mut self) -> Vec<u8> {
+        self.chip8.set_seed(self.seed.wrapping_add(self.episodes.wrapping_mul(SEED_STEP)));
+        self.episodes += 1;
+        self.chip8.reset();
+        self.scores = self.read_scores();
+        self.observation()
+    }
+
+    // holds the action's keys for frame_skip frames, or until the episode ends, and adds up the reward
+    pub fn step(&mut self, action: usize) -> Step {
+        assert!(action < self.actions.len(), "action {} is out of range, there are {} actions", action, self.actions.len());
+        let keys = &self.actions[action];
+        for key in 0..NUM_KEYS as u8 {
+            if keys.contains(&key) {
+                self.chip8.press_key(key);
+            } else {
+                self.chip8.release_key(key);
+            }
+        }
+
+        let mut reward = 0.0;
+        let (mut terminated, mut truncated) = (false, false);
+        for _ in 0..self.config.frame_skip {
+            self.chip8.step_frame();
+            let scores = self.read_scores();
+            for ((reward_spec, &before), &after) in self.config.rewards.iter().zip(&self.scores).zip(&scores) {
+                reward += (after as f64 - before as f64) * reward_spec.weight;
+            }
+            self.scores = scores;
+
+            let memory = self.chip8.memory();
+            terminated = self.chip8.is_halted() || self.config.end.iter().any(|(counter, value)| counter.read(memory) >= *value);
+            truncated = self.config.max_frames > 0 && self.chip8.frame() >= self.config.max_frames;
+            if terminated || truncated {
+                break;
+            }
+        }
+        Step { observation: self.observation(), reward, terminated, truncated }
+    }
+
+    pub fn observation(&self) -> Vec<u8> {
+        self.chip8.display().frame_buffer.iter().flatten().map(|&lit| lit as u8).collect()
+    }
+
+    fn read_scores(&self) -> Vec<u32> {
+        self.config.rewards.iter().map(|reward| reward.counter.read(self.chip8.memory())).collect()
+    }
+}
+
+enum Request {
+    Reset,
+    Step(Vec<usize>), // an action for each of the worker's environments
+}
+
+enum Reply {
+    Observations(Vec<Vec<u8>>),
+    Steps(Vec<Step>),
+}
+
+struct Worker {
+    requests: Sender<Request>,
+    replies: Receiver<Reply>,
+    instances: Range<usize>,
+    thread: JoinHandle<()>,
+}
+
+// many environments stepped together, split between worker threads. Instance i is seeded with the
+// config's seed plus i, and an instance whose episode ended is reset before its next step, so a step
+// where done() is true still has the episode's last screen
+pub struct VecEnv {
+    workers: Vec<Worker>,
+    instances: usize,
+    actions: Vec<Vec<u8>>,
+}
+
+impl VecEnv {
+    pub fn new(rom: &[u8], name: &str, config: EnvConfig, instances: usize, threads: usize) -> Result<Self, String> {
+        if instances == 0 {
+            return Err(String::from("there has to be at least one instance"));
+        }
+        //a Chip8 can't move between threads, so each worker boots its own, after this one shows the ROM
+        //and config work
+        let env = Env::new(rom, name, config.clone())?;
+        let actions = env.actions().to_vec();
+        let seed = env.seed;
+
+        let threads = threads.clamp(1, instances);
+        let mut workers = Vec::new();
+        for worker in 0..threads {
+            let range = worker * instances / threads..(worker + 1) * instances / threads;
+            let (requests, worker_requests) = mpsc::channel();
+            let (worker_replies, replies) = mpsc::channel();
+            let (rom, name, config, instances) = (rom.to_vec(), name.to_string(), config.clone(), range.clone());
+            let thread = thread::spawn(move || {
+                let mut envs: Vec<Env> = instances
+                    .map(|instance| {
+                        let mut config = config.clone();
+                        config.settings.seed = Some(seed.wrapping_add(instance as u64));
+                        Env::new(&rom, &name, config).expect("the ROM booted on the calling thread")
+                    })
+                    .collect();
+                let mut done = vec![false; envs.len()];
+                for request in worker_requests {
+                    let reply = match request {
+                        Request::Reset => {
+                            done.fill(false);
+                            Reply::Observations(envs.iter_mut().map(Env::reset).collect())
+                        }
+                        Request::Step(actions) => Reply::Steps(
+                            envs.iter_mut()
+                                .zip(&mut done)
+                                .zip(actions)
+                                .map(|((env, done), action)| {
+                                    if *done {
+                                        env.reset();
+                                    }
+                                    let step = env.step(action);
+                                    *done = step.done();
+                                    step
+                                })
+                                .collect(),
+                        ),
+                    };
+                    if worker_replies.send(reply).is_err() {
+                        break;
+                    }
+                }
+            });
+            workers.push(Worker { requests, replies, instances: range, thread });
+        }
+        Ok(VecEnv { workers, instances, actions })
+    }
+
+    pub fn instances(&self) -> usize {
+        self.instances
+    }
+
+    pub fn actions(&self) -> &[Vec<u8>] {
+        &self.actions
+    }
+
+    // resets every instance and returns their first observations, in instance order
+    pub fn reset(&mut self) -> Vec<Vec<u8>> {
+        self.send(|_| Request::Reset)
+            .flat_map(|reply| match reply {
+                Reply::Observations(observations) => observations,
+                Reply::Steps(_) => unreachable!("a worker answered a reset with steps"),
+            })
+            .collect()
+    }
+
+    // steps instance i with actions[i], all at once
+    pub fn step(&mut self, actions: &[usize]) -> Vec<Step> {
+        assert_eq!(actions.len(), self.instances, "expected an action for each instance");
+        //checked here, since a worker that panics on one takes its environments down with it
+        if let Some(&action) = actions.iter().find(|&&action| action >= self.actions.len()) {
+            panic!("action {} is out of range, there are {} actions", action, self.actions.len());
+        }
+        self.send(|instances| Request::Step(actions[instances].to_vec()))
+            .flat_map(|reply| match reply {
+                Reply::Steps(steps) => steps,
+                Reply::Observations(_) => unreachable!("a worker answered a step with observations"),
+            })
+            .collect()
+    }
+
+    //every worker gets its request before any reply is waited on, so they all run at once
+    fn send(&self, request: impl Fn(Range<usize>) -> Request) -> impl Iterator<Item = Reply> + '_ {
+        for worker in &self.workers {
+            worker.requests.send(request(worker.instances.clone())).expect("an environment worker stopped");
+        }
+        self.workers.iter().map(|worker| worker.replies.recv().expect("an environment worker stopped"))
+    }
+}
+
+impl Drop for VecEnv {
+    fn drop(&mut self) {
+        for worker in self.workers.drain(..) {
+            drop(worker.requests);
+            let _ = worker.thread.join();
+        }
+    }
+}
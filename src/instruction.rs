@@ -0,0 +1,99 @@
+// CHIP-8 instructions taken apart once, with their operands pulled out of the opcode. The interpreter
+// keeps them by address so a loop that runs millions of times is only decoded the first time through.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Instruction {
+    Clear,                           // 00E0
+    Return,                          // 00EE
+    Jump(u16),                       // 1NNN
+    Call(u16),                       // 2NNN
+    SkipIfEqual(u8, u8),             // 3XNN
+    SkipIfNotEqual(u8, u8),          // 4XNN
+    SkipIfRegistersEqual(u8, u8),    // 5XY0
+    Load(u8, u8),                    // 6XNN
+    Add(u8, u8),                     // 7XNN
+    Copy(u8, u8),                    // 8XY0
+    Or(u8, u8),                      // 8XY1
+    And(u8, u8),                     // 8XY2
+    Xor(u8, u8),                     // 8XY3
+    AddRegisters(u8, u8),            // 8XY4
+    Subtract(u8, u8),                // 8XY5
+    ShiftRight(u8, u8),              // 8XY6
+    SubtractFrom(u8, u8),            // 8XY7
+    ShiftLeft(u8, u8),               // 8XYE
+    SkipIfRegistersNotEqual(u8, u8), // 9XY0
+    SetIndex(u16),                   // ANNN
+    JumpWithOffset(u16, u8),         // BNNN, with X for the jump quirk
+    Random(u8, u8),                  // CXNN
+    Draw(u8, u8, u8),                // DXYN
+    SkipIfKey(u8),                   // EX9E
+    SkipIfNotKey(u8),                // EXA1
+    ReadDelay(u8),                   // FX07
+    WaitForKey(u8),                  // FX0A
+    SetDelay(u8),                    // FX15
+    SetSound(u8),                    // FX18
+    AddToIndex(u8),                  // FX1E
+    Character(u8),                   // FX29
+    Decimal(u8),                     // FX33
+    StoreRegisters(u8),              // FX55
+    LoadRegisters(u8),               // FX65
+    SaveFlags(u8),                   // FX75
+    LoadFlags(u8),                   // FX85
+    Unknown(u16),                    // anything else, which does nothing
+}
+
+impl Instruction {
+    pub fn decode(opcode: u16) -> Self {
+        let x = (opcode >> 8 & 0xF) as u8;
+        let y = (opcode >> 4 & 0xF) as u8;
+        let n = (opcode & 0xF) as u8;
+        let nn = (opcode & 0xFF) as u8;
+        let nnn = opcode & 0xFFF;
+
+        match opcode >> 12 {
+            0x0 if opcode == 0x00E0 => Instruction::Clear,
+            0x0 if opcode == 0x00EE => Instruction::Return,
+            0x1 => Instruction::Jump(nnn),
+            0x2 => Instruction::Call(nnn),
+            0x3 => Instruction::SkipIfEqual(x, nn),
+            0x4 => Instruction::SkipIfNotEqual(x, nn),
+            0x5 if n == 0 => Instruction::SkipIfRegistersEqual(x, y),
+            0x6 => Instruction::Load(x, nn),
+            0x7 => Instruction::Add(x, nn),
+            0x8 => match n {
+                0x0 => Instruction::Copy(x, y),
+                0x1 => Instruction::Or(x, y),
+                0x2 => Instruction::And(x, y),
+                0x3 => Instruction::Xor(x, y),
+                0x4 => Instruction::AddRegisters(x, y),
+                0x5 => Instruction::Subtract(x, y),
+                0x6 => Instruction::ShiftRight(x, y),
+                0x7 => Instruction::SubtractFrom(x, y),
+                0xE => Instruction::ShiftLeft(x, y),
+                _ => Instruction::Unknown(opcode),
+            },
+            0x9 if n == 0 => Instruction::SkipIfRegistersNotEqual(x, y),
+            0xA => Instruction::SetIndex(nnn),
+            0xB => Instruction::JumpWithOffset(nnn, x),
+            0xC => Instruction::Random(x, nn),
+            0xD => Instruction::Draw(x, y, n),
+            0xE if nn == 0x9E => Instruction::SkipIfKey(x),
+            0xE if nn == 0xA1 => Instruction::SkipIfNotKey(x),
+            0xF => match nn {
+                0x07 => Instruction::ReadDelay(x),
+                0x0A => Instruction::WaitForKey(x),
+                0x15 => Instruction::SetDelay(x),
+                0x18 => Instruction::SetSound(x),
+                0x1E => Instruction::AddToIndex(x),
+                0x29 => Instruction::Character(x),
+                0x33 => Instruction::Decimal(x),
+                0x55 => Instruction::StoreRegisters(x),
+                0x65 => Instruction::LoadRegisters(x),
+                0x75 => Instruction::SaveFlags(x),
+                0x85 => Instruction::LoadFlags(x),
+                _ => Instruction::Unknown(opcode),
+            },
+            _ => Instruction::Unknown(opcode),
+        }
+    }
+}
@@ -0,0 +1,529 @@
+// Compiles CHIP-8 code into native code with Cranelift, behind the jit feature. Compiling starts at the
+// address the interpreter is about to run and takes the basic blocks reachable from it through jumps and
+// skips: runs of instructions that only touch registers, I, the timers or read memory, each ending in a
+// jump, a skip, or before the first instruction the JIT leaves to the interpreter (drawing, keys, random
+// numbers, the stack, memory writes, the flags). BNNN jumps back out to the interpreter, as do blocks
+// whose code keeps being written over. Loops, like waiting on the delay timer, then run without leaving
+// native code.
+//
+// Compiled code runs whole basic blocks up to an instruction limit, so the interpreter can stop it at the
+// end of a frame or before a key event. It is compiled for one set of quirks and dropped when the quirks
+// change or its memory is written; the machine code of dropped code stays allocated until the Jit is
+// dropped, which the limit on rewrites keeps bounded.
+
+use crate::instruction::Instruction;
+use crate::quirks::Quirks;
+use cranelift_codegen::ir::condcodes::IntCC;
+use cranelift_codegen::ir::{types, AbiParam, Block as Label, InstBuilder, MemFlags, Value};
+use cranelift_codegen::settings::{self, Configurable};
+use cranelift_codegen::Context;
+use cranelift_frontend::{FunctionBuilder, FunctionBuilderContext, Variable};
+use cranelift_jit::{JITBuilder, JITModule};
+use cranelift_module::{default_libcall_names, Module};
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+
+const MEMORY_SIZE: usize = 4096;
+const MAX_INSTRUCTIONS: usize = 64; // compiled from one entry point
+const MAX_REWRITES: u32 = 4; // code written over this many times is left to the interpreter
+
+// machine state compiled code reads and writes, laid out for it
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct State {
+    pub v: [u8; 16],
+    pub i: u16,
+    pub pc: u16,
+    pub delay_timer: u8,
+    pub sound_timer: u8,
+}
+
+const I_OFFSET: i32 = 16;
+const PC_OFFSET: i32 = 18;
+const DELAY_TIMER_OFFSET: i32 = 20;
+const SOUND_TIMER_OFFSET: i32 = 21;
+
+// runs instructions from state.pc, at most limit of them, and returns how many it ran
+type CompiledFn = unsafe extern "C" fn(*mut State, *const u8, u64) -> u64;
+
+#[derive(Clone, Copy)]
+pub struct Compiled {
+    function: CompiledFn,
+}
+
+impl Compiled {
+    // runs whole basic blocks while they fit in limit instructions, leaving pc on the next instruction;
+    // runs none when the first block doesn't fit
+    pub fn run(&self, state: &mut State, memory: &[u8; MEMORY_SIZE], limit: usize) -> usize {
+        //SAFETY: the function was compiled for exactly this State layout and only reads memory at
+        //addresses masked to 12 bits
+        unsafe { (self.function)(state, memory.as_ptr(), limit as u64) as usize }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Entry {
+    Compiled(Compiled),
+    Interpreted, // the first instruction is one the JIT leaves to the interpreter
+}
+
+pub struct Jit {
+    module: JITModule,
+    context: Context,
+    builder_context: FunctionBuilderContext,
+    entries: Vec<Option<Entry>>, // by entry address
+    covered: Vec<Vec<u16>>,      // by address, the entries whose code includes that byte
+    rewrites: Vec<u32>,          // by entry address, times its code was written over
+    quirks: Quirks,
+    failures: u64, // code Cranelift failed to compile, which the interpreter runs instead
+}
+
+impl fmt::Debug for Jit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let compiled = self.entries.iter().filter(|entry| matches!(entry, Some(Entry::Compiled(_)))).count();
+        write!(f, "Jit {{ {} entry points compiled }}", compiled)
+    }
+}
+
+impl Jit {
+    pub fn new() -> Result<Self, String> {
+        let mut flags = settings::builder();
+        flags.set("opt_level", "speed").map_err(|e| e.to_string())?;
+        let isa = cranelift_native::builder()
+            .map_err(|e| format!("the JIT does not support this machine: {}", e))?
+            .finish(settings::Flags::new(flags))
+            .map_err(|e| e.to_string())?;
+        let module = JITModule::new(JITBuilder::with_isa(isa, default_libcall_names()));
+        Ok(Jit {
+            context: module.make_context(),
+            module,
+            builder_context: FunctionBuilderContext::new(),
+            entries: vec![None; MEMORY_SIZE],
+            covered: vec![Vec::new(); MEMORY_SIZE],
+            rewrites: vec![0; MEMORY_SIZE],
+            quirks: Quirks::default(),
+            failures: 0,
+        })
+    }
+
+    // the code for address, compiling it the first time; None when the interpreter should run it
+    pub fn compiled(&mut self, address: u16, memory: &[u8; MEMORY_SIZE], quirks: Quirks) -> Option<Compiled> {
+        if quirks != self.quirks {
+            self.clear();
+            self.quirks = quirks;
+        }
+        let address = address as usize % MEMORY_SIZE;
+        if self.rewrites[address] >= MAX_REWRITES {
+            return None;
+        }
+        let entry = match self.entries[address] {
+            Some(entry) => entry,
+            None => self.compile(address as u16, memory),
+        };
+        match entry {
+            Entry::Compiled(compiled) => Some(compiled),
+            Entry::Interpreted => None,
+        }
+    }
+
+    // drops the code that includes the byte at address
+    pub fn invalidate(&mut self, address: u16) {
+        for entry in std::mem::take(&mut self.covered[address as usize % MEMORY_SIZE]) {
+            if let Some(Entry::Compiled(_)) = self.entries[entry as usize].take() {
+                self.rewrites[entry as usize] += 1;
+            }
+        }
+    }
+
+    // times compiling failed and the code was left to the interpreter
+    pub fn failures(&self) -> u64 {
+        self.failures
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.fill(None);
+        self.covered.iter_mut().for_each(Vec::clear);
+        self.rewrites.fill(0);
+    }
+
+    fn compile(&mut self, entry: u16, memory: &[u8; MEMORY_SIZE]) -> Entry {
+        let blocks = basic_blocks(entry, memory);
+        let compiled = match blocks.is_empty() {
+            true => Entry::Interpreted,
+            false => match self.define(&blocks) {
+                Ok(function) => Entry::Compiled(Compiled { function }),
+                //the interpreter runs it just the same, so this only shows in failures()
+                Err(_) => {
+                    self.failures += 1;
+                    Entry::Interpreted
+                }
+            },
+        };
+
+        let mut addresses: Vec<usize> = match compiled {
+            Entry::Compiled(_) => blocks
+                .iter()
+                .flat_map(|block| (0..block.instructions.len() * 2).map(|offset| block.start as usize + offset))
+                .collect(),
+            Entry::Interpreted => vec![entry as usize, entry as usize + 1],
+        };
+        addresses.sort_unstable();
+        addresses.dedup();
+        for address in addresses {
+            self.covered[address % MEMORY_SIZE].push(entry);
+        }
+        self.entries[entry as usize] = Some(compiled);
+        compiled
+    }
+
+    fn define(&mut self, blocks: &[BasicBlock]) -> Result<CompiledFn, String> {
+        let pointer = self.module.target_config().pointer_type();
+        self.module.clear_context(&mut self.context);
+        let signature = &mut self.context.func.signature;
+        signature.params.extend([AbiParam::new(pointer), AbiParam::new(pointer), AbiParam::new(types::I64)]);
+        signature.returns.push(AbiParam::new(types::I64));
+
+        let builder = FunctionBuilder::new(&mut self.context.func, &mut self.builder_context);
+        Emitter::new(builder, pointer, self.quirks).function(blocks);
+
+        let id = self.module.declare_anonymous_function(&self.context.func.signature).map_err(|e| e.to_string())?;
+        self.module.define_function(id, &mut self.context).map_err(|e| e.to_string())?;
+        self.module.finalize_definitions().map_err(|e| e.to_string())?;
+        let code = self.module.get_finalized_function(id);
+        //SAFETY: the function was just built with the signature CompiledFn has
+        Ok(unsafe { std::mem::transmute::<*const u8, CompiledFn>(code) })
+    }
+}
+
+struct BasicBlock {
+    start: u16,
+    instructions: Vec<Instruction>,
+}
+
+impl BasicBlock {
+    // address after the last instruction
+    fn end(&self) -> u16 {
+        (self.start + self.instructions.len() as u16 * 2) & 0x0FFF
+    }
+
+    // where it can go next: targets of its jump or skip, or the instruction after it
+    fn successors(&self) -> Vec<u16> {
+        let end = self.end();
+        match self.instructions.last() {
+            Some(Instruction::Jump(address)) => vec![*address],
+            Some(Instruction::JumpWithOffset(..)) => Vec::new(),
+            Some(&instruction) if is_skip(instruction) => vec![end, (end + 2) & 0x0FFF],
+            _ => vec![end],
+        }
+    }
+}
+
+// the basic blocks reachable from entry, entry's first; an empty list when entry can't be compiled
+fn basic_blocks(entry: u16, memory: &[u8; MEMORY_SIZE]) -> Vec<BasicBlock> {
+    let mut blocks: Vec<BasicBlock> = Vec::new();
+    let mut queue = VecDeque::from([entry]);
+    let mut total = 0;
+    while let Some(start) = queue.pop_front() {
+        if total >= MAX_INSTRUCTIONS || blocks.iter().any(|block| block.start == start) {
+            continue;
+        }
+        let mut instructions = Vec::new();
+        let mut address = start as usize;
+        while total + instructions.len() < MAX_INSTRUCTIONS && address + 1 < MEMORY_SIZE {
+            let instruction = Instruction::decode(u16::from_be_bytes([memory[address], memory[address + 1]]));
+            if !compiles(instruction) {
+                break;
+            }
+            instructions.push(instruction);
+            address += 2;
+            if ends_block(instruction) {
+                break;
+            }
+        }
+        if instructions.is_empty() {
+            continue;
+        }
+        total += instructions.len();
+        let block = BasicBlock { start, instructions };
+        queue.extend(block.successors());
+        blocks.push(block);
+    }
+    blocks
+}
+
+fn compiles(instruction: Instruction) -> bool {
+    use Instruction::*;
+    matches!(
+        instruction,
+        Jump(..) | SkipIfEqual(..) | SkipIfNotEqual(..) | SkipIfRegistersEqual(..) | Load(..) | Add(..) | Copy(..) | Or(..)
+            | And(..) | Xor(..) | AddRegisters(..) | Subtract(..) | ShiftRight(..) | SubtractFrom(..) | ShiftLeft(..)
+            | SkipIfRegistersNotEqual(..) | SetIndex(..) | JumpWithOffset(..) | ReadDelay(..) | SetDelay(..) | SetSound(..)
+            | AddToIndex(..) | Character(..) | LoadRegisters(..)
+    )
+}
+
+fn is_skip(instruction: Instruction) -> bool {
+    use Instruction::*;
+    matches!(instruction, SkipIfEqual(..) | SkipIfNotEqual(..) | SkipIfRegistersEqual(..) | SkipIfRegistersNotEqual(..))
+}
+
+fn ends_block(instruction: Instruction) -> bool {
+    matches!(instruction, Instruction::Jump(..) | Instruction::JumpWithOffset(..)) || is_skip(instruction)
+}
+
+// variables past the 16 registers
+const I: usize = 16;
+const DELAY_TIMER: usize = 17;
+const SOUND_TIMER: usize = 18;
+const COUNT: usize = 19; // instructions run so far
+
+fn var(index: usize) -> Variable {
+    Variable::from_u32(index as u32)
+}
+
+// emits one function, keeping the machine state in SSA variables that are loaded on entry and stored on exit
+struct Emitter<'a> {
+    builder: FunctionBuilder<'a>,
+    pointer: types::Type,
+    quirks: Quirks,
+    state: Value,
+    memory: Value,
+    limit: Value,
+    exit: Label, // takes the next pc
+    labels: HashMap<u16, Label>,
+}
+
+impl<'a> Emitter<'a> {
+    fn new(mut builder: FunctionBuilder<'a>, pointer: types::Type, quirks: Quirks) -> Self {
+        let entry = builder.create_block();
+        builder.append_block_params_for_function_params(entry);
+        builder.switch_to_block(entry);
+        let params = builder.block_params(entry);
+        let (state, memory, limit) = (params[0], params[1], params[2]);
+        let exit = builder.create_block();
+        builder.append_block_param(exit, types::I16);
+        Emitter { builder, pointer, quirks, state, memory, limit, exit, labels: HashMap::new() }
+    }
+
+    fn function(mut self, blocks: &[BasicBlock]) {
+        let flags = MemFlags::trusted();
+        for x in 0..16 {
+            let value = self.builder.ins().load(types::I8, flags, self.state, x as i32);
+            self.builder.declare_var(var(x), types::I8);
+            self.builder.def_var(var(x), value);
+        }
+        for (variable, offset, ty) in [(I, I_OFFSET, types::I16), (DELAY_TIMER, DELAY_TIMER_OFFSET, types::I8), (SOUND_TIMER, SOUND_TIMER_OFFSET, types::I8)] {
+            let value = self.builder.ins().load(ty, flags, self.state, offset);
+            self.builder.declare_var(var(variable), ty);
+            self.builder.def_var(var(variable), value);
+        }
+        let zero = self.builder.ins().iconst(types::I64, 0);
+        self.builder.declare_var(var(COUNT), types::I64);
+        self.builder.def_var(var(COUNT), zero);
+
+        for block in blocks {
+            let label = self.builder.create_block();
+            self.labels.insert(block.start, label);
+        }
+        self.goto(blocks[0].start);
+        for block in blocks {
+            self.basic_block(block);
+        }
+
+        self.builder.switch_to_block(self.exit);
+        let pc = self.builder.block_params(self.exit)[0];
+        for x in 0..16usize {
+            let value = self.get(x);
+            self.builder.ins().store(flags, value, self.state, x as i32);
+        }
+        for (variable, offset) in [(I, I_OFFSET), (DELAY_TIMER, DELAY_TIMER_OFFSET), (SOUND_TIMER, SOUND_TIMER_OFFSET)] {
+            let value = self.get(variable);
+            self.builder.ins().store(flags, value, self.state, offset);
+        }
+        self.builder.ins().store(flags, pc, self.state, PC_OFFSET);
+        let count = self.get(COUNT);
+        self.builder.ins().return_(&[count]);
+        self.builder.seal_all_blocks();
+        self.builder.finalize();
+    }
+
+    // a block runs only if all of it fits under the limit, otherwise the function returns before it
+    fn basic_block(&mut self, block: &BasicBlock) {
+        self.builder.switch_to_block(self.labels[&block.start]);
+        let count = self.get(COUNT);
+        let count = self.builder.ins().iadd_imm(count, block.instructions.len() as i64);
+        let fits = self.builder.ins().icmp(IntCC::UnsignedLessThanOrEqual, count, self.limit);
+        let body = self.builder.create_block();
+        let start = self.builder.ins().iconst(types::I16, block.start as i64);
+        self.builder.ins().brif(fits, body, &[], self.exit, &[start]);
+        self.builder.switch_to_block(body);
+        self.set(COUNT, count);
+
+        let (&last, straight) = block.instructions.split_last().unwrap();
+        for &instruction in straight {
+            self.instruction(instruction);
+        }
+        let end = block.end();
+        match last {
+            Instruction::Jump(address) => self.goto(address),
+            Instruction::JumpWithOffset(address, x) => {
+                let offset = if self.quirks.jump { self.get(x) } else { self.get(0u8) };
+                let offset = self.builder.ins().uextend(types::I16, offset);
+                let target = self.builder.ins().iadd_imm(offset, address as i64);
+                let target = self.builder.ins().band_imm(target, 0x0FFF);
+                self.builder.ins().jump(self.exit, &[target]);
+            }
+            instruction if is_skip(instruction) => {
+                let condition = self.skip_condition(instruction);
+                let (skipped, skipped_args) = self.label((end + 2) & 0x0FFF);
+                let (next, next_args) = self.label(end);
+                self.builder.ins().brif(condition, skipped, &skipped_args, next, &next_args);
+            }
+            instruction => {
+                self.instruction(instruction);
+                self.goto(end);
+            }
+        }
+    }
+
+    // the compiled block at address, or the exit with address as the next pc
+    fn label(&mut self, address: u16) -> (Label, Vec<Value>) {
+        match self.labels.get(&address) {
+            Some(&label) => (label, Vec::new()),
+            None => (self.exit, vec![self.builder.ins().iconst(types::I16, address as i64)]),
+        }
+    }
+
+    fn goto(&mut self, address: u16) {
+        let (label, args) = self.label(address);
+        self.builder.ins().jump(label, &args);
+    }
+
+    fn get(&mut self, variable: impl Into<usize>) -> Value {
+        self.builder.use_var(var(variable.into()))
+    }
+
+    fn set(&mut self, variable: impl Into<usize>, value: Value) {
+        self.builder.def_var(var(variable.into()), value);
+    }
+
+    fn skip_condition(&mut self, instruction: Instruction) -> Value {
+        let (condition, a, b) = match instruction {
+            Instruction::SkipIfEqual(x, nn) => (IntCC::Equal, self.get(x), self.builder.ins().iconst(types::I8, nn as i64)),
+            Instruction::SkipIfNotEqual(x, nn) => (IntCC::NotEqual, self.get(x), self.builder.ins().iconst(types::I8, nn as i64)),
+            Instruction::SkipIfRegistersEqual(x, y) => (IntCC::Equal, self.get(x), self.get(y)),
+            Instruction::SkipIfRegistersNotEqual(x, y) => (IntCC::NotEqual, self.get(x), self.get(y)),
+            _ => unreachable!("{:?} doesn't skip", instruction),
+        };
+        self.builder.ins().icmp(condition, a, b)
+    }
+
+    fn instruction(&mut self, instruction: Instruction) {
+        use Instruction::*;
+        match instruction {
+            Load(x, nn) => {
+                let value = self.builder.ins().iconst(types::I8, nn as i64);
+                self.set(x, value);
+            }
+            Add(x, nn) => {
+                let vx = self.get(x);
+                let value = self.builder.ins().iadd_imm(vx, nn as i64);
+                self.set(x, value);
+            }
+            Copy(x, y) => {
+                let vy = self.get(y);
+                self.set(x, vy);
+            }
+            Or(x, y) | And(x, y) | Xor(x, y) => {
+                let (vx, vy) = (self.get(x), self.get(y));
+                let value = match instruction {
+                    Or(..) => self.builder.ins().bor(vx, vy),
+                    And(..) => self.builder.ins().band(vx, vy),
+                    _ => self.builder.ins().bxor(vx, vy),
+                };
+                self.set(x, value);
+                if self.quirks.logic {
+                    let zero = self.builder.ins().iconst(types::I8, 0);
+                    self.set(0xFu8, zero);
+                }
+            }
+            //the flag is set after the result, so it wins when X is F
+            AddRegisters(x, y) => {
+                let (vx, vy) = (self.get(x), self.get(y));
+                let sum = self.builder.ins().iadd(vx, vy);
+                let carry = self.builder.ins().icmp(IntCC::UnsignedLessThan, sum, vx);
+                self.set(x, sum);
+                self.set(0xFu8, carry);
+            }
+            Subtract(x, y) | SubtractFrom(x, y) => {
+                let (vx, vy) = (self.get(x), self.get(y));
+                let (minuend, subtrahend) = if matches!(instruction, Subtract(..)) { (vx, vy) } else { (vy, vx) };
+                let difference = self.builder.ins().isub(minuend, subtrahend);
+                let no_borrow = self.builder.ins().icmp(IntCC::UnsignedGreaterThanOrEqual, minuend, subtrahend);
+                self.set(x, difference);
+                self.set(0xFu8, no_borrow);
+            }
+            ShiftRight(x, y) => {
+                let value = if self.quirks.shift { self.get(x) } else { self.get(y) };
+                let lsb = self.builder.ins().band_imm(value, 1);
+                let shifted = self.builder.ins().ushr_imm(value, 1);
+                self.set(x, shifted);
+                self.set(0xFu8, lsb);
+            }
+            ShiftLeft(x, y) => {
+                let value = if self.quirks.shift { self.get(x) } else { self.get(y) };
+                let msb = self.builder.ins().ushr_imm(value, 7);
+                let shifted = self.builder.ins().ishl_imm(value, 1);
+                self.set(x, shifted);
+                self.set(0xFu8, msb);
+            }
+            SetIndex(address) => {
+                let value = self.builder.ins().iconst(types::I16, address as i64);
+                self.set(I, value);
+            }
+            ReadDelay(x) => {
+                let value = self.get(DELAY_TIMER);
+                self.set(x, value);
+            }
+            SetDelay(x) => {
+                let value = self.get(x);
+                self.set(DELAY_TIMER, value);
+            }
+            SetSound(x) => {
+                let value = self.get(x);
+                self.set(SOUND_TIMER, value);
+            }
+            AddToIndex(x) => {
+                let (i, vx) = (self.get(I), self.get(x));
+                let vx = self.builder.ins().uextend(types::I16, vx);
+                let value = self.builder.ins().iadd(i, vx);
+                self.set(I, value);
+            }
+            Character(x) => {
+                let vx = self.get(x);
+                let digit = self.builder.ins().band_imm(vx, 0xF);
+                let digit = self.builder.ins().uextend(types::I16, digit);
+                let offset = self.builder.ins().imul_imm(digit, 5);
+                let value = self.builder.ins().iadd_imm(offset, 0x050);
+                self.set(I, value);
+            }
+            LoadRegisters(x) => {
+                let i = self.get(I);
+                for register in 0..=x {
+                    let address = self.builder.ins().iadd_imm(i, register as i64);
+                    let address = self.builder.ins().band_imm(address, 0x0FFF);
+                    let address = self.builder.ins().uextend(self.pointer, address);
+                    let address = self.builder.ins().iadd(self.memory, address);
+                    let value = self.builder.ins().load(types::I8, MemFlags::trusted(), address, 0);
+                    self.set(register, value);
+                }
+                if !self.quirks.memory_leave_i_unchanged {
+                    let increment = if self.quirks.memory_increment_by_x { x } else { x + 1 };
+                    let value = self.builder.ins().iadd_imm(i, increment as i64);
+                    self.set(I, value);
+                }
+            }
+            _ => unreachable!("{:?} is left to the interpreter", instruction),
+        }
+    }
+}
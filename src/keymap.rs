@@ -0,0 +1,323 @@
+// Host key bindings for the 16-key keypad and the emulator hotkeys, loaded from a TOML file:
+//
+//     [keypad]
+//     "1" = ["Key1"]
+//     "4" = ["A", "Q"]       # several host keys can press the same CHIP-8 key
+//
+//     [hotkeys]
+//     pause = ["P", "Pause"]
+//
+//     [roms.607c4f7f4e4dce9f99d96b3182bfe7e88bb090ee.keypad]     # overrides for one ROM, by its SHA-1
+//     "1" = ["Up"]
+//     "4" = ["Down"]
+//
+// Host key names are minifb::Key variant names (case-insensitive), and bare digits work as well.
+
+use crate::keypad::NUM_KEYS;
+use minifb::Key;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// the order keys are asked for when rebinding, which follows the physical keypad layout
+pub const KEYPAD_LAYOUT: [usize; NUM_KEYS] = [0x1, 0x2, 0x3, 0xC, 0x4, 0x5, 0x6, 0xD, 0x7, 0x8, 0x9, 0xE, 0xA, 0x0, 0xB, 0xF];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Hotkey {
+    Quit,
+    Pause,
+    Reset,
+    SaveState,
+    LoadState,
+    Rebind,
+    CycleFilter,
+    IntegerScale,
+    Fullscreen,
+    Record,
+    Screenshot,
+    Movie,
+    Tas,
+    FrameAdvance,
+    FrameBack,
+    TruncateInput,
+}
+
+impl Hotkey {
+    pub const ALL: [Hotkey; 16] = [
+        Hotkey::Quit,
+        Hotkey::Pause,
+        Hotkey::Reset,
+        Hotkey::SaveState,
+        Hotkey::LoadState,
+        Hotkey::Rebind,
+        Hotkey::CycleFilter,
+        Hotkey::IntegerScale,
+        Hotkey::Fullscreen,
+        Hotkey::Record,
+        Hotkey::Screenshot,
+        Hotkey::Movie,
+        Hotkey::Tas,
+        Hotkey::FrameAdvance,
+        Hotkey::FrameBack,
+        Hotkey::TruncateInput,
+    ];
+
+    // name used in the [hotkeys] table
+    pub fn name(self) -> &'static str {
+        match self {
+            Hotkey::Quit => "quit",
+            Hotkey::Pause => "pause",
+            Hotkey::Reset => "reset",
+            Hotkey::SaveState => "save_state",
+            Hotkey::LoadState => "load_state",
+            Hotkey::Rebind => "rebind",
+            Hotkey::CycleFilter => "cycle_filter",
+            Hotkey::IntegerScale => "integer_scale",
+            Hotkey::Fullscreen => "fullscreen",
+            Hotkey::Record => "record",
+            Hotkey::Screenshot => "screenshot",
+            Hotkey::Movie => "movie",
+            Hotkey::Tas => "tas",
+            Hotkey::FrameAdvance => "frame_advance",
+            Hotkey::FrameBack => "frame_back",
+            Hotkey::TruncateInput => "truncate_input",
+        }
+    }
+
+    fn default_keys(self) -> Vec<Key> {
+        match self {
+            Hotkey::Quit => vec![Key::Escape],
+            Hotkey::Pause => vec![Key::P],
+            Hotkey::Reset => vec![Key::F5],
+            Hotkey::SaveState => vec![Key::F6],
+            Hotkey::LoadState => vec![Key::F7],
+            Hotkey::Rebind => vec![Key::F8],
+            Hotkey::CycleFilter => vec![Key::F2],
+            Hotkey::IntegerScale => vec![Key::F3],
+            Hotkey::Fullscreen => vec![Key::F11],
+            Hotkey::Record => vec![Key::F10],
+            Hotkey::Screenshot => vec![Key::F12],
+            Hotkey::Movie => vec![Key::F9],
+            Hotkey::Tas => vec![Key::F4],
+            Hotkey::FrameAdvance => vec![Key::Period],
+            Hotkey::FrameBack => vec![Key::Comma],
+            Hotkey::TruncateInput => vec![Key::Delete],
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum KeymapError {
+    Io(io::Error),
+    Parse(toml::de::Error),
+    UnknownKey(String),
+    UnknownHotkey(String),
+    InvalidKeypadKey(String),
+    InvalidRomSection(String),
+}
+
+impl fmt::Display for KeymapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeymapError::Io(e) => write!(f, "{}", e),
+            KeymapError::Parse(e) => write!(f, "{}", e),
+            KeymapError::UnknownKey(name) => write!(f, "unknown host key \"{}\"", name),
+            KeymapError::UnknownHotkey(name) => write!(f, "unknown hotkey \"{}\"", name),
+            KeymapError::InvalidKeypadKey(name) => write!(f, "\"{}\" is not a CHIP-8 key, use 0-9 or A-F", name),
+            KeymapError::InvalidRomSection(name) => write!(f, "[roms.{}] is not named by a ROM's SHA-1 in lowercase hex", name),
+        }
+    }
+}
+
+impl std::error::Error for KeymapError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Keymap {
+    pub keypad: [Vec<Key>; NUM_KEYS],
+    pub hotkeys: BTreeMap<Hotkey, Vec<Key>>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let mut keypad: [Vec<Key>; NUM_KEYS] = Default::default();
+        let qwerty = [
+            (0x1, Key::Key1), (0x2, Key::Key2), (0x3, Key::Key3), (0xC, Key::Key4),
+            (0x4, Key::Q),    (0x5, Key::W),    (0x6, Key::E),    (0xD, Key::R),
+            (0x7, Key::A),    (0x8, Key::S),    (0x9, Key::D),    (0xE, Key::F),
+            (0xA, Key::Z),    (0x0, Key::X),    (0xB, Key::C),    (0xF, Key::V),
+        ];
+        for (chip8_key, host_key) in qwerty {
+            keypad[chip8_key].push(host_key);
+        }
+
+        let hotkeys = Hotkey::ALL.iter().map(|&hotkey| (hotkey, hotkey.default_keys())).collect();
+
+        Keymap { keypad, hotkeys }
+    }
+}
+
+impl Keymap {
+    pub fn keypad_states(&self, pressed: &[Key]) -> [bool; NUM_KEYS] {
+        let mut states = [false; NUM_KEYS];
+        for (state, bindings) in states.iter_mut().zip(self.keypad.iter()) {
+            *state = bindings.iter().any(|key| pressed.contains(key));
+        }
+        states
+    }
+
+    pub fn keypad_index(&self, key: Key) -> Option<usize> {
+        self.keypad.iter().position(|bindings| bindings.contains(&key))
+    }
+
+    pub fn hotkey_keys(&self, hotkey: Hotkey) -> &[Key] {
+        self.hotkeys.get(&hotkey).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    pub fn hotkey_for(&self, key: Key) -> Option<Hotkey> {
+        Hotkey::ALL.into_iter().find(|&hotkey| self.hotkey_keys(hotkey).contains(&key))
+    }
+
+    fn apply(&mut self, section: &KeymapSection) -> Result<(), KeymapError> {
+        for (chip8_key, names) in &section.keypad {
+            let index = parse_keypad_key(chip8_key)?;
+            self.keypad[index] = parse_keys(names)?;
+        }
+        for (hotkey_name, names) in &section.hotkeys {
+            let hotkey = Hotkey::ALL
+                .into_iter()
+                .find(|hotkey| hotkey.name() == hotkey_name)
+                .ok_or_else(|| KeymapError::UnknownHotkey(hotkey_name.clone()))?;
+            self.hotkeys.insert(hotkey, parse_keys(names)?);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct KeymapSection {
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    keypad: BTreeMap<String, Vec<String>>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    hotkeys: BTreeMap<String, Vec<String>>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeymapFile {
+    #[serde(flatten)]
+    global: KeymapSection,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    roms: BTreeMap<String, KeymapSection>,
+}
+
+impl KeymapFile {
+    // <config dir>/rust-chip8-emulator/keymap.toml
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("rust-chip8-emulator").join("keymap.toml"))
+    }
+
+    // a missing file is the same as an empty one, so everything keeps its default binding
+    pub fn load(path: &Path) -> Result<Self, KeymapError> {
+        let file: KeymapFile = match fs::read_to_string(path) {
+            Ok(contents) => toml::from_str(&contents).map_err(KeymapError::Parse)?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(KeymapFile::default()),
+            Err(e) => return Err(KeymapError::Io(e)),
+        };
+        //sections named after a file would never match, since ROMs are looked up by their contents
+        if let Some(name) = file.roms.keys().find(|sha1| sha1.len() != 40 || !sha1.chars().all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c))) {
+            return Err(KeymapError::InvalidRomSection(name.clone()));
+        }
+        Ok(file)
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let contents = toml::to_string_pretty(self).map_err(io::Error::other)?;
+        fs::write(path, contents)
+    }
+
+    // defaults, then the global tables, then the tables for the ROM with this SHA-1
+    pub fn keymap_for(&self, rom_sha1: &str) -> Result<Keymap, KeymapError> {
+        let mut keymap = Keymap::default();
+        keymap.apply(&self.global)?;
+        if let Some(section) = self.roms.get(rom_sha1) {
+            keymap.apply(section)?;
+        }
+        Ok(keymap)
+    }
+
+    // stores the whole keypad of keymap as the global binding, used after rebinding in the window
+    pub fn set_keypad(&mut self, keymap: &Keymap) {
+        self.global.keypad.clear();
+        for (index, bindings) in keymap.keypad.iter().enumerate() {
+            let names = bindings.iter().map(|key| key_name(*key)).collect();
+            self.global.keypad.insert(format!("{:X}", index), names);
+        }
+    }
+}
+
+pub fn key_name(key: Key) -> String {
+    format!("{:?}", key)
+}
+
+pub fn parse_key(name: &str) -> Option<Key> {
+    let name = name.trim();
+    if name.len() == 1 {
+        if let Some(key) = name.chars().next().and_then(key_from_char) {
+            return Some(key);
+        }
+    }
+    ALL_KEYS.iter().copied().find(|key| key_name(*key).eq_ignore_ascii_case(name))
+}
+
+// letter and digit keys for a typed character, used by the terminal frontend
+pub fn key_from_char(c: char) -> Option<Key> {
+    let c = c.to_ascii_uppercase();
+    match c {
+        '0'..='9' => ALL_KEYS.get(c as usize - '0' as usize).copied(),
+        'A'..='Z' => ALL_KEYS.get(10 + c as usize - 'A' as usize).copied(),
+        ' ' => Some(Key::Space),
+        ',' => Some(Key::Comma),
+        '.' => Some(Key::Period),
+        _ => None,
+    }
+}
+
+fn parse_keys(names: &[String]) -> Result<Vec<Key>, KeymapError> {
+    names
+        .iter()
+        .map(|name| parse_key(name).ok_or_else(|| KeymapError::UnknownKey(name.clone())))
+        .collect()
+}
+
+fn parse_keypad_key(name: &str) -> Result<usize, KeymapError> {
+    let trimmed = name.trim().trim_start_matches("0x");
+    match usize::from_str_radix(trimmed, 16) {
+        Ok(index) if trimmed.len() == 1 && index < NUM_KEYS => Ok(index),
+        _ => Err(KeymapError::InvalidKeypadKey(name.to_string())),
+    }
+}
+
+// every minifb key in declaration order, digits and letters first as key_from_char relies on
+const ALL_KEYS: [Key; 106] = [
+    Key::Key0, Key::Key1, Key::Key2, Key::Key3, Key::Key4, Key::Key5, Key::Key6, Key::Key7, Key::Key8, Key::Key9,
+    Key::A, Key::B, Key::C, Key::D, Key::E, Key::F, Key::G, Key::H, Key::I, Key::J, Key::K, Key::L, Key::M,
+    Key::N, Key::O, Key::P, Key::Q, Key::R, Key::S, Key::T, Key::U, Key::V, Key::W, Key::X, Key::Y, Key::Z,
+    Key::F1, Key::F2, Key::F3, Key::F4, Key::F5, Key::F6, Key::F7, Key::F8, Key::F9, Key::F10, Key::F11,
+    Key::F12, Key::F13, Key::F14, Key::F15,
+    Key::Down, Key::Left, Key::Right, Key::Up,
+    Key::Apostrophe, Key::Backquote, Key::Backslash, Key::Comma, Key::Equal, Key::LeftBracket, Key::Minus,
+    Key::Period, Key::RightBracket, Key::Semicolon, Key::Slash,
+    Key::Backspace, Key::Delete, Key::End, Key::Enter, Key::Escape, Key::Home, Key::Insert, Key::Menu,
+    Key::PageDown, Key::PageUp, Key::Pause, Key::Space, Key::Tab,
+    Key::NumLock, Key::CapsLock, Key::ScrollLock,
+    Key::LeftShift, Key::RightShift, Key::LeftCtrl, Key::RightCtrl,
+    Key::NumPad0, Key::NumPad1, Key::NumPad2, Key::NumPad3, Key::NumPad4, Key::NumPad5, Key::NumPad6,
+    Key::NumPad7, Key::NumPad8, Key::NumPad9, Key::NumPadDot, Key::NumPadSlash, Key::NumPadAsterisk,
+    Key::NumPadMinus, Key::NumPadPlus, Key::NumPadEnter,
+    Key::LeftAlt, Key::RightAlt, Key::LeftSuper, Key::RightSuper,
+];
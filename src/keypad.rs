@@ -0,0 +1,111 @@
+// The 16-key keypad as a queue of press and release events. Frontends push events tagged with the
+// instruction cycle they should take effect on, and the interpreter applies them before each instruction,
+// so a tap shorter than a frame is still seen by the program.
+
+use std::collections::VecDeque;
+
+pub const NUM_KEYS: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyEvent {
+    pub key: u8,
+    pub pressed: bool,
+    pub cycle: u64, // first instruction cycle that sees the new state
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Keypad {
+    states: [bool; NUM_KEYS],
+    events: VecDeque<KeyEvent>,
+    waiting: bool,
+    latched: Option<u8>, // first key to go down since FX0A started waiting, counted once it's released
+}
+
+impl Keypad {
+    pub fn is_pressed(&self, key: u8) -> bool {
+        self.states[(key & 0x0F) as usize]
+    }
+
+    pub fn states(&self) -> [bool; NUM_KEYS] {
+        self.states
+    }
+
+    // state of key once every queued event has been applied
+    pub fn queued_state(&self, key: u8) -> bool {
+        self.events
+            .iter()
+            .rev()
+            .find(|event| event.key == key)
+            .map_or(self.is_pressed(key), |event| event.pressed)
+    }
+
+    // queues a change, ignoring events that would not change the queued state (like key repeat);
+    // events are kept in cycle order and never scheduled before ones already queued for the same key
+    pub fn push(&mut self, key: u8, pressed: bool, cycle: u64) {
+        let key = key & 0x0F;
+        if self.queued_state(key) == pressed {
+            return;
+        }
+        let cycle = self
+            .events
+            .iter()
+            .filter(|event| event.key == key)
+            .map(|event| event.cycle)
+            .max()
+            .map_or(cycle, |last| last.max(cycle));
+        let position = self.events.iter().position(|event| event.cycle > cycle).unwrap_or(self.events.len());
+        self.events.insert(position, KeyEvent { key, pressed, cycle });
+    }
+
+    // queues whatever events turn the queued state into states, all at the same cycle
+    pub fn set_states(&mut self, states: [bool; NUM_KEYS], cycle: u64) {
+        for (key, &pressed) in states.iter().enumerate() {
+            self.push(key as u8, pressed, cycle);
+        }
+    }
+
+    pub fn pending(&self) -> impl Iterator<Item = &KeyEvent> {
+        self.events.iter()
+    }
+
+    // whether an event is queued for cycle or earlier
+    pub fn is_due(&self, cycle: u64) -> bool {
+        self.events.front().is_some_and(|event| event.cycle <= cycle)
+    }
+
+    // applies every queued event due at or before cycle, returning them in order
+    pub fn apply_until(&mut self, cycle: u64) -> Vec<KeyEvent> {
+        let mut applied = Vec::new();
+        while self.is_due(cycle) {
+            let Some(event) = self.events.pop_front() else { break };
+            self.states[event.key as usize] = event.pressed;
+            if event.pressed && self.waiting && self.latched.is_none() {
+                self.latched = Some(event.key);
+            }
+            applied.push(event);
+        }
+        applied
+    }
+
+    // FX0A: like the COSMAC VIP, a key counts once it has been pressed and then released while waiting.
+    // Returns the key when that happens and None while the instruction should keep blocking.
+    pub fn wait_for_key(&mut self) -> Option<u8> {
+        if !self.waiting {
+            self.waiting = true;
+            self.latched = None;
+        }
+
+        match self.latched {
+            Some(key) if !self.is_pressed(key) => {
+                self.waiting = false;
+                self.latched = None;
+                Some(key)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        *self = Keypad::default();
+    }
+}
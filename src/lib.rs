@@ -0,0 +1,29 @@
+pub mod asm;
+pub mod cartridge;
+pub mod chip8;
+pub mod config;
+pub mod disasm;
+pub mod display;
+pub mod filters;
+pub mod flags;
+pub mod gym;
+pub mod instruction;
+#[cfg(feature = "jit")]
+pub mod jit;
+pub mod keymap;
+pub mod keypad;
+pub mod library;
+pub mod metadata;
+pub mod movie;
+pub mod octo;
+pub mod quirks;
+pub mod recompiler;
+pub mod rom;
+pub mod screenshot;
+pub mod snapshot;
+pub mod stack;
+pub mod tas;
+pub mod terminal;
+pub mod testing;
+pub mod timers;
+pub mod video;
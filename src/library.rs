@@ -0,0 +1,166 @@
+// The game library: ROMs found by scanning directories, including the programs inside zip and gzip
+// archives, titled from the ROM database where known, plus the recently played and favorite games kept in library.toml in the config directory.
+
+use crate::metadata::{self, RomInfo};
+use crate::rom::{self, ROM_EXTENSIONS};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub const PAGE_SIZE: usize = 10;
+const MAX_RECENT: usize = 10;
+
+#[derive(Debug)]
+pub enum LibraryError {
+    Io(io::Error),
+    Parse(toml::de::Error),
+}
+
+impl fmt::Display for LibraryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LibraryError::Io(e) => write!(f, "{}", e),
+            LibraryError::Parse(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for LibraryError {}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LibraryFile {
+    #[serde(default = "default_directories")]
+    pub directories: Vec<PathBuf>,
+    #[serde(default)]
+    pub recent: Vec<PathBuf>, // most recently played first
+    #[serde(default)]
+    pub favorites: Vec<PathBuf>,
+}
+
+impl Default for LibraryFile {
+    fn default() -> Self {
+        LibraryFile {
+            directories: default_directories(),
+            recent: Vec::new(),
+            favorites: Vec::new(),
+        }
+    }
+}
+
+fn default_directories() -> Vec<PathBuf> {
+    vec![PathBuf::from("roms")]
+}
+
+impl LibraryFile {
+    // <config dir>/rust-chip8-emulator/library.toml
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("rust-chip8-emulator").join("library.toml"))
+    }
+
+    // a missing file scans the bundled roms directory
+    pub fn load(path: &Path) -> Result<Self, LibraryError> {
+        match fs::read_to_string(path) {
+            Ok(contents) => toml::from_str(&contents).map_err(LibraryError::Parse),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(LibraryFile::default()),
+            Err(e) => Err(LibraryError::Io(e)),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let contents = toml::to_string_pretty(self).map_err(io::Error::other)?;
+        fs::write(path, contents)
+    }
+
+    pub fn add_recent(&mut self, path: &Path) {
+        self.recent.retain(|recent| recent != path);
+        self.recent.insert(0, path.to_path_buf());
+        self.recent.truncate(MAX_RECENT);
+    }
+
+    pub fn is_favorite(&self, path: &Path) -> bool {
+        self.favorites.iter().any(|favorite| favorite == path)
+    }
+
+    // returns whether path is now a favorite
+    pub fn toggle_favorite(&mut self, path: &Path) -> bool {
+        if self.is_favorite(path) {
+            self.favorites.retain(|favorite| favorite != path);
+            false
+        } else {
+            self.favorites.push(path.to_path_buf());
+            true
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LibraryEntry {
+    pub path: PathBuf,
+    pub info: Option<RomInfo>,
+}
+
+impl LibraryEntry {
+    pub fn title(&self) -> String {
+        match &self.info {
+            Some(info) => info.title.clone(),
+            None => rom::name(&self.path),
+        }
+    }
+
+    // case-insensitive match on the title, file name or authors
+    pub fn matches(&self, query: &str) -> bool {
+        let query = query.to_lowercase();
+        let authors = self.info.iter().flat_map(|info| info.authors.iter());
+        [self.title(), self.path.to_string_lossy().into_owned()]
+            .into_iter()
+            .chain(authors.cloned())
+            .any(|text| text.to_lowercase().contains(&query))
+    }
+}
+
+impl fmt::Display for LibraryEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.info {
+            Some(info) if !info.authors.is_empty() => write!(f, "{} ({}, {})", info.title, info.authors.join(", "), info.release),
+            _ => write!(f, "{} ({})", self.title(), self.path.display()),
+        }
+    }
+}
+
+// every ROM under directories, sorted by title; directories that can't be read are skipped
+pub fn scan(directories: &[PathBuf]) -> Vec<LibraryEntry> {
+    let mut paths = Vec::new();
+    for directory in directories {
+        collect_roms(directory, &mut paths);
+    }
+    paths.sort();
+    paths.dedup();
+
+    let mut entries: Vec<LibraryEntry> = paths.into_iter().map(|path| entry(&path)).collect();
+    entries.sort_by_key(|entry| entry.title().to_lowercase());
+    entries
+}
+
+pub fn entry(path: &Path) -> LibraryEntry {
+    let info = rom::load(path).ok().and_then(|rom| metadata::identify(&rom.data));
+    LibraryEntry { path: path.to_path_buf(), info }
+}
+
+fn collect_roms(directory: &Path, paths: &mut Vec<PathBuf>) {
+    let Ok(read_dir) = fs::read_dir(directory) else { return };
+    for dir_entry in read_dir.flatten() {
+        let path = dir_entry.path();
+        if path.is_dir() {
+            collect_roms(&path, paths);
+        } else if rom::is_archive(&path) {
+            paths.extend(rom::list_archive(&path).unwrap_or_default());
+        } else if path.extension().and_then(|ext| ext.to_str()).is_some_and(|ext| ROM_EXTENSIONS.contains(&ext.to_lowercase().as_str())) {
+            paths.push(path);
+        }
+    }
+}
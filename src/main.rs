@@ -1,78 +1,84 @@
-mod chip8;
-mod display;
-mod timers;
-mod stack;
-use chip8::Chip8;
+use clap::Parser;
+use cli::{Cli, Command};
+use rust_chip8_emulator::chip8::Chip8;
+use rust_chip8_emulator::config::ConfigFile;
+use rust_chip8_emulator::display::{Display, DEFAULT_SCALE};
+use rust_chip8_emulator::keymap::{Keymap, KeymapFile};
+use rust_chip8_emulator::library::{self, LibraryEntry, LibraryFile, PAGE_SIZE};
+use rust_chip8_emulator::metadata;
+use rust_chip8_emulator::rom;
+use rust_chip8_emulator::terminal::{Terminal, TerminalMode};
 use std::io;
-use std::process;
+use std::path::{Path, PathBuf};
+use std::process::{self, ExitCode};
 use std::io::Write;
 
-fn main() {
-    loop {
-        println!("\nWelcome to Arnav and Mikey's CHIP-8 Emulator! The games you can play are listed below.");
-        println!("    1. Astro Dodge");
-        println!("    2. Blitz");
-        println!("    3. Brix");
-        println!("    4. Nim");
-        println!("    5. Pong");
-        println!("    6. Space Invaders");
-
-        let game_choice = loop {
-            print!("Enter a number from 1 to 6 to play a game, or 0 to quit: ");
-            io::stdout().flush().expect("Failed to flush stdout");
+mod cli;
 
-            let mut input = String::new();
-            io::stdin()
-                .read_line(&mut input)
-                .expect("Failed to read input");
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let command = match cli.command {
+        Some(command) => command,
+        None if cli.run.rom.is_some() => Command::Run(cli.run),
+        None => menu(),
+    };
+    match cli::execute(command) {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
 
-            match input.trim().parse::<u32>() {
-                Ok(num) if (0..=6).contains(&num) => break num,
-                _ => println!("\nInvalid input. Enter a number between 1 and 6."),
-            }
-        };
+// the interactive launcher, it only returns by exiting the process
+fn menu() -> ! {
+    let library_path = LibraryFile::default_path();
+    let mut library = match library_path.as_deref().map(LibraryFile::load) {
+        Some(Ok(library)) => library,
+        Some(Err(e)) => {
+            println!("Ignoring game library settings: {}", e);
+            LibraryFile::default()
+        }
+        None => LibraryFile::default(),
+    };
+    let config = match ConfigFile::default_path().as_deref().map(ConfigFile::load) {
+        Some(Ok(config)) => config,
+        Some(Err(e)) => {
+            println!("Ignoring settings file: {}", e);
+            ConfigFile::default()
+        }
+        None => ConfigFile::default(),
+    };
+    //the library's titles come from the ROM database, so it has to be in place first
+    if let Err(e) = cli::use_database(config.database.as_deref()) {
+        println!("Using the bundled ROM database instead: {}", e);
+    }
+    let games = library::scan(&library.directories);
 
-        println!("");
+    loop {
+        println!("\nWelcome to Arnav and Mikey's CHIP-8 Emulator! The games you can play are listed below.");
+        let Some(game) = choose_game(&games, &mut library, library_path.as_deref()) else { process::exit(0) };
 
-        let mut path = "";
-        match game_choice {
-            0 => process::exit(0),
-            1 => {
-                path = "roms/astro_dodge.ch8";
-                println!("Your goal is to make your way through the asteroids field and dodge the asteroids,\n\
-                        scoring points for each asteroid you are able to dodge.\n\
-                        2,Q,E,S will move your ship, W will start the game.");
-            }
-            2 => {
-                path = "roms/blitz.ch8";
-                println!("You are in a plane, and you must destroy the towers of a town.\n\
-                        Your plane is flying left to right, and goes down. Use W to drop a bomb and to start game.\n\
-                        The game ends when you crash yourself on a tower...");
+        println!();
+        //a ROM that can't be loaded never gets as far as a machine
+        let rom = match cli::read_rom(&game.path) {
+            Ok(rom) => rom,
+            Err(e) => {
+                println!("Could not load {}", e);
+                continue;
             }
-            3 => {
-                path = "roms/brix.ch8";
-                println!("Use your slider to break all the bricks! Use Q and E to move left and right.");
-            }
-            4 => {
-                path = "roms/nim.ch8";
-                println!("Each turn, you subtract 1, 2 or 3 from the score, playing against the computer.\n\
-                        Whoever ends up with the last number loses!\n\
-                        Press V to go first, and any other for the computer to go first.\n\
-                        Press 1, 2, or 3 to subtract respective amount of points.");
-            }
-            5 => {
-                path = "roms/pong.ch8";
-                println!("Don't let the ball past you! Use 1 and Q to move.");
-            }
-            6 => {
-                path = "roms/space_invaders.ch8";
-                println!("Destroy the invaders with your ship. Start game and shoot with W, move with Q and E.");
-            }
-            _ => {}
+        };
+        let settings = cli::rom_settings(&config, &rom);
+        let scale = settings.scale.unwrap_or(DEFAULT_SCALE);
+        let (keymap, keymap_path) = load_keymap(settings.keymap.clone(), &metadata::sha1_hex(&rom.data));
+        if let Some(info) = &game.info {
+            println!("{}", info.description);
+            println!("Controls: {}", info.key_hints(&keymap));
         }
 
         let start = loop {
-            print!("\nStart game? 1 to start, 0 to quit: ");
+            print!("\nStart game? 1 to start, 2 to play in this terminal, 3 for terminal with Braille, 0 to quit: ");
             io::stdout().flush().expect("Failed to flush stdout");
 
             let mut input = String::new();
@@ -81,22 +87,135 @@ fn main() {
                 .expect("Failed to read input");
 
             match input.trim().parse::<u32>() {
-                Ok(num) if (0..=1).contains(&num) => break num,
-                _ => println!("\nInvalid input. Enter 0 or 1."),
+                Ok(num) if (0..=3).contains(&num) => break num,
+                _ => println!("\nInvalid input. Enter a number between 0 and 3."),
             }
         };
 
         match start {
             0 => process::exit(0),
             1 => {
-                let mut chip8: Chip8 = Chip8::default();
-                let result = chip8.load_rom(path);
-                if result.is_err() {
-                    println!("Error in reading file");
+                let mut chip8: Chip8 = Chip8::new(Display::new(scale));
+                if let Err(e) = cli::boot(&mut chip8, &rom, &settings) {
+                    println!("Could not start {}", e);
+                    continue;
                 }
+                chip8.set_keymap(keymap, keymap_path);
                 chip8.run();
             }, 
+            2 | 3 => {
+                let mut chip8 = Chip8::new(Display::headless(scale));
+                if let Err(e) = cli::boot(&mut chip8, &rom, &settings) {
+                    println!("Could not start {}", e);
+                    continue;
+                }
+                chip8.set_keymap(keymap, keymap_path);
+                let mode = if start == 2 { TerminalMode::HalfBlocks } else { TerminalMode::Braille };
+                let result = Terminal::new(mode).and_then(|mut terminal| chip8.run_in_terminal(&mut terminal));
+                if let Err(e) = result {
+                    println!("Terminal error: {}", e);
+                }
+            }
             _ => {}
         }
+
+        library.add_recent(&game.path);
+        save_library(&library, library_path.as_deref());
+    }
+}
+
+enum View {
+    All,
+    Search(String),
+    Recent,
+    Favorites,
+}
+
+// pages through the library until a game is picked, None quits
+fn choose_game(games: &[LibraryEntry], library: &mut LibraryFile, library_path: Option<&Path>) -> Option<LibraryEntry> {
+    let mut view = View::All;
+    let mut page = 0;
+
+    loop {
+        let (heading, shown): (String, Vec<LibraryEntry>) = match &view {
+            View::All => (String::from("All games"), games.to_vec()),
+            View::Search(query) => (format!("Games matching \"{}\"", query), games.iter().filter(|game| game.matches(query)).cloned().collect()),
+            View::Recent => (String::from("Recently played"), library.recent.iter().filter(|path| rom::exists(path)).map(|path| library::entry(path)).collect()),
+            View::Favorites => (String::from("Favorites"), games.iter().filter(|game| library.is_favorite(&game.path)).cloned().collect()),
+        };
+        let pages = shown.len().div_ceil(PAGE_SIZE).max(1);
+        page = page.min(pages - 1);
+
+        println!("{} (page {} of {}):", heading, page + 1, pages);
+        if shown.is_empty() {
+            println!("    No games found.");
+        }
+        for (number, game) in shown.iter().enumerate().skip(page * PAGE_SIZE).take(PAGE_SIZE) {
+            let favorite = if library.is_favorite(&game.path) { "*" } else { " " };
+            println!("  {} {}. {}", favorite, number + 1, game);
+        }
+
+        print!("Enter a number to play, n/p for the next/previous page, /text to search, r for recent, f for favorites, f<number> to mark a favorite, a for all, or 0 to quit: ");
+        io::stdout().flush().expect("Failed to flush stdout");
+
+        let mut input = String::new();
+        let read = io::stdin()
+            .read_line(&mut input)
+            .expect("Failed to read input");
+        let input = input.trim();
+        println!();
+
+        //end of input quits, so piping a script into the menu can't loop forever
+        if read == 0 {
+            return None;
+        }
+
+        match input {
+            "0" => return None,
+            "n" => page = (page + 1).min(pages - 1),
+            "p" => page = page.saturating_sub(1),
+            "a" => (view, page) = (View::All, 0),
+            "r" => (view, page) = (View::Recent, 0),
+            "f" => (view, page) = (View::Favorites, 0),
+            _ if input.starts_with('/') => (view, page) = (View::Search(input[1..].trim().to_string()), 0),
+            _ => {
+                let (favorite, number) = match input.strip_prefix('f') {
+                    Some(number) => (true, number.trim()),
+                    None => (false, input),
+                };
+                match number.parse::<usize>() {
+                    Ok(number) if (1..=shown.len()).contains(&number) => {
+                        let game = &shown[number - 1];
+                        if !favorite {
+                            return Some(game.clone());
+                        }
+                        let marked = library.toggle_favorite(&game.path);
+                        println!("{} {} favorites.\n", game.title(), if marked { "added to" } else { "removed from" });
+                        save_library(library, library_path);
+                    }
+                    _ => println!("Invalid input. Enter a number between 1 and {}.\n", shown.len()),
+                }
+            }
+        }
+    }
+}
+
+fn save_library(library: &LibraryFile, path: Option<&Path>) {
+    let Some(path) = path else { return };
+    if let Err(e) = library.save(path) {
+        println!("Failed to save game library settings: {}", e);
+    }
+}
+
+// the keymap file (the default one unless path is given, with any overrides for the ROM with rom_sha1) and
+// where it lives, falling back to the default layout
+fn load_keymap(path: Option<PathBuf>, rom_sha1: &str) -> (Keymap, Option<PathBuf>) {
+    let Some(path) = path.or_else(KeymapFile::default_path) else { return (Keymap::default(), None) };
+    match KeymapFile::load(&path).and_then(|file| file.keymap_for(rom_sha1)) {
+        Ok(keymap) => (keymap, Some(path)),
+        Err(e) => {
+            println!("Ignoring key bindings in {}: {}", path.display(), e);
+            (Keymap::default(), Some(path))
+        }
     }
-}
\ No newline at end of file
+}
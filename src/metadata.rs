@@ -0,0 +1,217 @@
+// ROM identification against the community chip-8-database (https://github.com/chip-8/chip-8-database).
+// sha1-hashes.json maps the SHA-1 of a ROM to its program in programs.json, and platforms.json holds each
+// platform's quirks and speed. A copy of the database covering the bundled ROMs is compiled in, and a
+// checkout of the full database can be used instead with --database or `database` in config.toml.
+
+use crate::display::Palette;
+use crate::keymap::{self, Keymap};
+use crate::quirks::Quirks;
+use serde::Deserialize;
+use sha1::{Digest, Sha1};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+const PROGRAMS: &str = include_str!("../data/chip-8-database/programs.json");
+const SHA1_HASHES: &str = include_str!("../data/chip-8-database/sha1-hashes.json");
+const PLATFORMS: &str = include_str!("../data/chip-8-database/platforms.json");
+
+static EXTERNAL: OnceLock<Database> = OnceLock::new();
+
+#[derive(Debug)]
+pub enum DatabaseError {
+    Io(PathBuf, io::Error),
+    Parse(PathBuf, serde_json::Error),
+    AlreadyInstalled,
+}
+
+impl fmt::Display for DatabaseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DatabaseError::Io(path, e) => write!(f, "{}: {}", path.display(), e),
+            DatabaseError::Parse(path, e) => write!(f, "{}: {}", path.display(), e),
+            DatabaseError::AlreadyInstalled => write!(f, "another ROM database is already in use"),
+        }
+    }
+}
+
+impl std::error::Error for DatabaseError {}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Program {
+    title: String,
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    release: String,
+    #[serde(default)]
+    authors: Vec<String>,
+    #[serde(default)]
+    roms: HashMap<String, Rom>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Rom {
+    description: Option<String>,
+    #[serde(default)]
+    platforms: Vec<String>,
+    #[serde(default)]
+    quirky_platforms: HashMap<String, BTreeMap<String, bool>>,
+    tickrate: Option<usize>,
+    #[serde(default)]
+    keys: BTreeMap<String, u8>,
+    colors: Option<Colors>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Colors {
+    #[serde(default)]
+    pixels: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Platform {
+    id: String,
+    name: String,
+    default_tickrate: usize,
+    quirks: BTreeMap<String, bool>,
+}
+
+// what the database knows about one ROM, with the platform settings already resolved
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RomInfo {
+    pub title: String,
+    pub description: String,
+    pub release: String,
+    pub authors: Vec<String>,
+    pub platform: String, // platform id, like "originalChip8"
+    pub platform_name: String,
+    pub quirks: Quirks,
+    pub speed: usize, // instructions per frame
+    pub palette: Option<Palette>,
+    pub keys: BTreeMap<String, u8>, // action name to CHIP-8 key
+}
+
+impl RomInfo {
+    // "left: Q, right: E" using the host keys bound in keymap
+    pub fn key_hints(&self, keymap: &Keymap) -> String {
+        self.keys
+            .iter()
+            .filter_map(|(action, &chip8_key)| {
+                let keys = keymap.keypad.get(chip8_key as usize)?;
+                let names = keys.iter().map(|&key| keymap::key_name(key)).collect::<Vec<_>>().join("/");
+                Some(format!("{}: {}", action, names))
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Database {
+    programs: Vec<Program>,
+    hashes: HashMap<String, usize>,
+    platforms: Vec<Platform>,
+}
+
+impl Database {
+    // the three files of the chip-8-database
+    pub fn parse(programs: &str, sha1_hashes: &str, platforms: &str) -> Result<Database, serde_json::Error> {
+        Ok(Database {
+            programs: serde_json::from_str(programs)?,
+            hashes: serde_json::from_str(sha1_hashes)?,
+            platforms: serde_json::from_str(platforms)?,
+        })
+    }
+
+    // the database/ directory of a chip-8-database checkout
+    pub fn load(dir: &Path) -> Result<Database, DatabaseError> {
+        fn read<T: serde::de::DeserializeOwned>(path: PathBuf) -> Result<T, DatabaseError> {
+            match fs::read_to_string(&path) {
+                Ok(contents) => serde_json::from_str(&contents).map_err(|e| DatabaseError::Parse(path, e)),
+                Err(e) => Err(DatabaseError::Io(path, e)),
+            }
+        }
+        Ok(Database {
+            programs: read(dir.join("programs.json"))?,
+            hashes: read(dir.join("sha1-hashes.json"))?,
+            platforms: read(dir.join("platforms.json"))?,
+        })
+    }
+
+    pub fn bundled() -> &'static Database {
+        static BUNDLED: OnceLock<Database> = OnceLock::new();
+        BUNDLED.get_or_init(|| Database::parse(PROGRAMS, SHA1_HASHES, PLATFORMS).expect("bundled ROM database is valid"))
+    }
+
+    // makes this the database every ROM is identified with from now on, in place of the bundled one;
+    // it can only be done once, before any ROM is loaded
+    pub fn install(self) -> Result<(), DatabaseError> {
+        EXTERNAL.set(self).map_err(|_| DatabaseError::AlreadyInstalled)
+    }
+
+    // the installed database, or the bundled one
+    pub fn current() -> &'static Database {
+        EXTERNAL.get().unwrap_or_else(Database::bundled)
+    }
+
+    // sha1 is lowercase hex, as Chip8::rom_sha1 gives it
+    pub fn lookup(&self, sha1: &str) -> Option<RomInfo> {
+        let program = self.programs.get(*self.hashes.get(sha1)?)?;
+        let rom = program.roms.get(sha1)?;
+
+        //the first platform the ROM runs on that we have settings for
+        let platform = rom
+            .platforms
+            .iter()
+            .find_map(|id| self.platforms.iter().find(|platform| &platform.id == id))?;
+        let mut quirks = Quirks::default();
+        for (name, &value) in platform.quirks.iter().chain(rom.quirky_platforms.get(&platform.id).into_iter().flatten()) {
+            quirks.set(name, value);
+        }
+
+        let palette = rom.colors.as_ref().and_then(|colors| match colors.pixels.as_slice() {
+            [background, foreground, ..] => Some(Palette {
+                background: parse_color(background)?,
+                foreground: parse_color(foreground)?,
+            }),
+            _ => None,
+        });
+
+        Some(RomInfo {
+            title: program.title.clone(),
+            description: rom.description.clone().unwrap_or_else(|| program.description.clone()),
+            release: program.release.clone(),
+            authors: program.authors.clone(),
+            platform: platform.id.clone(),
+            platform_name: platform.name.clone(),
+            quirks,
+            speed: rom.tickrate.unwrap_or(platform.default_tickrate),
+            palette,
+            keys: rom.keys.clone(),
+        })
+    }
+}
+
+pub fn sha1_hex(rom: &[u8]) -> String {
+    Sha1::digest(rom).iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+// looks a ROM up in the current database
+pub fn identify(rom: &[u8]) -> Option<RomInfo> {
+    Database::current().lookup(&sha1_hex(rom))
+}
+
+// "#rrggbb" as 0x00rrggbb
+fn parse_color(color: &str) -> Option<u32> {
+    let hex = color.strip_prefix('#')?;
+    if hex.len() != 6 {
+        return None;
+    }
+    u32::from_str_radix(hex, 16).ok()
+}
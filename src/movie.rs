@@ -0,0 +1,192 @@
+// Input movies: every keypad change of a session, tagged with the frame and instruction cycle it took
+// effect on, plus everything else the run depends on (ROM, quirks, speed, RNG seed and the FX75 user
+// flags it started with). Playing one back on the same ROM reproduces the session exactly. Files are
+// plain text:
+//
+//     chip8-movie 1
+//     rom_sha1 4cb2a1a9cd7ea7a47ebd9a66c2ceff9dc18a6ef2
+//     rom_name brix
+//     seed 1234
+//     speed 10
+//     frames 300
+//     quirks shift=1 memoryIncrementByX=0 memoryLeaveIUnchanged=1 wrap=0 jump=0 vblank=0 logic=0
+//     flags 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00
+//     # frame cycle key pressed
+//     120 1200 4 1
+//     124 1245 4 0
+
+use crate::flags::NUM_FLAGS;
+use crate::quirks::Quirks;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub const MOVIE_DIR: &str = "movies";
+pub const MOVIE_EXTENSION: &str = "c8m";
+const HEADER: &str = "chip8-movie 1";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MovieEvent {
+    pub frame: u64,
+    pub cycle: u64,
+    pub key: u8,
+    pub pressed: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Movie {
+    pub rom_sha1: String,
+    pub rom_name: String,
+    pub seed: u64,
+    pub speed: usize,
+    pub quirks: Quirks,
+    pub flags: [u8; NUM_FLAGS], // the user flags at the start, 0 in movies from before they were recorded
+    pub frames: u64,            // length of the recording
+    pub events: Vec<MovieEvent>,
+}
+
+#[derive(Debug)]
+pub enum MovieError {
+    Io(io::Error),
+    Parse { line: usize, message: String },
+    RomMismatch { expected: String, found: String },
+    Recording, // a movie is being recorded, which a replay would throw away
+}
+
+impl fmt::Display for MovieError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MovieError::Io(e) => write!(f, "{}", e),
+            MovieError::Parse { line, message } => write!(f, "line {}: {}", line, message),
+            MovieError::RomMismatch { expected, found } => {
+                write!(f, "movie was recorded on ROM {} but the loaded ROM is {}", expected, found)
+            }
+            MovieError::Recording => write!(f, "a movie is being recorded, stop it before replaying one"),
+        }
+    }
+}
+
+impl std::error::Error for MovieError {}
+
+impl From<io::Error> for MovieError {
+    fn from(e: io::Error) -> Self {
+        MovieError::Io(e)
+    }
+}
+
+impl Movie {
+    pub fn load(path: &Path) -> Result<Movie, MovieError> {
+        Movie::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, self.to_string())
+    }
+
+    pub fn parse(text: &str) -> Result<Movie, MovieError> {
+        let mut movie = Movie {
+            rom_sha1: String::new(),
+            rom_name: String::new(),
+            seed: 0,
+            speed: 0,
+            quirks: Quirks::default(),
+            flags: [0; NUM_FLAGS],
+            frames: 0,
+            events: Vec::new(),
+        };
+        let mut seen_header = false;
+
+        for (index, line) in text.lines().enumerate() {
+            let line_number = index + 1;
+            let error = |message: String| MovieError::Parse { line: line_number, message };
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if !seen_header {
+                if line != HEADER {
+                    return Err(error(format!("expected \"{}\"", HEADER)));
+                }
+                seen_header = true;
+                continue;
+            }
+
+            let (field, value) = line.split_once(' ').unwrap_or((line, ""));
+            match field {
+                "rom_sha1" => movie.rom_sha1 = value.to_string(),
+                "rom_name" => movie.rom_name = value.to_string(),
+                "seed" => movie.seed = value.parse().map_err(|_| error(format!("invalid seed \"{}\"", value)))?,
+                "speed" => movie.speed = value.parse().map_err(|_| error(format!("invalid speed \"{}\"", value)))?,
+                "frames" => movie.frames = value.parse().map_err(|_| error(format!("invalid frame count \"{}\"", value)))?,
+                "quirks" => movie.quirks = value.parse().map_err(error)?,
+                "flags" => {
+                    let bytes: Option<Vec<u8>> = value.split_whitespace().map(|byte| u8::from_str_radix(byte, 16).ok()).collect();
+                    movie.flags = bytes
+                        .and_then(|bytes| bytes.try_into().ok())
+                        .ok_or_else(|| error(format!("expected {} hex bytes of flags, got \"{}\"", NUM_FLAGS, value)))?;
+                }
+                _ => {
+                    let numbers = line
+                        .split_whitespace()
+                        .map(|part| part.parse::<u64>())
+                        .collect::<Result<Vec<u64>, _>>()
+                        .map_err(|_| error(format!("expected \"frame cycle key pressed\", got \"{}\"", line)))?;
+                    let [frame, cycle, key, pressed] = numbers[..] else {
+                        return Err(error(format!("expected \"frame cycle key pressed\", got \"{}\"", line)));
+                    };
+                    if key > 0xF || pressed > 1 {
+                        return Err(error(format!("invalid key event \"{}\"", line)));
+                    }
+                    if movie.events.last().is_some_and(|last| last.cycle > cycle) {
+                        return Err(error(String::from("events must be in cycle order")));
+                    }
+                    movie.events.push(MovieEvent { frame, cycle, key: key as u8, pressed: pressed == 1 });
+                }
+            }
+        }
+
+        if !seen_header {
+            return Err(MovieError::Parse { line: 1, message: format!("expected \"{}\"", HEADER) });
+        }
+        if movie.speed == 0 {
+            return Err(MovieError::Parse { line: 1, message: String::from("missing speed") });
+        }
+        Ok(movie)
+    }
+}
+
+// the newest movie saved for rom_name in dir; saved names are timestamped, so the newest sorts last
+pub fn latest(dir: &Path, rom_name: &str) -> Option<PathBuf> {
+    let prefix = format!("{}_", rom_name);
+    fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension().is_some_and(|ext| ext == MOVIE_EXTENSION)
+                && path.file_name().and_then(|name| name.to_str()).is_some_and(|name| name.starts_with(&prefix))
+        })
+        .max()
+}
+
+impl fmt::Display for Movie {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", HEADER)?;
+        writeln!(f, "rom_sha1 {}", self.rom_sha1)?;
+        writeln!(f, "rom_name {}", self.rom_name)?;
+        writeln!(f, "seed {}", self.seed)?;
+        writeln!(f, "speed {}", self.speed)?;
+        writeln!(f, "frames {}", self.frames)?;
+        writeln!(f, "quirks {}", self.quirks)?;
+        let flags: Vec<String> = self.flags.iter().map(|flag| format!("{:02x}", flag)).collect();
+        writeln!(f, "flags {}", flags.join(" "))?;
+        writeln!(f, "# frame cycle key pressed")?;
+        for event in &self.events {
+            writeln!(f, "{} {} {} {}", event.frame, event.cycle, event.key, event.pressed as u8)?;
+        }
+        Ok(())
+    }
+}
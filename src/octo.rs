@@ -0,0 +1,696 @@
+// Compiler for Octo, the language .8o files and Octo cartridges are written in:
+//
+//     : main
+//         i := ball
+//         v0 := 30
+//         loop
+//             sprite v0 v1 1
+//             v0 += 1
+//             if v0 == 60 then v0 := 0
+//         again
+//     : ball 0x80
+//
+// It covers the CHIP-8 instructions plus saveflags and loadflags, labels, :const, :alias, :unpack, :next, :org, :byte, :macro and
+// :calc. SUPER-CHIP and XO-CHIP instructions are reported as errors since this machine doesn't run
+// them. Like Octo, programs start with a jump to main.
+
+use crate::asm::AsmError;
+use std::collections::{HashMap, VecDeque};
+
+const START: usize = 0x200;
+const MEMORY_SIZE: usize = 4096;
+const COMPARE_TEMP: u16 = 0xF; // the register comparisons like vx < vy work in
+const MAX_MACRO_DEPTH: usize = 64; // macros expanding macros, so one that uses itself stops instead of growing forever
+
+const EXTENDED: [&str; 14] = [
+    "hires", "lores", "scroll-down", "scroll-up", "scroll-left", "scroll-right", "exit", "plane", "audio", "pitch",
+    "bighex", "long", ":pointer", ":stringmode",
+];
+
+#[derive(Debug, Clone)]
+struct Token {
+    text: String,
+    line: usize,
+    depth: usize, // how many macro expansions it came out of
+}
+
+struct Macro {
+    params: Vec<String>,
+    body: Vec<Token>,
+}
+
+// an address used before its label is defined
+struct Reference {
+    name: String,
+    line: usize,
+    at: usize,
+    unpack: Option<u16>, // :unpack's nibble, for the two v0/v1 loads instead of one instruction
+}
+
+enum Block {
+    Loop { start: usize, whiles: Vec<usize> },
+    If { jump: usize },
+    Else { jump: usize },
+}
+
+struct Compiler {
+    tokens: VecDeque<Token>,
+    line: usize,
+    depth: usize, // of the last token read, like line
+    memory: Vec<u8>,
+    here: usize,
+    end: usize,
+    labels: HashMap<String, usize>,
+    constants: HashMap<String, f64>,
+    aliases: HashMap<String, u16>,
+    macros: HashMap<String, Macro>,
+    references: Vec<Reference>,
+    blocks: Vec<Block>,
+}
+
+pub fn compile(source: &str) -> Result<Vec<u8>, AsmError> {
+    let mut compiler = Compiler::new(source);
+    compiler.compile().map_err(|message| AsmError { line: compiler.line, message })?;
+    Ok(compiler.memory[START..compiler.end].to_vec())
+}
+
+fn tokenize(source: &str) -> VecDeque<Token> {
+    let mut tokens = VecDeque::new();
+    for (index, line) in source.lines().enumerate() {
+        for text in line.split('#').next().unwrap_or("").split_whitespace() {
+            tokens.push_back(Token { text: text.to_string(), line: index + 1, depth: 0 });
+        }
+    }
+    tokens
+}
+
+impl Compiler {
+    fn new(source: &str) -> Self {
+        Compiler {
+            tokens: tokenize(source),
+            line: 1,
+            depth: 0,
+            memory: vec![0; MEMORY_SIZE],
+            here: START,
+            end: START,
+            labels: HashMap::new(),
+            constants: HashMap::new(),
+            aliases: HashMap::new(),
+            macros: HashMap::new(),
+            references: Vec::new(),
+            blocks: Vec::new(),
+        }
+    }
+
+    fn compile(&mut self) -> Result<(), String> {
+        //the jump to main is filled in like any other forward reference
+        self.reference(String::from("main"), None);
+        self.instruction(0x1000)?;
+
+        while let Some(token) = self.next_token() {
+            self.statement(&token)?;
+        }
+        if let Some(block) = self.blocks.last() {
+            return Err(String::from(match block {
+                Block::Loop { .. } => "a loop is missing its again",
+                Block::If { .. } | Block::Else { .. } => "an if ... begin is missing its end",
+            }));
+        }
+
+        for reference in std::mem::take(&mut self.references) {
+            self.line = reference.line;
+            let Some(&address) = self.labels.get(&reference.name) else {
+                if reference.name == "main" {
+                    return Err(String::from("the program has no main label to start at"));
+                }
+                return Err(format!("undefined name \"{}\"", reference.name));
+            };
+            match reference.unpack {
+                Some(nibble) => {
+                    self.memory[reference.at + 1] = (nibble << 4 | address as u16 >> 8) as u8;
+                    self.memory[reference.at + 3] = address as u8;
+                }
+                None => {
+                    self.memory[reference.at] |= (address >> 8) as u8 & 0x0F;
+                    self.memory[reference.at + 1] = address as u8;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn next_token(&mut self) -> Option<String> {
+        let token = self.tokens.pop_front()?;
+        self.line = token.line;
+        self.depth = token.depth;
+        Some(token.text)
+    }
+
+    fn expect_token(&mut self) -> Result<String, String> {
+        self.next_token().ok_or_else(|| String::from("the program ends in the middle of a statement"))
+    }
+
+    fn peek(&self) -> Option<&str> {
+        self.tokens.front().map(|token| token.text.as_str())
+    }
+
+    fn expect(&mut self, expected: &str) -> Result<(), String> {
+        let token = self.expect_token()?;
+        if token != expected {
+            return Err(format!("expected \"{}\", got \"{}\"", expected, token));
+        }
+        Ok(())
+    }
+
+    fn statement(&mut self, token: &str) -> Result<(), String> {
+        if EXTENDED.contains(&token) {
+            return Err(format!("\"{}\" is a SUPER-CHIP or XO-CHIP instruction, which this emulator doesn't run", token));
+        }
+        if self.is_register(token) {
+            return self.register_statement(token);
+        }
+        match token {
+            ":" => {
+                let name = self.expect_token()?;
+                self.define(name, self.here)?;
+            }
+            ":next" => {
+                let name = self.expect_token()?;
+                self.define(name, self.here + 1)?;
+            }
+            ":const" => {
+                let name = self.expect_token()?;
+                let token = self.expect_token()?;
+                let value = self.value(&token).ok_or_else(|| format!("\"{}\" is not a number or a constant", token))?;
+                self.check_name(&name)?;
+                self.constants.insert(name, value);
+            }
+            ":alias" => {
+                let name = self.expect_token()?;
+                let register = self.register()?;
+                self.check_name(&name)?;
+                self.aliases.insert(name, register);
+            }
+            ":unpack" => {
+                let nibble = self.number(0xF)?;
+                let name = self.expect_token()?;
+                match self.labels.get(&name).copied().or_else(|| self.constants.get(&name).map(|&value| value as usize)) {
+                    Some(address) => {
+                        self.instruction(0x6000 | nibble << 4 | (address as u16 >> 8 & 0xF))?;
+                        self.instruction(0x6100 | (address as u16 & 0xFF))?;
+                    }
+                    None => {
+                        self.reference(name, Some(nibble));
+                        self.instruction(0x6000)?;
+                        self.instruction(0x6100)?;
+                    }
+                }
+            }
+            ":org" => self.here = self.number(0xFFF)? as usize,
+            ":byte" => {
+                let value = if self.peek() == Some("{") { self.calc()? as i64 as u8 } else { self.number(0xFF)? as u8 };
+                self.byte(value)?;
+            }
+            ":call" => self.address(0x2000)?,
+            ":macro" => self.define_macro()?,
+            ":calc" => {
+                let name = self.expect_token()?;
+                let value = self.calc()?;
+                self.check_name(&name)?;
+                self.constants.insert(name, value);
+            }
+            //debugger hints Octo's IDE uses, nothing to run
+            ":breakpoint" => {
+                self.expect_token()?;
+            }
+            ":monitor" => {
+                self.expect_token()?;
+                self.expect_token()?;
+            }
+            "clear" => self.instruction(0x00E0)?,
+            "return" | ";" => self.instruction(0x00EE)?,
+            "jump" => self.address(0x1000)?,
+            "jump0" => self.address(0xB000)?,
+            "native" => self.address(0x0000)?,
+            "bcd" => self.register_instruction(0xF033)?,
+            "save" => self.register_instruction(0xF055)?,
+            "load" => self.register_instruction(0xF065)?,
+            "saveflags" => self.register_instruction(0xF075)?,
+            "loadflags" => self.register_instruction(0xF085)?,
+            "delay" => {
+                self.expect(":=")?;
+                self.register_instruction(0xF015)?;
+            }
+            "buzzer" => {
+                self.expect(":=")?;
+                self.register_instruction(0xF018)?;
+            }
+            "sprite" => {
+                let x = self.register()?;
+                let y = self.register()?;
+                let n = self.number(0xF)?;
+                self.instruction(0xD000 | x << 8 | y << 4 | n)?;
+            }
+            "i" => match self.expect_token()?.as_str() {
+                ":=" if self.peek() == Some("hex") => {
+                    self.next_token();
+                    self.register_instruction(0xF029)?;
+                }
+                ":=" => self.address(0xA000)?,
+                "+=" => self.register_instruction(0xF01E)?,
+                operator => return Err(format!("i can't be used with \"{}\", only := and +=", operator)),
+            },
+            "if" => {
+                let block = self.tokens.iter().take(4).find(|token| token.text == "then" || token.text == "begin");
+                if block.is_some_and(|token| token.text == "begin") {
+                    self.conditional(true)?;
+                    self.expect("begin")?;
+                    self.blocks.push(Block::If { jump: self.here });
+                    self.instruction(0x1000)?;
+                } else {
+                    self.conditional(false)?;
+                    self.expect("then")?;
+                }
+            }
+            "else" => match self.blocks.pop() {
+                Some(Block::If { jump }) => {
+                    self.blocks.push(Block::Else { jump: self.here });
+                    self.instruction(0x1000)?;
+                    self.patch(jump, self.here);
+                }
+                _ => return Err(String::from("else without an if ... begin")),
+            },
+            "end" => match self.blocks.pop() {
+                Some(Block::If { jump } | Block::Else { jump }) => self.patch(jump, self.here),
+                _ => return Err(String::from("end without an if ... begin")),
+            },
+            "loop" => self.blocks.push(Block::Loop { start: self.here, whiles: Vec::new() }),
+            "while" => {
+                self.conditional(true)?;
+                let jump = self.here;
+                match self.blocks.iter_mut().rev().find(|block| matches!(block, Block::Loop { .. })) {
+                    Some(Block::Loop { whiles, .. }) => whiles.push(jump),
+                    _ => return Err(String::from("while outside of a loop")),
+                }
+                self.instruction(0x1000)?;
+            }
+            "again" => match self.blocks.pop() {
+                Some(Block::Loop { start, whiles }) => {
+                    self.instruction(0x1000 | start as u16)?;
+                    for jump in whiles {
+                        self.patch(jump, self.here);
+                    }
+                }
+                _ => return Err(String::from("again without a loop")),
+            },
+            _ if self.macros.contains_key(token) => self.expand_macro(token)?,
+            //numbers on their own are data
+            _ if parse_number(token).is_some() || self.constants.contains_key(token) => {
+                let value = self.fit(token, 0xFF)?;
+                self.byte(value as u8)?;
+            }
+            _ if is_identifier(token) => self.address_value(0x2000, token.to_string())?,
+            _ => return Err(format!("unexpected \"{}\"", token)),
+        }
+        Ok(())
+    }
+
+    fn register_statement(&mut self, token: &str) -> Result<(), String> {
+        let x = self.parse_register(token).unwrap_or(0);
+        let operator = self.expect_token()?;
+        let xy = |opcode: u16, y: u16| opcode | x << 8 | y << 4;
+        let rhs = self.peek().map(str::to_string).unwrap_or_default();
+        let rhs_register = self.parse_register(&rhs);
+        match (operator.as_str(), rhs_register) {
+            (":=", Some(y)) => self.operand_instruction(xy(0x8000, y))?,
+            (":=", None) if rhs == "random" => {
+                self.next_token();
+                let nn = self.number(0xFF)?;
+                self.instruction(0xC000 | x << 8 | nn)?;
+            }
+            (":=", None) if rhs == "key" => self.operand_instruction(0xF00A | x << 8)?,
+            (":=", None) if rhs == "delay" => self.operand_instruction(0xF007 | x << 8)?,
+            (":=", None) => {
+                let nn = self.number(0xFF)?;
+                self.instruction(0x6000 | x << 8 | nn)?;
+            }
+            ("+=", Some(y)) => self.operand_instruction(xy(0x8004, y))?,
+            ("+=", None) => {
+                let nn = self.number(0xFF)?;
+                self.instruction(0x7000 | x << 8 | nn)?;
+            }
+            ("-=", Some(y)) => self.operand_instruction(xy(0x8005, y))?,
+            ("-=", None) => {
+                let nn = self.number(0xFF)?;
+                self.instruction(0x7000 | x << 8 | (nn.wrapping_neg() & 0xFF))?;
+            }
+            ("=-", Some(y)) => self.operand_instruction(xy(0x8007, y))?,
+            ("|=", Some(y)) => self.operand_instruction(xy(0x8001, y))?,
+            ("&=", Some(y)) => self.operand_instruction(xy(0x8002, y))?,
+            ("^=", Some(y)) => self.operand_instruction(xy(0x8003, y))?,
+            (">>=", Some(y)) => self.operand_instruction(xy(0x8006, y))?,
+            ("<<=", Some(y)) => self.operand_instruction(xy(0x800E, y))?,
+            _ => return Err(format!("can't use \"{}\" on a register with \"{}\"", operator, rhs)),
+        }
+        Ok(())
+    }
+
+    // emits the test of an if or while; normally it skips the next instruction when the condition is
+    // false, negated it skips it when the condition is true
+    fn conditional(&mut self, negated: bool) -> Result<(), String> {
+        let x = self.register()?;
+        let mut comparison = self.expect_token()?;
+        if negated {
+            let opposite = match comparison.as_str() {
+                "==" => "!=",
+                "!=" => "==",
+                "key" => "-key",
+                "-key" => "key",
+                "<" => ">=",
+                ">" => "<=",
+                "<=" => ">",
+                ">=" => "<",
+                other => return Err(format!("unknown comparison \"{}\"", other)),
+            };
+            comparison = opposite.to_string();
+        }
+        match comparison.as_str() {
+            "key" => return self.instruction(0xE0A1 | x << 8),
+            "-key" => return self.instruction(0xE09E | x << 8),
+            "==" | "!=" | "<" | ">" | "<=" | ">=" => {}
+            other => return Err(format!("unknown comparison \"{}\"", other)),
+        }
+
+        let rhs = self.peek().map(str::to_string).unwrap_or_default();
+        match (comparison.as_str(), self.parse_register(&rhs)) {
+            ("==", Some(y)) => self.operand_instruction(0x9000 | x << 8 | y << 4),
+            ("==", None) => {
+                let nn = self.number(0xFF)?;
+                self.instruction(0x4000 | x << 8 | nn)
+            }
+            ("!=", Some(y)) => self.operand_instruction(0x5000 | x << 8 | y << 4),
+            ("!=", None) => {
+                let nn = self.number(0xFF)?;
+                self.instruction(0x3000 | x << 8 | nn)
+            }
+            //the rest compare by subtracting in vf and checking the borrow flag
+            (comparison, y) => {
+                match y {
+                    Some(y) => self.operand_instruction(0x8000 | COMPARE_TEMP << 8 | y << 4)?,
+                    None => {
+                        let nn = self.number(0xFF)?;
+                        self.instruction(0x6000 | COMPARE_TEMP << 8 | nn)?;
+                    }
+                }
+                let (subtract, skip) = match comparison {
+                    ">" => (0x5, 0x3000),
+                    "<" => (0x7, 0x3000),
+                    ">=" => (0x7, 0x4000),
+                    _ => (0x5, 0x4000),
+                };
+                self.instruction(0x8000 | COMPARE_TEMP << 8 | x << 4 | subtract)?;
+                self.instruction(skip | COMPARE_TEMP << 8 | 1)
+            }
+        }
+    }
+
+    fn define(&mut self, name: String, address: usize) -> Result<(), String> {
+        self.check_name(&name)?;
+        if self.labels.contains_key(&name) {
+            return Err(format!("label \"{}\" is defined twice", name));
+        }
+        self.labels.insert(name, address);
+        Ok(())
+    }
+
+    fn check_name(&self, name: &str) -> Result<(), String> {
+        if !is_identifier(name) || self.is_register(name) {
+            return Err(format!("\"{}\" can't be used as a name", name));
+        }
+        Ok(())
+    }
+
+    fn define_macro(&mut self) -> Result<(), String> {
+        let name = self.expect_token()?;
+        self.check_name(&name)?;
+        let mut params = Vec::new();
+        loop {
+            let token = self.expect_token()?;
+            if token == "{" {
+                break;
+            }
+            params.push(token);
+        }
+        let body = self.braced()?;
+        self.macros.insert(name, Macro { params, body });
+        Ok(())
+    }
+
+    fn expand_macro(&mut self, name: &str) -> Result<(), String> {
+        let line = self.line;
+        let depth = self.depth + 1;
+        if depth > MAX_MACRO_DEPTH {
+            return Err(format!("macro \"{}\" is nested more than {} deep, does it use itself?", name, MAX_MACRO_DEPTH));
+        }
+        let params = self.macros[name].params.clone();
+        let mut args = HashMap::new();
+        for param in params {
+            args.insert(param, self.expect_token()?);
+        }
+        let body: Vec<Token> = self.macros[name]
+            .body
+            .iter()
+            .map(|token| Token { text: args.get(&token.text).unwrap_or(&token.text).clone(), line, depth })
+            .collect();
+        for token in body.into_iter().rev() {
+            self.tokens.push_front(token);
+        }
+        Ok(())
+    }
+
+    // the tokens up to the closing brace, after the opening one has been read
+    fn braced(&mut self) -> Result<Vec<Token>, String> {
+        let mut depth = 1;
+        let mut tokens = Vec::new();
+        loop {
+            let token = self.tokens.pop_front().ok_or_else(|| String::from("a { is missing its }"))?;
+            self.line = token.line;
+            match token.text.as_str() {
+                "{" => depth += 1,
+                "}" => depth -= 1,
+                _ => {}
+            }
+            if depth == 0 {
+                return Ok(tokens);
+            }
+            tokens.push(token);
+        }
+    }
+
+    fn calc(&mut self) -> Result<f64, String> {
+        self.expect("{")?;
+        let tokens: Vec<String> = self.braced()?.into_iter().map(|token| token.text).collect();
+        let mut position = 0;
+        let value = self.calc_expression(&tokens, &mut position)?;
+        if position != tokens.len() {
+            return Err(format!("unexpected \"{}\" in :calc", tokens[position]));
+        }
+        Ok(value)
+    }
+
+    // operators have no precedence and group to the right, as in Octo
+    fn calc_expression(&self, tokens: &[String], position: &mut usize) -> Result<f64, String> {
+        let left = self.calc_term(tokens, position)?;
+        let Some(operator) = tokens.get(*position).filter(|token| token.as_str() != ")") else {
+            return Ok(left);
+        };
+        *position += 1;
+        let right = self.calc_expression(tokens, position)?;
+        let int = |value: f64| value as i64;
+        //shifts by 64 or more, or by a negative amount, have no value rather than overflowing
+        let shift = |shift: fn(i64, u32) -> Option<i64>| {
+            let amount = int(right);
+            u32::try_from(amount)
+                .ok()
+                .and_then(|amount| shift(int(left), amount))
+                .map(|value| value as f64)
+                .ok_or_else(|| format!("can't shift by {} in :calc", amount))
+        };
+        Ok(match operator.as_str() {
+            "+" => left + right,
+            "-" => left - right,
+            "*" => left * right,
+            "/" => left / right,
+            "%" => left % right,
+            "&" => (int(left) & int(right)) as f64,
+            "|" => (int(left) | int(right)) as f64,
+            "^" => (int(left) ^ int(right)) as f64,
+            "<<" => shift(i64::checked_shl)?,
+            ">>" => shift(i64::checked_shr)?,
+            "pow" => left.powf(right),
+            "min" => left.min(right),
+            "max" => left.max(right),
+            "<" => (left < right) as u8 as f64,
+            ">" => (left > right) as u8 as f64,
+            "<=" => (left <= right) as u8 as f64,
+            ">=" => (left >= right) as u8 as f64,
+            "==" => (left == right) as u8 as f64,
+            "!=" => (left != right) as u8 as f64,
+            _ => return Err(format!("unknown operator \"{}\" in :calc", operator)),
+        })
+    }
+
+    fn calc_term(&self, tokens: &[String], position: &mut usize) -> Result<f64, String> {
+        let token = tokens.get(*position).ok_or_else(|| String::from(":calc expression ends early"))?;
+        *position += 1;
+        let unary = |f: fn(f64) -> f64, position: &mut usize| self.calc_term(tokens, position).map(f);
+        match token.as_str() {
+            "(" => {
+                let value = self.calc_expression(tokens, position)?;
+                if tokens.get(*position).map(String::as_str) != Some(")") {
+                    return Err(String::from("a ( in :calc is missing its )"));
+                }
+                *position += 1;
+                Ok(value)
+            }
+            "-" => unary(|value| -value, position),
+            "~" => unary(|value| !(value as i64) as f64, position),
+            "!" => unary(|value| (value == 0.0) as u8 as f64, position),
+            "abs" => unary(f64::abs, position),
+            "sqrt" => unary(f64::sqrt, position),
+            "floor" => unary(f64::floor, position),
+            "ceil" => unary(f64::ceil, position),
+            "sin" => unary(f64::sin, position),
+            "cos" => unary(f64::cos, position),
+            "log" => unary(f64::ln, position),
+            "sign" => unary(f64::signum, position),
+            "@" => {
+                let address = self.calc_term(tokens, position)? as usize;
+                Ok(*self.memory.get(address).ok_or_else(|| format!("@ {} is outside of memory", address))? as f64)
+            }
+            "HERE" => Ok(self.here as f64),
+            "PI" => Ok(std::f64::consts::PI),
+            "E" => Ok(std::f64::consts::E),
+            _ => self.value(token).ok_or_else(|| format!("unknown name \"{}\" in :calc", token)),
+        }
+    }
+
+    // a number, constant or label that is already defined
+    fn value(&self, token: &str) -> Option<f64> {
+        parse_number(token)
+            .or_else(|| self.constants.get(token).copied())
+            .or_else(|| self.labels.get(token).map(|&address| address as f64))
+    }
+
+    fn number(&mut self, max: u16) -> Result<u16, String> {
+        let token = self.expect_token()?;
+        self.fit(&token, max)
+    }
+
+    // the value of token if it is no bigger than max; negative numbers become two's complement
+    fn fit(&self, token: &str, max: u16) -> Result<u16, String> {
+        let value = self.value(token).ok_or_else(|| format!("\"{}\" is not a number or a constant", token))? as i64;
+        if value > max as i64 || value < -(max as i64 + 1) / 2 {
+            return Err(format!("{} doesn't fit, the most this takes is 0x{:X}", token, max));
+        }
+        Ok(value as u16 & max)
+    }
+
+    // opcode with an address that can be a label defined later
+    fn address(&mut self, opcode: u16) -> Result<(), String> {
+        let token = self.expect_token()?;
+        self.address_value(opcode, token)
+    }
+
+    fn address_value(&mut self, opcode: u16, token: String) -> Result<(), String> {
+        match self.value(&token) {
+            Some(address) if (0.0..=0xFFF as f64).contains(&address) => self.instruction(opcode | address as u16),
+            Some(_) => Err(format!("{} is not an address", token)),
+            None if is_identifier(&token) => {
+                self.reference(token, None);
+                self.instruction(opcode)
+            }
+            None => Err(format!("\"{}\" is not an address", token)),
+        }
+    }
+
+    fn reference(&mut self, name: String, unpack: Option<u16>) {
+        self.references.push(Reference { name, line: self.line, at: self.here, unpack });
+    }
+
+    fn patch(&mut self, at: usize, address: usize) {
+        self.memory[at] = 0x10 | (address >> 8) as u8 & 0x0F;
+        self.memory[at + 1] = address as u8;
+    }
+
+    // for an operand that was only peeked at
+    fn operand_instruction(&mut self, opcode: u16) -> Result<(), String> {
+        self.next_token();
+        self.instruction(opcode)
+    }
+
+    fn register_instruction(&mut self, opcode: u16) -> Result<(), String> {
+        let x = self.register()?;
+        if self.peek() == Some("-") {
+            return Err(String::from("saving or loading a range of registers is an XO-CHIP instruction, which this emulator doesn't run"));
+        }
+        self.instruction(opcode | x << 8)
+    }
+
+    fn register(&mut self) -> Result<u16, String> {
+        let token = self.expect_token()?;
+        self.parse_register(&token).ok_or_else(|| format!("expected a register v0-vf, got \"{}\"", token))
+    }
+
+    fn is_register(&self, token: &str) -> bool {
+        self.parse_register(token).is_some()
+    }
+
+    fn parse_register(&self, token: &str) -> Option<u16> {
+        if let Some(&register) = self.aliases.get(token) {
+            return Some(register);
+        }
+        let digit = token.strip_prefix(['v', 'V'])?;
+        if digit.len() != 1 {
+            return None;
+        }
+        u16::from_str_radix(digit, 16).ok()
+    }
+
+    fn instruction(&mut self, opcode: u16) -> Result<(), String> {
+        self.byte((opcode >> 8) as u8)?;
+        self.byte(opcode as u8)
+    }
+
+    fn byte(&mut self, value: u8) -> Result<(), String> {
+        if self.here >= MEMORY_SIZE {
+            return Err(String::from("the program doesn't fit in memory"));
+        }
+        self.memory[self.here] = value;
+        self.here += 1;
+        self.end = self.end.max(self.here);
+        Ok(())
+    }
+}
+
+fn parse_number(token: &str) -> Option<f64> {
+    let lower = token.to_lowercase();
+    let (negative, digits) = match lower.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, lower.as_str()),
+    };
+    let value = if let Some(hex) = digits.strip_prefix("0x") {
+        i64::from_str_radix(hex, 16).ok()?
+    } else if let Some(binary) = digits.strip_prefix("0b") {
+        i64::from_str_radix(binary, 2).ok()?
+    } else if digits.starts_with(|c: char| c.is_ascii_digit()) {
+        digits.parse().ok()?
+    } else {
+        return None;
+    };
+    Some(if negative { -value } else { value } as f64)
+}
+
+fn is_identifier(token: &str) -> bool {
+    !token.is_empty() && !token.starts_with(|c: char| c.is_ascii_digit() || c == ':' || c == '-') && parse_number(token).is_none()
+}
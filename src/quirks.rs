@@ -0,0 +1,151 @@
+// Behaviors that differ between CHIP-8 interpreters. The field names follow the quirk names used by
+// the community chip-8-database, and the default matches what this emulator has always done.
+
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Quirks {
+    pub shift: bool,                    // 8XY6/8XYE shift VX in place instead of shifting VY into VX
+    pub memory_increment_by_x: bool,    // FX55/FX65 leave I at I + X
+    pub memory_leave_i_unchanged: bool, // FX55/FX65 leave I alone (otherwise it ends at I + X + 1)
+    pub wrap: bool,                     // sprites wrap around the screen edges instead of being clipped
+    pub jump: bool,                     // BXNN jumps to XNN + VX instead of NNN + V0
+    pub vblank: bool,                   // DXYN waits for the next frame before the program continues
+    pub logic: bool,                    // 8XY1/8XY2/8XY3 reset VF to 0
+}
+
+impl Default for Quirks {
+    fn default() -> Self {
+        Quirks {
+            shift: true,
+            memory_increment_by_x: false,
+            memory_leave_i_unchanged: true,
+            wrap: false,
+            jump: false,
+            vblank: false,
+            logic: false,
+        }
+    }
+}
+
+impl Quirks {
+    pub const NAMES: [&'static str; 7] = ["shift", "memoryIncrementByX", "memoryLeaveIUnchanged", "wrap", "jump", "vblank", "logic"];
+    pub const PRESETS: [&'static str; 4] = ["default", "chip8", "schip", "xochip"];
+
+    // the original COSMAC VIP interpreter
+    pub fn chip8() -> Self {
+        Quirks {
+            shift: false,
+            memory_increment_by_x: false,
+            memory_leave_i_unchanged: false,
+            wrap: false,
+            jump: false,
+            vblank: true,
+            logic: true,
+        }
+    }
+
+    // SUPER-CHIP 1.1 on the HP48
+    pub fn schip() -> Self {
+        Quirks {
+            shift: true,
+            memory_increment_by_x: false,
+            memory_leave_i_unchanged: true,
+            wrap: false,
+            jump: true,
+            vblank: false,
+            logic: false,
+        }
+    }
+
+    // Octo's XO-CHIP
+    pub fn xochip() -> Self {
+        Quirks {
+            shift: false,
+            memory_increment_by_x: false,
+            memory_leave_i_unchanged: false,
+            wrap: true,
+            jump: false,
+            vblank: false,
+            logic: false,
+        }
+    }
+
+    // preset by name, chip-8-database platform ids work too
+    pub fn preset(name: &str) -> Option<Self> {
+        match name {
+            "default" => Some(Quirks::default()),
+            "chip8" | "originalChip8" => Some(Quirks::chip8()),
+            "schip" | "superchip" => Some(Quirks::schip()),
+            "xochip" => Some(Quirks::xochip()),
+            _ => None,
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<bool> {
+        self.values().into_iter().find(|(quirk, _)| *quirk == name).map(|(_, value)| value)
+    }
+
+    // returns false if name isn't one of NAMES
+    pub fn set(&mut self, name: &str, value: bool) -> bool {
+        let field = match name {
+            "shift" => &mut self.shift,
+            "memoryIncrementByX" => &mut self.memory_increment_by_x,
+            "memoryLeaveIUnchanged" => &mut self.memory_leave_i_unchanged,
+            "wrap" => &mut self.wrap,
+            "jump" => &mut self.jump,
+            "vblank" => &mut self.vblank,
+            "logic" => &mut self.logic,
+            _ => return false,
+        };
+        *field = value;
+        true
+    }
+
+    // sets the quirks listed as "name=0 name=1 ...", separated by spaces or commas
+    pub fn apply(&mut self, overrides: &str) -> Result<(), String> {
+        for part in overrides.split([' ', ',']).filter(|part| !part.is_empty()) {
+            let (name, value) = part.split_once('=').ok_or_else(|| format!("expected name=0 or name=1, got \"{}\"", part))?;
+            let value = match value {
+                "0" | "false" => false,
+                "1" | "true" => true,
+                _ => return Err(format!("quirk {} must be 0 or 1, got \"{}\"", name, value)),
+            };
+            if !self.set(name, value) {
+                return Err(format!("unknown quirk \"{}\"", name));
+            }
+        }
+        Ok(())
+    }
+
+    fn values(&self) -> [(&'static str, bool); 7] {
+        [
+            ("shift", self.shift),
+            ("memoryIncrementByX", self.memory_increment_by_x),
+            ("memoryLeaveIUnchanged", self.memory_leave_i_unchanged),
+            ("wrap", self.wrap),
+            ("jump", self.jump),
+            ("vblank", self.vblank),
+            ("logic", self.logic),
+        ]
+    }
+}
+
+// written as "shift=1 memoryIncrementByX=0 ...", the form movie files use
+impl fmt::Display for Quirks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let parts = self.values().map(|(name, value)| format!("{}={}", name, value as u8));
+        write!(f, "{}", parts.join(" "))
+    }
+}
+
+impl FromStr for Quirks {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut quirks = Quirks::default();
+        quirks.apply(s)?;
+        Ok(quirks)
+    }
+}
@@ -0,0 +1,248 @@
+// Static recompiler: translates a ROM into a Rust module with a function per basic block, found by
+// following jumps, calls and skips from 0x200. Register arithmetic, jumps and skips become plain Rust
+// on the machine's registers; drawing, timers, keys, random numbers, memory and the stack call
+// Chip8::execute. The module's PROGRAM is handed to Chip8::set_recompiled, and step_frame runs a block
+// wherever pc lands on one and its bytes in memory are still the ones it was translated from. Memory
+// writes end their block, so code written ahead of them is checked again before it runs. Code the
+// program wrote over, and BNNN jumps to code the translation didn't reach, run on the interpreter until
+// it gets back to a block.
+
+use crate::chip8::Chip8;
+use crate::disasm;
+use crate::instruction::Instruction;
+use crate::quirks::Quirks;
+use crate::rom::START_ADDRESS;
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+// a translated basic block
+#[derive(Debug)]
+pub struct Block {
+    pub start: u16,
+    pub code: &'static [u8], // the bytes it was translated from
+    pub run: fn(&mut Chip8), // runs every instruction in the block and leaves pc on the next one
+    pub draws: bool,         // ends with DXYN, which ends the frame with the vblank quirk
+}
+
+impl Block {
+    pub fn instructions(&self) -> usize {
+        self.code.len() / 2
+    }
+}
+
+#[derive(Debug)]
+pub struct Program {
+    pub sha1: &'static str,
+    pub quirks: Quirks,          // the blocks only run on a machine with these
+    pub blocks: &'static [Block], // by start address
+}
+
+impl Program {
+    // the block starting at address, if memory still holds the code it was translated from
+    pub fn block(&self, address: u16, memory: &[u8]) -> Option<&Block> {
+        let index = self.blocks.binary_search_by_key(&address, |block| block.start).ok()?;
+        let block = &self.blocks[index];
+        let start = block.start as usize;
+        (memory.get(start..start + block.code.len()) == Some(block.code)).then_some(block)
+    }
+}
+
+// where execution can go after the instruction at address, and whether it ends a basic block
+fn successors(address: u16, instruction: Instruction) -> (Vec<u16>, bool) {
+    let next = (address + 2) & 0x0FFF;
+    match instruction {
+        Instruction::Jump(target) => (vec![target], true),
+        Instruction::Call(target) => (vec![target, next], true),
+        Instruction::Return | Instruction::JumpWithOffset(..) => (Vec::new(), true),
+        Instruction::SkipIfEqual(..)
+        | Instruction::SkipIfNotEqual(..)
+        | Instruction::SkipIfRegistersEqual(..)
+        | Instruction::SkipIfRegistersNotEqual(..)
+        | Instruction::SkipIfKey(..)
+        | Instruction::SkipIfNotKey(..) => (vec![next, (next + 2) & 0x0FFF], true),
+        //a draw can end the frame and a key wait repeats until a key comes, so both end their block
+        Instruction::Draw(..) | Instruction::WaitForKey(..) => (vec![next], true),
+        //a memory write can change the code after it, which has to be checked again before it runs
+        Instruction::Decimal(..) | Instruction::StoreRegisters(..) => (vec![next], true),
+        _ => (vec![next], false),
+    }
+}
+
+// addresses basic blocks start at, found by following every path from the entry point through the ROM
+fn leaders(rom: &[u8]) -> BTreeSet<u16> {
+    let end = START_ADDRESS + rom.len();
+    let opcode = |address: usize| u16::from_be_bytes([rom[address - START_ADDRESS], rom[address + 1 - START_ADDRESS]]);
+    let in_rom = |address: u16| address as usize >= START_ADDRESS && address as usize + 1 < end;
+
+    let mut leaders = BTreeSet::new();
+    let mut code = BTreeSet::new();
+    let mut queue = vec![START_ADDRESS as u16];
+    while let Some(start) = queue.pop() {
+        if !in_rom(start) || !leaders.insert(start) || code.contains(&start) {
+            continue;
+        }
+        let mut address = start;
+        loop {
+            let instruction = Instruction::decode(opcode(address as usize));
+            code.insert(address);
+            if let Instruction::WaitForKey(_) = instruction {
+                //the wait repeats from its own address, so it gets a block to itself
+                if address != start {
+                    queue.push(address);
+                    break;
+                }
+            }
+            let (next, ends) = successors(address, instruction);
+            if ends {
+                queue.extend(next);
+                break;
+            }
+            address = next[0];
+            //running into code that's already been followed splits it there
+            if !in_rom(address) || code.contains(&address) {
+                queue.push(address);
+                break;
+            }
+        }
+    }
+    leaders
+}
+
+// translates rom into a Rust module whose PROGRAM runs it with quirks
+pub fn translate(rom: &[u8], name: &str, sha1: &str, quirks: Quirks) -> String {
+    let leaders = leaders(rom);
+    let end = START_ADDRESS + rom.len();
+    let mut blocks = Vec::new();
+    for &start in &leaders {
+        let mut instructions = Vec::new();
+        let mut address = start;
+        while (address as usize) + 1 < end && (address == start || !leaders.contains(&address)) {
+            let offset = address as usize - START_ADDRESS;
+            let opcode = u16::from_be_bytes([rom[offset], rom[offset + 1]]);
+            instructions.push((address, opcode));
+            if successors(address, Instruction::decode(opcode)).1 {
+                break;
+            }
+            address += 2;
+        }
+        blocks.push((start, instructions));
+    }
+
+    let mut out = String::new();
+    writeln!(out, "// {} recompiled to Rust by `rust-chip8-emulator recompile`. Each function is a basic block of the", name).unwrap();
+    writeln!(out, "// ROM, translated for the quirks in PROGRAM; Chip8::set_recompiled(Some(&PROGRAM)) runs them in place").unwrap();
+    writeln!(out, "// of the interpreter.").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "use rust_chip8_emulator::chip8::Chip8;").unwrap();
+    writeln!(out, "use rust_chip8_emulator::instruction::Instruction;").unwrap();
+    writeln!(out, "use rust_chip8_emulator::quirks::Quirks;").unwrap();
+    writeln!(out, "use rust_chip8_emulator::recompiler::{{Block, Program}};").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "pub static PROGRAM: Program = Program {{").unwrap();
+    writeln!(out, "    sha1: {:?},", sha1).unwrap();
+    writeln!(out, "    quirks: {:?},", quirks).unwrap();
+    writeln!(out, "    blocks: &[").unwrap();
+    for (start, instructions) in &blocks {
+        let code: Vec<String> = instructions.iter().flat_map(|(_, opcode)| opcode.to_be_bytes()).map(|byte| format!("0x{:02X}", byte)).collect();
+        let draws = matches!(instructions.last(), Some((_, opcode)) if opcode >> 12 == 0xD);
+        writeln!(out, "        Block {{ start: 0x{:03X}, code: &[{}], run: block_{:03x}, draws: {} }},", start, code.join(", "), start, draws).unwrap();
+    }
+    writeln!(out, "    ],").unwrap();
+    writeln!(out, "}};").unwrap();
+
+    for (start, instructions) in &blocks {
+        writeln!(out).unwrap();
+        writeln!(out, "fn block_{:03x}(c: &mut Chip8) {{", start).unwrap();
+        let mut ends_with_jump = false;
+        for &(address, opcode) in instructions {
+            writeln!(out, "    // {:03X}: {:04X}  {}", address, opcode, disasm::disassemble(opcode)).unwrap();
+            let (statements, jumps) = statements(address, Instruction::decode(opcode), quirks);
+            for statement in statements {
+                writeln!(out, "    {}", statement).unwrap();
+            }
+            ends_with_jump = jumps;
+        }
+        if !ends_with_jump {
+            let (address, _) = instructions.last().unwrap();
+            writeln!(out, "    c.set_pc(0x{:03X});", (address + 2) & 0x0FFF).unwrap();
+        }
+        writeln!(out, "}}").unwrap();
+    }
+    out
+}
+
+// the Rust for one instruction at address, and whether it sets pc itself
+fn statements(address: u16, instruction: Instruction, quirks: Quirks) -> (Vec<String>, bool) {
+    let next = (address + 2) & 0x0FFF;
+    let skip = |condition: String| (vec![format!("c.set_pc(if {} {{ 0x{:03X} }} else {{ 0x{:03X} }});", condition, (next + 2) & 0x0FFF, next)], true);
+    let v = |x: u8| format!("c.register(0x{:X})", x);
+    let set = |x: u8, value: String| format!("c.set_register(0x{:X}, {});", x, value);
+    //the instruction runs as if it had just been fetched, with pc already past it
+    let execute = |instruction: String, jumps: bool| match jumps {
+        true => (vec![format!("c.set_pc(0x{:03X});", next), format!("c.execute(Instruction::{});", instruction)], true),
+        false => (vec![format!("c.execute(Instruction::{});", instruction)], false),
+    };
+    let logic = |x: u8, operator: &str, y: u8| {
+        let mut statements = vec![set(x, format!("{} {} {}", v(x), operator, v(y)))];
+        if quirks.logic {
+            statements.push(set(0xF, "0".to_string()));
+        }
+        (statements, false)
+    };
+
+    match instruction {
+        Instruction::Clear => execute("Clear".to_string(), false),
+        Instruction::Return => execute("Return".to_string(), true),
+        Instruction::Jump(target) => (vec![format!("c.set_pc(0x{:03X});", target)], true),
+        Instruction::Call(target) => execute(format!("Call(0x{:03X})", target), true),
+        Instruction::SkipIfEqual(x, nn) => skip(format!("{} == 0x{:02X}", v(x), nn)),
+        Instruction::SkipIfNotEqual(x, nn) => skip(format!("{} != 0x{:02X}", v(x), nn)),
+        Instruction::SkipIfRegistersEqual(x, y) => skip(format!("{} == {}", v(x), v(y))),
+        Instruction::SkipIfRegistersNotEqual(x, y) => skip(format!("{} != {}", v(x), v(y))),
+        Instruction::SkipIfKey(x) => skip(format!("c.keypad().is_pressed({})", v(x))),
+        Instruction::SkipIfNotKey(x) => skip(format!("!c.keypad().is_pressed({})", v(x))),
+        Instruction::Load(x, nn) => (vec![set(x, format!("0x{:02X}", nn))], false),
+        Instruction::Add(x, nn) => (vec![set(x, format!("{}.wrapping_add(0x{:02X})", v(x), nn))], false),
+        Instruction::Copy(x, y) => (vec![set(x, v(y))], false),
+        Instruction::Or(x, y) => logic(x, "|", y),
+        Instruction::And(x, y) => logic(x, "&", y),
+        Instruction::Xor(x, y) => logic(x, "^", y),
+        //the flag is set after the result, so it wins when X is F
+        Instruction::AddRegisters(x, y) => (
+            vec![format!("let (sum, carry) = {}.overflowing_add({});", v(x), v(y)), set(x, "sum".to_string()), set(0xF, "carry as u8".to_string())],
+            false,
+        ),
+        Instruction::Subtract(x, y) | Instruction::SubtractFrom(x, y) => {
+            let (minuend, subtrahend) = if let Instruction::Subtract(..) = instruction { (x, y) } else { (y, x) };
+            let difference = format!("let (difference, borrow) = {}.overflowing_sub({});", v(minuend), v(subtrahend));
+            (vec![difference, set(x, "difference".to_string()), set(0xF, "!borrow as u8".to_string())], false)
+        }
+        Instruction::ShiftRight(x, y) | Instruction::ShiftLeft(x, y) => {
+            let value = format!("let value = {};", v(if quirks.shift { x } else { y }));
+            let (shifted, flag) = match instruction {
+                Instruction::ShiftRight(..) => ("value >> 1", "value & 1"),
+                _ => ("value << 1", "value >> 7"),
+            };
+            (vec![value, set(x, shifted.to_string()), set(0xF, flag.to_string())], false)
+        }
+        Instruction::SetIndex(address) => (vec![format!("c.set_i_register(0x{:03X});", address)], false),
+        Instruction::JumpWithOffset(address, x) => {
+            let offset = if quirks.jump { x } else { 0 };
+            (vec![format!("c.set_pc(0x{:03X} + {} as u16);", address, v(offset))], true)
+        }
+        Instruction::AddToIndex(x) => (vec![format!("c.set_i_register(c.i_register().wrapping_add({} as u16));", v(x))], false),
+        Instruction::Character(x) => (vec![format!("c.set_i_register(0x050 + ({} as u16 & 0xF) * 5);", v(x))], false),
+        Instruction::Random(x, nn) => execute(format!("Random(0x{:X}, 0x{:02X})", x, nn), false),
+        Instruction::Draw(x, y, n) => execute(format!("Draw(0x{:X}, 0x{:X}, {})", x, y, n), false),
+        Instruction::WaitForKey(x) => execute(format!("WaitForKey(0x{:X})", x), true),
+        Instruction::ReadDelay(x) => execute(format!("ReadDelay(0x{:X})", x), false),
+        Instruction::SetDelay(x) => execute(format!("SetDelay(0x{:X})", x), false),
+        Instruction::SetSound(x) => execute(format!("SetSound(0x{:X})", x), false),
+        Instruction::Decimal(x) => execute(format!("Decimal(0x{:X})", x), false),
+        Instruction::StoreRegisters(x) => execute(format!("StoreRegisters(0x{:X})", x), false),
+        Instruction::LoadRegisters(x) => execute(format!("LoadRegisters(0x{:X})", x), false),
+        Instruction::SaveFlags(x) => execute(format!("SaveFlags(0x{:X})", x), false),
+        Instruction::LoadFlags(x) => execute(format!("LoadFlags(0x{:X})", x), false),
+        Instruction::Unknown(_) => (vec!["// does nothing".to_string()], false),
+    }
+}
@@ -0,0 +1,246 @@
+// Reading ROM images. Programs are loaded at 0x200, so a ROM can use the rest of the 4 KB address space.
+//
+// ROMs can also be read from archives. A gzipped ROM like brix.ch8.gz holds one program, and a program
+// in a zip file is addressed as if the zip were a directory: roms/pack.zip/games/brix.ch8. A zip with a
+// single program can be given by its own path.
+//
+// Octo source (.8o) and Octo cartridges (.gif) are compiled when they are loaded.
+
+use crate::asm::AsmError;
+use crate::cartridge::{Cartridge, CartridgeError};
+use crate::config::Settings;
+use crate::octo;
+use flate2::read::GzDecoder;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use zip::result::ZipError;
+use zip::ZipArchive;
+
+pub const START_ADDRESS: usize = 0x200;
+pub const MAX_ROM_SIZE: usize = 4096 - START_ADDRESS;
+// Octo source and cartridge GIFs are much bigger than the programs they hold, but still have a limit
+pub const MAX_SOURCE_SIZE: usize = 1 << 20;
+pub const ROM_EXTENSIONS: [&str; 5] = ["ch8", "sc8", "xo8", "8o", "gif"];
+pub const ARCHIVE_EXTENSIONS: [&str; 2] = ["zip", "gz"];
+
+#[derive(Debug)]
+pub enum RomError {
+    Io(io::Error),
+    Empty,
+    TooLarge { size: Option<usize>, max: usize }, // size is None when reading stopped as soon as it went past max
+    SourceTooLarge { max: usize },                // Octo source or a cartridge bigger than MAX_SOURCE_SIZE
+    Archive(ZipError),
+    NoPrograms,
+    SeveralPrograms(Vec<String>), // the programs in a zip given without picking one
+    Compile(AsmError),
+    Cartridge(CartridgeError),
+    Settings(String), // the settings the file asks for don't work
+}
+
+impl fmt::Display for RomError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RomError::Io(e) => write!(f, "{}", e),
+            RomError::Empty => write!(f, "the ROM is empty"),
+            RomError::TooLarge { size: Some(size), max } => {
+                write!(f, "the ROM is {} bytes but only {} bytes fit in memory after 0x{:X}", size, max, START_ADDRESS)
+            }
+            RomError::TooLarge { size: None, max } => {
+                write!(f, "the ROM is more than the {} bytes that fit in memory after 0x{:X}", max, START_ADDRESS)
+            }
+            RomError::SourceTooLarge { max } => write!(f, "the file is more than {} bytes, which is too big to be a CHIP-8 program", max),
+            RomError::Archive(e) => write!(f, "{}", e),
+            RomError::NoPrograms => write!(f, "the archive holds no CHIP-8 programs"),
+            RomError::SeveralPrograms(names) => {
+                write!(f, "the archive holds {} programs, pick one by adding its name to the path: {}", names.len(), names.join(", "))
+            }
+            RomError::Compile(e) => write!(f, "{}", e),
+            RomError::Cartridge(e) => write!(f, "{}", e),
+            RomError::Settings(message) => write!(f, "the file's settings are invalid: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for RomError {}
+
+impl From<io::Error> for RomError {
+    fn from(e: io::Error) -> Self {
+        RomError::Io(e)
+    }
+}
+
+impl From<ZipError> for RomError {
+    fn from(e: ZipError) -> Self {
+        match e {
+            ZipError::Io(e) => RomError::Io(e),
+            e => RomError::Archive(e),
+        }
+    }
+}
+
+impl From<CartridgeError> for RomError {
+    fn from(e: CartridgeError) -> Self {
+        match e {
+            CartridgeError::Compile(e) => RomError::Compile(e),
+            e => RomError::Cartridge(e),
+        }
+    }
+}
+
+// a program ready to run, with the settings its file asks for
+#[derive(Debug, Clone)]
+pub struct RomFile {
+    pub path: PathBuf, // after resolve
+    pub data: Vec<u8>,
+    pub settings: Settings, // the platform a .sc8 or .xo8 hints at, or a cartridge's options
+}
+
+pub fn load(path: &Path) -> Result<RomFile, RomError> {
+    let path = resolve(path)?;
+    let file_name = inner_name(&path);
+    let (data, settings) = if has_extension(&file_name, &["gif"]) {
+        let cartridge = Cartridge::decode(contents(&path, MAX_SOURCE_SIZE, source_too_large)?.as_slice())?;
+        (cartridge.compile()?, cartridge.settings())
+    } else if has_extension(&file_name, &["8o"]) {
+        let source = String::from_utf8(contents(&path, MAX_SOURCE_SIZE, source_too_large)?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        (octo::compile(&source).map_err(RomError::Compile)?, Settings::default())
+    } else {
+        let platform = platform_hint(&file_name).map(String::from);
+        (contents(&path, MAX_ROM_SIZE, rom_too_large)?, Settings { platform, ..Settings::default() })
+    };
+    validate(&data)?;
+    Ok(RomFile { path, data, settings })
+}
+
+// the file's contents, from inside an archive if the path goes through one; nothing past max + 1 bytes is
+// decompressed, so an archive that unpacks to far more than it should fails early
+fn contents(path: &Path, max: usize, too_large: fn(Option<usize>) -> RomError) -> Result<Vec<u8>, RomError> {
+    if let Some((archive, entry)) = split_zip_path(path) {
+        let mut zip = ZipArchive::new(File::open(archive)?)?;
+        let file = zip.by_name(&entry)?;
+        //the size in the zip's directory is only a claim, so the read is limited as well
+        if file.size() > max as u64 {
+            return Err(too_large(usize::try_from(file.size()).ok()));
+        }
+        return read_limited(file, max, too_large);
+    }
+    let file = File::open(path)?;
+    if has_extension(path, &["gz"]) {
+        read_limited(GzDecoder::new(file), max, too_large)
+    } else {
+        read_limited(file, max, too_large)
+    }
+}
+
+// a zip given by its own path becomes the path of the one program inside it
+pub fn resolve(path: &Path) -> Result<PathBuf, RomError> {
+    if !(path.is_file() && has_extension(path, &["zip"])) {
+        return Ok(path.to_path_buf());
+    }
+    let mut programs = list_archive(path)?;
+    match programs.len() {
+        0 => Err(RomError::NoPrograms),
+        1 => Ok(programs.remove(0)),
+        _ => Err(RomError::SeveralPrograms(programs.iter().map(|program| program.display().to_string()).collect())),
+    }
+}
+
+// the CHIP-8 programs in an archive, as paths load can open
+pub fn list_archive(path: &Path) -> Result<Vec<PathBuf>, RomError> {
+    if has_extension(path, &["gz"]) {
+        return Ok(if has_extension(&inner_name(path), &ROM_EXTENSIONS) { vec![path.to_path_buf()] } else { Vec::new() });
+    }
+    let zip = ZipArchive::new(File::open(path)?)?;
+    let mut programs: Vec<PathBuf> = zip
+        .file_names()
+        .filter(|name| !name.ends_with('/') && has_extension(Path::new(name), &ROM_EXTENSIONS))
+        .map(|name| path.join(name))
+        .collect();
+    programs.sort();
+    Ok(programs)
+}
+
+pub fn is_archive(path: &Path) -> bool {
+    has_extension(path, &ARCHIVE_EXTENSIONS)
+}
+
+// whether path is a file, or a program in a zip file that exists
+pub fn exists(path: &Path) -> bool {
+    path.is_file() || split_zip_path(path).is_some()
+}
+
+// the program's file name without the extension, brix for roms/brix.ch8.gz
+pub fn name(path: &Path) -> String {
+    inner_name(path).file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default()
+}
+
+// the quirk preset a file extension asks for
+fn platform_hint(file_name: &Path) -> Option<&'static str> {
+    match file_name.extension()?.to_str()?.to_lowercase().as_str() {
+        "sc8" => Some("schip"),
+        "xo8" => Some("xochip"),
+        _ => None,
+    }
+}
+
+// the program's file name, without the .gz of a gzipped ROM
+fn inner_name(path: &Path) -> PathBuf {
+    let file_name = PathBuf::from(path.file_name().unwrap_or_default());
+    if has_extension(&file_name, &["gz"]) {
+        PathBuf::from(file_name.file_stem().unwrap_or_default())
+    } else {
+        file_name
+    }
+}
+
+// the zip file a path goes through, and the name of the entry inside it
+fn split_zip_path(path: &Path) -> Option<(&Path, String)> {
+    let archive = path.ancestors().skip(1).find(|ancestor| ancestor.is_file())?;
+    if !has_extension(archive, &["zip"]) {
+        return None;
+    }
+    let entry = path.strip_prefix(archive).ok()?;
+    let parts: Vec<String> = entry.components().map(|part| part.as_os_str().to_string_lossy().into_owned()).collect();
+    Some((archive, parts.join("/")))
+}
+
+fn has_extension(path: &Path, extensions: &[&str]) -> bool {
+    path.extension().and_then(|ext| ext.to_str()).is_some_and(|ext| extensions.contains(&ext.to_lowercase().as_str()))
+}
+
+// reads the whole ROM, stopping one byte past the most that fits so endless or compressed input can't run on
+pub fn read(reader: impl Read) -> Result<Vec<u8>, RomError> {
+    let rom = read_limited(reader, MAX_ROM_SIZE, rom_too_large)?;
+    validate(&rom)?;
+    Ok(rom)
+}
+
+fn read_limited(reader: impl Read, max: usize, too_large: fn(Option<usize>) -> RomError) -> Result<Vec<u8>, RomError> {
+    let mut contents = Vec::new();
+    reader.take(max as u64 + 1).read_to_end(&mut contents)?;
+    if contents.len() > max {
+        return Err(too_large(None));
+    }
+    Ok(contents)
+}
+
+fn rom_too_large(size: Option<usize>) -> RomError {
+    RomError::TooLarge { size, max: MAX_ROM_SIZE }
+}
+
+fn source_too_large(_size: Option<usize>) -> RomError {
+    RomError::SourceTooLarge { max: MAX_SOURCE_SIZE }
+}
+
+pub fn validate(rom: &[u8]) -> Result<(), RomError> {
+    if rom.is_empty() {
+        return Err(RomError::Empty);
+    }
+    if rom.len() > MAX_ROM_SIZE {
+        return Err(RomError::TooLarge { size: Some(rom.len()), max: MAX_ROM_SIZE });
+    }
+    Ok(())
+}
@@ -0,0 +1,112 @@
+use crate::display::Display;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const SCREENSHOT_DIR: &str = "screenshots";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    Png, // palette, filter and scale applied, as seen in the window
+    Pbm, // raw 1-bit frame buffer, one image pixel per CHIP-8 pixel
+}
+
+impl ImageFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ImageFormat::Png => "png",
+            ImageFormat::Pbm => "pbm",
+        }
+    }
+}
+
+// saves the display into dir as <rom>_<timestamp>.<ext> and returns the path written
+pub fn save(display: &Display, dir: &Path, rom_name: &str, format: ImageFormat) -> io::Result<PathBuf> {
+    let path = timestamped_path(dir, rom_name, format.extension())?;
+    let mut writer = BufWriter::new(File::create(&path)?);
+    match format {
+        ImageFormat::Png => write_png(display, &mut writer)?,
+        ImageFormat::Pbm => write_pbm(display, &mut writer)?,
+    }
+    writer.flush()?;
+    Ok(path)
+}
+
+// dir/<rom>_<timestamp>.<extension>, creating dir if needed
+pub fn timestamped_path(dir: &Path, rom_name: &str, extension: &str) -> io::Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+    let rom_name = if rom_name.is_empty() { "chip8" } else { rom_name };
+    Ok(dir.join(format!("{}_{}.{}", rom_name, timestamp(), extension)))
+}
+
+pub fn write_png<W: Write>(display: &Display, writer: W) -> io::Result<()> {
+    let (pixels, width, height) = display.scaled_pixels();
+    let mut encoder = png::Encoder::new(writer, width as u32, height as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    //minifb pixels are 0RGB, so the top byte is dropped
+    let mut data = Vec::with_capacity(pixels.len() * 3);
+    for pixel in pixels {
+        data.extend_from_slice(&[(pixel >> 16) as u8, (pixel >> 8) as u8, pixel as u8]);
+    }
+
+    let mut png_writer = encoder.write_header().map_err(io::Error::other)?;
+    png_writer.write_image_data(&data).map_err(io::Error::other)?;
+    png_writer.finish().map_err(io::Error::other)
+}
+
+// binary (P4) PBM of the frame buffer, where 1 is a lit pixel
+pub fn write_pbm<W: Write>(display: &Display, writer: W) -> io::Result<()> {
+    let (width, height) = display.resolution();
+    let lit: Vec<bool> = display.frame_buffer.iter().flatten().copied().collect();
+    write_pbm_pixels(&lit, width, height, writer)
+}
+
+// binary (P4) PBM of width x height row-major pixels, each row padded out to a whole byte
+pub fn write_pbm_pixels<W: Write>(lit: &[bool], width: usize, height: usize, mut writer: W) -> io::Result<()> {
+    write!(writer, "P4\n{} {}\n", width, height)?;
+    for row in lit.chunks(width).take(height) {
+        let mut packed = vec![0u8; width.div_ceil(8)];
+        for (col, &pixel) in row.iter().enumerate() {
+            if pixel {
+                packed[col / 8] |= 0x80 >> (col % 8);
+            }
+        }
+        writer.write_all(&packed)?;
+    }
+    Ok(())
+}
+
+// UTC time as YYYYMMDD-HHMMSS-mmm, so screenshots sort by when they were taken
+fn timestamp() -> String {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = now.as_secs();
+    let (year, month, day) = civil_from_days((secs / 86400) as i64);
+    let time_of_day = secs % 86400;
+    format!(
+        "{:04}{:02}{:02}-{:02}{:02}{:02}-{:03}",
+        year,
+        month,
+        day,
+        time_of_day / 3600,
+        (time_of_day / 60) % 60,
+        time_of_day % 60,
+        now.subsec_millis()
+    )
+}
+
+//days since 1970-01-01 to a (year, month, day) date, Howard Hinnant's algorithm
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
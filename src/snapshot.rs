@@ -0,0 +1,128 @@
+// Frame buffer snapshots for regression tests. The .txt form is one line per row with # for a lit pixel
+// and . for a dark one, so diffs of a snapshot are readable; anything else is a 64x32 black and white
+// PNG, one image pixel per CHIP-8 pixel.
+
+use crate::display::{Display, HEIGHT, WIDTH};
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter};
+use std::path::Path;
+
+#[derive(Debug)]
+pub enum SnapshotError {
+    Io(io::Error),
+    Invalid(String),
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotError::Io(e) => write!(f, "{}", e),
+            SnapshotError::Invalid(message) => write!(f, "invalid snapshot: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+impl From<io::Error> for SnapshotError {
+    fn from(e: io::Error) -> Self {
+        SnapshotError::Io(e)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Snapshot {
+    pub pixels: [[bool; WIDTH]; HEIGHT],
+}
+
+impl Snapshot {
+    pub fn of(display: &Display) -> Self {
+        Snapshot { pixels: display.frame_buffer }
+    }
+
+    pub fn load(path: &Path) -> Result<Self, SnapshotError> {
+        if is_text(path) {
+            Snapshot::parse(&fs::read_to_string(path)?).map_err(SnapshotError::Invalid)
+        } else {
+            Snapshot::read_png(path)
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        if is_text(path) {
+            return fs::write(path, self.to_string());
+        }
+        let mut encoder = png::Encoder::new(BufWriter::new(File::create(path)?), WIDTH as u32, HEIGHT as u32);
+        encoder.set_color(png::ColorType::Grayscale);
+        encoder.set_depth(png::BitDepth::Eight);
+        let data: Vec<u8> = self.pixels.iter().flatten().map(|&pixel| if pixel { 0xFF } else { 0 }).collect();
+        let mut writer = encoder.write_header().map_err(io::Error::other)?;
+        writer.write_image_data(&data).map_err(io::Error::other)?;
+        writer.finish().map_err(io::Error::other)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let rows: Vec<&str> = text.lines().map(str::trim_end).filter(|row| !row.is_empty()).collect();
+        if rows.len() != HEIGHT {
+            return Err(format!("expected {} rows, found {}", HEIGHT, rows.len()));
+        }
+        let mut pixels = [[false; WIDTH]; HEIGHT];
+        for (y, row) in rows.iter().enumerate() {
+            if row.chars().count() != WIDTH {
+                return Err(format!("row {} should be {} pixels wide", y + 1, WIDTH));
+            }
+            for (x, c) in row.chars().enumerate() {
+                pixels[y][x] = match c {
+                    '#' => true,
+                    '.' => false,
+                    _ => return Err(format!("row {} has '{}', only # and . are pixels", y + 1, c)),
+                };
+            }
+        }
+        Ok(Snapshot { pixels })
+    }
+
+    // how many pixels differ
+    pub fn diff(&self, other: &Snapshot) -> usize {
+        self.pixels.iter().flatten().zip(other.pixels.iter().flatten()).filter(|(a, b)| a != b).count()
+    }
+
+    // any pixel brighter than mid-gray is lit, so screenshots with a palette work as long as they're 64x32
+    fn read_png(path: &Path) -> Result<Self, SnapshotError> {
+        let mut decoder = png::Decoder::new(BufReader::new(File::open(path)?));
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info().map_err(|e| SnapshotError::Invalid(e.to_string()))?;
+        let mut data = vec![0; reader.output_buffer_size().unwrap_or(0)];
+        let info = reader.next_frame(&mut data).map_err(|e| SnapshotError::Invalid(e.to_string()))?;
+        if (info.width as usize, info.height as usize) != (WIDTH, HEIGHT) {
+            return Err(SnapshotError::Invalid(format!("the image is {}x{}, not {}x{}", info.width, info.height, WIDTH, HEIGHT)));
+        }
+
+        let channels = info.color_type.samples();
+        let mut pixels = [[false; WIDTH]; HEIGHT];
+        for (index, pixel) in data[..info.buffer_size()].chunks_exact(channels).enumerate() {
+            let color = if channels >= 3 { &pixel[..3] } else { &pixel[..1] };
+            let brightness = color.iter().map(|&value| value as usize).sum::<usize>() / color.len();
+            pixels[index / WIDTH][index % WIDTH] = brightness > 0x7F;
+        }
+        Ok(Snapshot { pixels })
+    }
+}
+
+impl fmt::Display for Snapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for row in &self.pixels {
+            let line: String = row.iter().map(|&pixel| if pixel { '#' } else { '.' }).collect();
+            writeln!(f, "{}", line)?;
+        }
+        Ok(())
+    }
+}
+
+fn is_text(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "txt")
+}
@@ -1,36 +1,58 @@
+use std::fmt;
+
 const STACK_MAX: usize = 16;
 
-#[derive(Debug)]
+// a program that calls too deep or returns with nothing on the stack has a bug; the interpreter stops on
+// the instruction instead of making up an address to carry on from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StackError {
+    Overflow,
+    Underflow,
+}
+
+impl fmt::Display for StackError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StackError::Overflow => write!(f, "stack overflow"),
+            StackError::Underflow => write!(f, "stack underflow"),
+        }
+    }
+}
+
+impl std::error::Error for StackError {}
+
+#[derive(Debug, Clone)]
 pub struct Stack {
     pub stack: [u16; STACK_MAX],
     pub stack_pointer: usize
 }
 
-impl Stack {
-    pub fn default() -> Self {
-        let stack = Stack {
+impl Default for Stack {
+    fn default() -> Self {
+        Stack {
             stack: [0; STACK_MAX],
             stack_pointer: 0,
-        };
-
-        stack
+        }
     }
+}
 
-    pub fn push(&mut self, address: u16) {
+impl Stack {
+    pub fn push(&mut self, address: u16) -> Result<(), StackError> {
         if self.stack_pointer >= STACK_MAX {
-            panic!("Stack overflow");
+            return Err(StackError::Overflow);
         }
 
         self.stack[self.stack_pointer] = address;
         self.stack_pointer += 1;
+        Ok(())
     }
 
-    pub fn pop(&mut self) -> u16 {
-        if self.stack_pointer <= 0 {
-            panic!("Stack underflow");
+    pub fn pop(&mut self) -> Result<u16, StackError> {
+        if self.stack_pointer == 0 {
+            return Err(StackError::Underflow);
         }
 
         self.stack_pointer -= 1;
-        self.stack[self.stack_pointer]
+        Ok(self.stack[self.stack_pointer])
     }
-}
\ No newline at end of file
+}
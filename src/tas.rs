@@ -0,0 +1,89 @@
+// Tool-assisted play: the keypad is held in an editable log with one entry per frame, and any frame can
+// be revisited by loading the nearest keyframe before it and emulating forward with the logged input.
+
+use crate::chip8::SaveState;
+use crate::keypad::NUM_KEYS;
+use std::collections::BTreeMap;
+
+// frames between keyframes, so seeking never emulates more than this many frames
+pub const KEYFRAME_INTERVAL: u64 = 60;
+
+#[derive(Debug, Clone)]
+pub struct Tas {
+    inputs: Vec<u16>, // keypad state for each frame from power-on, one bit per key
+    keyframes: BTreeMap<u64, SaveState>, // machine state at the start of a frame, before its input
+}
+
+impl Tas {
+    // start is the machine at power-on
+    pub fn new(start: SaveState) -> Self {
+        Tas {
+            inputs: Vec::new(),
+            keyframes: BTreeMap::from([(0, start)]),
+        }
+    }
+
+    // number of frames with logged input
+    pub fn len(&self) -> u64 {
+        self.inputs.len() as u64
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inputs.is_empty()
+    }
+
+    // input for frame, past the end of the log the last input stays held
+    pub fn input(&self, frame: u64) -> u16 {
+        self.inputs.get(frame as usize).or(self.inputs.last()).copied().unwrap_or(0)
+    }
+
+    // changes the input of one frame, keeping the frames after it
+    pub fn set_input(&mut self, frame: u64, input: u16) {
+        while self.len() <= frame {
+            self.inputs.push(self.input(frame));
+        }
+        if self.inputs[frame as usize] != input {
+            self.inputs[frame as usize] = input;
+            self.keyframes.retain(|&keyframe, _| keyframe <= frame);
+        }
+    }
+
+    pub fn toggle_key(&mut self, frame: u64, key: u8) {
+        self.set_input(frame, self.input(frame) ^ (1 << (key & 0x0F)));
+    }
+
+    // drops the input from frame on, so play continues recording from there
+    pub fn truncate(&mut self, frame: u64) {
+        self.inputs.truncate(frame as usize);
+        self.keyframes.retain(|&keyframe, _| keyframe <= frame);
+    }
+
+    // called with the machine at the start of frame, keeps it if it falls on a keyframe
+    pub fn add_keyframe(&mut self, frame: u64, state: impl FnOnce() -> SaveState) {
+        if frame.is_multiple_of(KEYFRAME_INTERVAL) && !self.keyframes.contains_key(&frame) {
+            self.keyframes.insert(frame, state());
+        }
+    }
+
+    // latest keyframe at or before frame; frame 0 is never dropped
+    pub fn keyframe_before(&self, frame: u64) -> (u64, &SaveState) {
+        let (&keyframe, state) = self.keyframes.range(..=frame).next_back().expect("power-on keyframe");
+        (keyframe, state)
+    }
+}
+
+pub fn input_states(input: u16) -> [bool; NUM_KEYS] {
+    std::array::from_fn(|key| input & (1 << key) != 0)
+}
+
+pub fn input_bits(states: [bool; NUM_KEYS]) -> u16 {
+    states.iter().enumerate().fold(0, |bits, (key, &pressed)| bits | ((pressed as u16) << key))
+}
+
+// the held keys as hex digits, "-" when none are
+pub fn input_name(input: u16) -> String {
+    if input == 0 {
+        return String::from("-");
+    }
+    (0..NUM_KEYS).filter(|key| input & (1 << key) != 0).map(|key| format!("{:X}", key)).collect()
+}
@@ -0,0 +1,291 @@
+// Terminal frontend: draws the frame buffer with Unicode half-blocks or Braille and reads the keypad
+// from raw terminal input, so ROMs can be played over SSH without a window system.
+
+use crate::display::Display;
+use crate::keymap::{self, Hotkey, Keymap};
+use crate::keypad::NUM_KEYS;
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers, KeyboardEnhancementFlags};
+use crossterm::style::{Color, Print, ResetColor, SetBackgroundColor, SetForegroundColor};
+use crossterm::{cursor, execute, queue, terminal};
+use std::io::{self, Stdout, Write};
+use std::time::{Duration, Instant};
+
+//most terminals only report presses (plus autorepeat), so a key counts as held for this long after its last event
+pub const HOLD_TIME: Duration = Duration::from_millis(150);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TerminalMode {
+    HalfBlocks, // one cell per 1x2 pixels, both colors
+    Braille,    // one cell per 2x4 pixels, foreground color only
+}
+
+impl TerminalMode {
+    // pixels covered by one cell, as (width, height)
+    pub fn cell_size(self) -> (usize, usize) {
+        match self {
+            TerminalMode::HalfBlocks => (1, 2),
+            TerminalMode::Braille => (2, 4),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cell {
+    pub symbol: char,
+    pub foreground: u32,
+    pub background: u32,
+}
+
+// the cells drawn last, so only the ones that changed are drawn again
+#[derive(Debug, Default)]
+pub struct CellCache {
+    cells: Vec<Option<Cell>>,
+}
+
+impl CellCache {
+    // indices of the cells that differ from what was drawn, which are then taken as drawn
+    pub fn changed(&mut self, cells: &[Cell]) -> Vec<usize> {
+        if self.cells.len() != cells.len() {
+            self.cells = vec![None; cells.len()];
+        }
+        let mut changed = Vec::new();
+        for (index, (drawn, &cell)) in self.cells.iter_mut().zip(cells).enumerate() {
+            if *drawn != Some(cell) {
+                *drawn = Some(cell);
+                changed.push(index);
+            }
+        }
+        changed
+    }
+
+    // forgets what was drawn, so every cell is drawn again
+    pub fn clear(&mut self) {
+        self.cells.clear();
+    }
+}
+
+// keypad state from terminal key events, which only come with releases when the terminal reports them
+#[derive(Debug, Clone)]
+pub struct KeyHold {
+    pressed_at: [Option<Instant>; NUM_KEYS],
+    held: [bool; NUM_KEYS],
+    release_events: bool, // terminal reports key releases (kitty keyboard protocol)
+}
+
+impl KeyHold {
+    pub fn new(release_events: bool) -> Self {
+        KeyHold {
+            pressed_at: [None; NUM_KEYS],
+            held: [false; NUM_KEYS],
+            release_events,
+        }
+    }
+
+    // a press or autorepeat
+    pub fn press(&mut self, key: usize, now: Instant) {
+        self.pressed_at[key] = Some(now);
+        self.held[key] = true;
+    }
+
+    pub fn release(&mut self, key: usize) {
+        self.held[key] = false;
+    }
+
+    pub fn states(&self, now: Instant) -> [bool; NUM_KEYS] {
+        let mut states = [false; NUM_KEYS];
+        for (key, state) in states.iter_mut().enumerate() {
+            *state = if self.release_events {
+                self.held[key]
+            } else {
+                self.pressed_at[key].is_some_and(|pressed_at| now.saturating_duration_since(pressed_at) < HOLD_TIME)
+            };
+        }
+        states
+    }
+}
+
+// the frame buffer as rows of terminal cells, returned with the number of columns and rows
+pub fn cells(display: &Display, mode: TerminalMode) -> (Vec<Cell>, usize, usize) {
+    let (width, height) = display.resolution();
+    let (cell_width, cell_height) = mode.cell_size();
+    let columns = width.div_ceil(cell_width);
+    let rows = height.div_ceil(cell_height);
+
+    let pixel = |x: usize, y: usize| x < width && y < height && display.frame_buffer[y][x];
+    let palette = display.palette;
+
+    let mut cells = Vec::with_capacity(columns * rows);
+    for row in 0..rows {
+        for column in 0..columns {
+            let (x, y) = (column * cell_width, row * cell_height);
+            cells.push(match mode {
+                TerminalMode::HalfBlocks => Cell {
+                    symbol: '▀',
+                    foreground: if pixel(x, y) { palette.foreground } else { palette.background },
+                    background: if pixel(x, y + 1) { palette.foreground } else { palette.background },
+                },
+                TerminalMode::Braille => {
+                    //Braille dot bits, indexed [dy][dx]
+                    const DOTS: [[u32; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+                    let mut bits = 0;
+                    for (dy, dots) in DOTS.iter().enumerate() {
+                        for (dx, dot) in dots.iter().enumerate() {
+                            if pixel(x + dx, y + dy) {
+                                bits |= dot;
+                            }
+                        }
+                    }
+                    Cell {
+                        symbol: char::from_u32(0x2800 + bits).unwrap_or(' '),
+                        foreground: palette.foreground,
+                        background: palette.background,
+                    }
+                }
+            });
+        }
+    }
+    (cells, columns, rows)
+}
+
+#[derive(Debug)]
+pub struct Terminal {
+    stdout: Stdout,
+    mode: TerminalMode,
+    cache: CellCache,
+    keys: KeyHold,
+    release_events: bool, // terminal reports key releases, so the enhancement flags are popped on exit
+    keymap: Keymap,
+    hotkeys: Vec<Hotkey>, // hotkeys pressed since the last take_hotkeys
+}
+
+impl Terminal {
+    pub fn new(mode: TerminalMode) -> io::Result<Self> {
+        let mut stdout = io::stdout();
+        terminal::enable_raw_mode()?;
+        execute!(stdout, terminal::EnterAlternateScreen, cursor::Hide, terminal::Clear(terminal::ClearType::All))?;
+
+        let release_events = terminal::supports_keyboard_enhancement().unwrap_or(false);
+        if release_events {
+            execute!(stdout, event::PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::REPORT_EVENT_TYPES))?;
+        }
+
+        Ok(Terminal {
+            stdout,
+            mode,
+            cache: CellCache::default(),
+            keys: KeyHold::new(release_events),
+            release_events,
+            keymap: Keymap::default(),
+            hotkeys: Vec::new(),
+        })
+    }
+
+    pub fn set_keymap(&mut self, keymap: Keymap) {
+        self.keymap = keymap;
+    }
+
+    pub fn take_hotkeys(&mut self) -> Vec<Hotkey> {
+        std::mem::take(&mut self.hotkeys)
+    }
+
+    // drains pending terminal events without blocking
+    pub fn poll_input(&mut self) -> io::Result<()> {
+        while event::poll(Duration::ZERO)? {
+            match event::read()? {
+                Event::Key(key_event) => {
+                    //raw mode swallows SIGINT, so Ctrl+C always quits
+                    if key_event.modifiers.contains(KeyModifiers::CONTROL) && key_event.code == KeyCode::Char('c') {
+                        self.hotkeys.push(Hotkey::Quit);
+                        continue;
+                    }
+                    let Some(key) = to_host_key(key_event.code) else { continue };
+                    if let Some(hotkey) = self.keymap.hotkey_for(key) {
+                        if key_event.kind == KeyEventKind::Press {
+                            self.hotkeys.push(hotkey);
+                        }
+                        continue;
+                    }
+                    if let Some(index) = self.keymap.keypad_index(key) {
+                        match key_event.kind {
+                            KeyEventKind::Press | KeyEventKind::Repeat => self.keys.press(index, Instant::now()),
+                            KeyEventKind::Release => self.keys.release(index),
+                        }
+                    }
+                }
+                Event::Resize(_, _) => {
+                    //the terminal may have been cleared, so draw everything again
+                    self.cache.clear();
+                    execute!(self.stdout, terminal::Clear(terminal::ClearType::All))?;
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    pub fn key_states(&self) -> [bool; NUM_KEYS] {
+        self.keys.states(Instant::now())
+    }
+
+    pub fn render(&mut self, display: &Display, status: &str) -> io::Result<()> {
+        let (cells, columns, rows) = cells(display, self.mode);
+        for index in self.cache.changed(&cells) {
+            let cell = cells[index];
+            queue!(
+                self.stdout,
+                cursor::MoveTo((index % columns) as u16, (index / columns) as u16),
+                SetForegroundColor(to_color(cell.foreground)),
+                SetBackgroundColor(to_color(cell.background)),
+                Print(cell.symbol)
+            )?;
+        }
+
+        queue!(
+            self.stdout,
+            ResetColor,
+            cursor::MoveTo(0, rows as u16),
+            terminal::Clear(terminal::ClearType::CurrentLine),
+            Print(status)
+        )?;
+        self.stdout.flush()
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        if self.release_events {
+            let _ = execute!(self.stdout, event::PopKeyboardEnhancementFlags);
+        }
+        let _ = execute!(self.stdout, ResetColor, cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+fn to_color(color: u32) -> Color {
+    Color::Rgb {
+        r: (color >> 16) as u8,
+        g: (color >> 8) as u8,
+        b: color as u8,
+    }
+}
+
+// the window's key for a terminal key code, so both frontends share one keymap
+fn to_host_key(code: KeyCode) -> Option<minifb::Key> {
+    use minifb::Key;
+    match code {
+        KeyCode::Char(c) => keymap::key_from_char(c),
+        KeyCode::F(n @ 1..=12) => [
+            Key::F1, Key::F2, Key::F3, Key::F4, Key::F5, Key::F6, Key::F7, Key::F8, Key::F9, Key::F10, Key::F11, Key::F12,
+        ].get(n as usize - 1).copied(),
+        KeyCode::Esc => Some(Key::Escape),
+        KeyCode::Enter => Some(Key::Enter),
+        KeyCode::Tab => Some(Key::Tab),
+        KeyCode::Backspace => Some(Key::Backspace),
+        KeyCode::Delete => Some(Key::Delete),
+        KeyCode::Up => Some(Key::Up),
+        KeyCode::Down => Some(Key::Down),
+        KeyCode::Left => Some(Key::Left),
+        KeyCode::Right => Some(Key::Right),
+        _ => None,
+    }
+}
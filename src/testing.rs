@@ -0,0 +1,98 @@
+// Headless test runs: a ROM is fed scripted key presses and run for a number of frames, or until the
+// program parks itself on a jump to its own address, and the screen it ends on is compared with a
+// snapshot. Key scripts are frame:key+ to press and frame:key- to release, with keys in hex:
+//
+//     60:5+ 90:5- 120:a+
+//
+// pressing 5 at frame 60, letting go at frame 90 and holding A from frame 120 on.
+
+use crate::chip8::Chip8;
+use crate::keypad::NUM_KEYS;
+use crate::snapshot::{Snapshot, SnapshotError};
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyEvent {
+    pub frame: u64,
+    pub key: u8,
+    pub pressed: bool,
+}
+
+// events separated by spaces or commas, sorted by frame
+pub fn parse_keys(script: &str) -> Result<Vec<KeyEvent>, String> {
+    let mut events = Vec::new();
+    for part in script.split([' ', ',', '\n']).filter(|part| !part.is_empty()) {
+        let error = || format!("expected frame:key+ or frame:key-, got \"{}\"", part);
+        let (frame, key) = part.split_once(':').ok_or_else(error)?;
+        let (key, pressed) = match key.strip_suffix('+') {
+            Some(key) => (key, true),
+            None => (key.strip_suffix('-').ok_or_else(error)?, false),
+        };
+        let frame = frame.parse().map_err(|_| error())?;
+        let key = u8::from_str_radix(key, 16).ok().filter(|&key| (key as usize) < NUM_KEYS).ok_or_else(error)?;
+        events.push(KeyEvent { frame, key, pressed });
+    }
+    events.sort_by_key(|event| event.frame);
+    Ok(events)
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TestRun {
+    pub frames: u64, // the most frames to run
+    pub keys: Vec<KeyEvent>,
+    pub until_halt: bool, // stop early once the program jumps to itself forever
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestResult {
+    pub frames: u64,
+    pub halted: bool,
+    pub snapshot: Snapshot,
+}
+
+impl TestRun {
+    // runs the ROM loaded into chip8 from where it is
+    pub fn run(&self, chip8: &mut Chip8) -> TestResult {
+        let start = chip8.frame();
+        let mut halted = false;
+        while chip8.frame() - start < self.frames {
+            let frame = chip8.frame() - start;
+            for event in self.keys.iter().filter(|event| event.frame == frame) {
+                if event.pressed {
+                    chip8.press_key(event.key);
+                } else {
+                    chip8.release_key(event.key);
+                }
+            }
+            chip8.step_frame();
+            if self.until_halt && chip8.is_halted() {
+                halted = true;
+                break;
+            }
+        }
+        TestResult { frames: chip8.frame() - start, halted, snapshot: Snapshot::of(chip8.display()) }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Match,
+    Mismatch { pixels: usize }, // how many pixels differ
+    Missing,                    // there is no snapshot to compare with yet
+    Updated,
+}
+
+// compares snapshot with the one stored at path, or stores it there when update is set
+pub fn compare(snapshot: &Snapshot, path: &Path, update: bool) -> Result<Comparison, SnapshotError> {
+    if update {
+        snapshot.save(path)?;
+        return Ok(Comparison::Updated);
+    }
+    if !path.exists() {
+        return Ok(Comparison::Missing);
+    }
+    Ok(match snapshot.diff(&Snapshot::load(path)?) {
+        0 => Comparison::Match,
+        pixels => Comparison::Mismatch { pixels },
+    })
+}
@@ -1,19 +1,10 @@
-#[derive(Debug)]
+#[derive(Debug, Clone, Default)]
 pub struct Timers {
     pub dt_register: u8,
     pub st_register: u8
 }
 
 impl Timers {
-    pub fn default() -> Self {
-        let timer = Timers {
-            dt_register: 0,
-            st_register: 0,
-        };
-
-        timer
-    }
-
     pub fn decrement_timers(&mut self) {
         if self.dt_register > 0 {
             self.dt_register -= 1;
@@ -0,0 +1,149 @@
+use crate::display::Display;
+use crate::filters;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+pub const RECORDING_DIR: &str = "recordings";
+pub const DEFAULT_VIDEO_SCALE: usize = 4;
+const FPS: u64 = 60;
+// GIF delays are in 1/100 s, so every frame is kept and three frames take 5/100 s, exactly 60 fps
+const GIF_DELAYS: [u16; 3] = [2, 2, 1];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VideoFormat {
+    Gif, // two-color animated GIF, small enough to attach to bug reports
+    Y4m, // uncompressed YUV 4:4:4 stream for piping into an encoder
+}
+
+impl VideoFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            VideoFormat::Gif => "gif",
+            VideoFormat::Y4m => "y4m",
+        }
+    }
+}
+
+enum Encoder {
+    Gif(gif::Encoder<BufWriter<Box<dyn Write>>>),
+    Y4m(BufWriter<Box<dyn Write>>),
+}
+
+// records the display palette (without filters) at a fixed whole-number scale, one video frame per emulated frame
+pub struct VideoRecorder {
+    encoder: Encoder,
+    format: VideoFormat,
+    scale: usize,
+    width: usize,
+    height: usize,
+    frames: u64,
+}
+
+impl std::fmt::Debug for VideoRecorder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("VideoRecorder")
+            .field("format", &self.format)
+            .field("scale", &self.scale)
+            .field("frames", &self.frames)
+            .finish()
+    }
+}
+
+impl VideoRecorder {
+    // a path of "-" writes to stdout, which is mostly useful for piping Y4M into ffmpeg
+    pub fn create(path: &Path, format: VideoFormat, display: &Display, scale: usize) -> io::Result<Self> {
+        let writer: Box<dyn Write> = if path == Path::new("-") {
+            Box::new(io::stdout())
+        } else {
+            Box::new(File::create(path)?)
+        };
+        VideoRecorder::from_writer(writer, format, display, scale)
+    }
+
+    pub fn from_writer(writer: Box<dyn Write>, format: VideoFormat, display: &Display, scale: usize) -> io::Result<Self> {
+        let scale = scale.max(1);
+        let (width, height) = display.resolution();
+        let (width, height) = (width * scale, height * scale);
+        let mut writer = BufWriter::new(writer);
+
+        let encoder = match format {
+            VideoFormat::Gif => {
+                let palette = display.palette;
+                let colors = [palette.background, palette.foreground]
+                    .iter()
+                    .flat_map(|color| [(color >> 16) as u8, (color >> 8) as u8, *color as u8])
+                    .collect::<Vec<u8>>();
+                let mut encoder = gif::Encoder::new(writer, width as u16, height as u16, &colors).map_err(io::Error::other)?;
+                encoder.set_repeat(gif::Repeat::Infinite).map_err(io::Error::other)?;
+                Encoder::Gif(encoder)
+            }
+            VideoFormat::Y4m => {
+                writeln!(writer, "YUV4MPEG2 W{} H{} F{}:1 Ip A1:1 C444", width, height, FPS)?;
+                Encoder::Y4m(writer)
+            }
+        };
+
+        Ok(VideoRecorder { encoder, format, scale, width, height, frames: 0 })
+    }
+
+    pub fn format(&self) -> VideoFormat {
+        self.format
+    }
+
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    pub fn write_frame(&mut self, display: &Display) -> io::Result<()> {
+        let (pixels, width, height) = display.pixels();
+        let (pixels, width, height) = filters::nearest(&pixels, width, height, self.scale);
+        if (width, height) != (self.width, self.height) {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "display resolution changed during recording"));
+        }
+
+        match &mut self.encoder {
+            Encoder::Gif(encoder) => {
+                let foreground = display.palette.foreground;
+                let indices = pixels.iter().map(|&pixel| (pixel == foreground) as u8).collect::<Vec<u8>>();
+                let mut frame = gif::Frame::from_indexed_pixels(width as u16, height as u16, indices, None);
+                frame.delay = GIF_DELAYS[(self.frames % GIF_DELAYS.len() as u64) as usize];
+                encoder.write_frame(&frame).map_err(io::Error::other)?;
+            }
+            Encoder::Y4m(writer) => {
+                writer.write_all(b"FRAME\n")?;
+                let mut planes = vec![0u8; pixels.len() * 3];
+                let (y_plane, chroma) = planes.split_at_mut(pixels.len());
+                let (u_plane, v_plane) = chroma.split_at_mut(pixels.len());
+                for (index, &pixel) in pixels.iter().enumerate() {
+                    let (y, u, v) = rgb_to_yuv(pixel);
+                    y_plane[index] = y;
+                    u_plane[index] = u;
+                    v_plane[index] = v;
+                }
+                writer.write_all(&planes)?;
+            }
+        }
+
+        self.frames += 1;
+        Ok(())
+    }
+
+    pub fn finish(self) -> io::Result<()> {
+        match self.encoder {
+            Encoder::Gif(encoder) => encoder.into_inner().map_err(io::Error::other)?.flush(),
+            Encoder::Y4m(mut writer) => writer.flush(),
+        }
+    }
+}
+
+//BT.601 limited range, which is what encoders assume for Y4M without a colorspace tag
+fn rgb_to_yuv(pixel: u32) -> (u8, u8, u8) {
+    let r = ((pixel >> 16) & 0xFF) as i32;
+    let g = ((pixel >> 8) & 0xFF) as i32;
+    let b = (pixel & 0xFF) as i32;
+    let y = ((66 * r + 129 * g + 25 * b + 128) >> 8) + 16;
+    let u = ((-38 * r - 74 * g + 112 * b + 128) >> 8) + 128;
+    let v = ((112 * r - 94 * g - 18 * b + 128) >> 8) + 128;
+    (y as u8, u as u8, v as u8)
+}
@@ -0,0 +1,104 @@
+// The assembler and disassembler: every 16-bit word goes through a listing and back unchanged, and bad
+// source is reported with its line.
+
+use rust_chip8_emulator::asm::{self, AsmError};
+use rust_chip8_emulator::disasm;
+
+fn error(source: &str) -> AsmError {
+    asm::assemble(source).unwrap_err()
+}
+
+#[test]
+fn every_word_round_trips_through_a_listing() {
+    let rom: Vec<u8> = (0..=0xFFFFu16).flat_map(u16::to_be_bytes).chain([0xAB]).collect();
+    let listing = disasm::listing(&rom, 0x200);
+    assert_eq!(asm::assemble(&listing).unwrap(), rom);
+}
+
+#[test]
+fn every_opcode_form_disassembles_to_its_mnemonic() {
+    let forms = [
+        (0x00E0, "CLS"),
+        (0x00EE, "RET"),
+        (0x0123, "SYS 0x123"),
+        (0x1ABC, "JP 0xABC"),
+        (0x2ABC, "CALL 0xABC"),
+        (0x31FF, "SE V1, 0xFF"),
+        (0x4210, "SNE V2, 0x10"),
+        (0x5340, "SE V3, V4"),
+        (0x6A05, "LD VA, 0x05"),
+        (0x7B01, "ADD VB, 0x01"),
+        (0x8120, "LD V1, V2"),
+        (0x8121, "OR V1, V2"),
+        (0x8122, "AND V1, V2"),
+        (0x8123, "XOR V1, V2"),
+        (0x8124, "ADD V1, V2"),
+        (0x8125, "SUB V1, V2"),
+        (0x8126, "SHR V1, V2"),
+        (0x8127, "SUBN V1, V2"),
+        (0x812E, "SHL V1, V2"),
+        (0x9340, "SNE V3, V4"),
+        (0xA123, "LD I, 0x123"),
+        (0xB123, "JP V0, 0x123"),
+        (0xC10F, "RND V1, 0x0F"),
+        (0xD12F, "DRW V1, V2, 15"),
+        (0xE59E, "SKP V5"),
+        (0xE5A1, "SKNP V5"),
+        (0xF507, "LD V5, DT"),
+        (0xF50A, "LD V5, K"),
+        (0xF515, "LD DT, V5"),
+        (0xF518, "LD ST, V5"),
+        (0xF51E, "ADD I, V5"),
+        (0xF529, "LD F, V5"),
+        (0xF533, "LD B, V5"),
+        (0xF555, "LD [I], V5"),
+        (0xF565, "LD V5, [I]"),
+        (0xF575, "LD R, V5"),
+        (0xF585, "LD V5, R"),
+        (0x5341, "DW 0x5341"),
+        (0x8128, "DW 0x8128"),
+        (0xE500, "DW 0xE500"),
+        (0xF500, "DW 0xF500"),
+    ];
+    for (opcode, text) in forms {
+        assert_eq!(disasm::disassemble(opcode), text);
+        assert_eq!(asm::assemble(text).unwrap(), opcode.to_be_bytes(), "{}", text);
+    }
+}
+
+#[test]
+fn labels_numbers_and_data() {
+    let source = "
+        start:  ld v0, #0A      ; hex with #
+                ld I, sprite
+                shr v3          ; one register shifts itself
+                jp start
+        sprite: db 0b11110000, 144, 0xF0
+                dw 0x1234
+    ";
+    assert_eq!(
+        asm::assemble(source).unwrap(),
+        [0x60, 0x0A, 0xA2, 0x08, 0x83, 0x36, 0x12, 0x00, 0xF0, 0x90, 0xF0, 0x12, 0x34]
+    );
+}
+
+#[test]
+fn bad_operands_are_errors_with_the_line() {
+    let cases = [
+        ("LD VG, 1", "expected a register V0-VF, got \"VG\""),
+        ("ADD V1, 0x100", "256 is too big, the most this operand takes is 0xFF"),
+        ("JP 0x1000", "4096 is too big, the most this operand takes is 0xFFF"),
+        ("DRW V0, V1, 16", "16 is too big, the most this operand takes is 0xF"),
+        ("LD V1, 12Q", "invalid number \"12Q\""),
+        ("CALL nowhere", "unknown label \"nowhere\""),
+        ("JMP 0x200", "unknown instruction \"JMP 0x200\""),
+        ("CLS V0", "unknown instruction \"CLS V0\""),
+        ("SKP", "unknown instruction \"SKP\""),
+        ("DB 256", "256 is too big, the most this operand takes is 0xFF"),
+    ];
+    for (source, message) in cases {
+        assert_eq!(error(&format!("CLS\n{}", source)), AsmError { line: 2, message: String::from(message) }, "{}", source);
+    }
+    assert_eq!(error("a: CLS\n\na: RET").line, 3);
+    assert_eq!(error("a: CLS\n\na: RET").message, "label \"a\" is defined twice");
+}
@@ -0,0 +1,160 @@
+// config.toml: what load accepts and rejects, how the layers of settings_for stack up, and how settings
+// are applied to a machine.
+
+use rust_chip8_emulator::chip8::Chip8;
+use rust_chip8_emulator::config::{ConfigError, ConfigFile, Settings};
+use rust_chip8_emulator::display::Palette;
+use rust_chip8_emulator::metadata::{self, RomInfo};
+use rust_chip8_emulator::quirks::Quirks;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+const BRIX: &str = "f13766c14aeb02ad8d4d103cb5eadd282d20cddc";
+
+fn config_path(name: &str, contents: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("chip8-config-{}-{}", std::process::id(), name));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("config.toml");
+    fs::write(&path, contents).unwrap();
+    path
+}
+
+fn load(name: &str, contents: &str) -> Result<ConfigFile, ConfigError> {
+    let path = config_path(name, contents);
+    let config = ConfigFile::load(&path);
+    let _ = fs::remove_dir_all(path.parent().unwrap());
+    config
+}
+
+fn invalid_section(error: ConfigError) -> String {
+    match error {
+        ConfigError::Invalid { section, .. } => section,
+        e => panic!("expected an invalid section, got {}", e),
+    }
+}
+
+fn brix_info() -> RomInfo {
+    metadata::identify(&fs::read("roms/brix.ch8").unwrap()).unwrap()
+}
+
+#[test]
+fn a_missing_file_is_empty() {
+    let path = env::temp_dir().join(format!("chip8-config-{}-missing", std::process::id())).join("config.toml");
+    assert_eq!(ConfigFile::load(&path).unwrap(), ConfigFile::default());
+}
+
+#[test]
+fn files_load_and_save() {
+    let text = format!(
+        "database = \"/opt/chip-8-database/database\"\n\n[defaults]\nips = 900\npalette = \"000000,33ff66\"\n\n[roms.{}]\nplatform = \"chip8\"\nquirks = \"vblank=0\"\n",
+        BRIX
+    );
+    let config = load("valid", &text).unwrap();
+    assert_eq!(config.database, Some(PathBuf::from("/opt/chip-8-database/database")));
+    assert_eq!(config.defaults.ips, Some(900));
+    assert_eq!(config.roms[BRIX].platform.as_deref(), Some("chip8"));
+
+    let path = config_path("save", "");
+    config.save(&path).unwrap();
+    assert_eq!(ConfigFile::load(&path).unwrap(), config);
+    let _ = fs::remove_dir_all(path.parent().unwrap());
+}
+
+#[test]
+fn unknown_fields_are_parse_errors() {
+    assert!(matches!(load("unknown-setting", "[defaults]\nspeed = 10\n"), Err(ConfigError::Parse(_))));
+    assert!(matches!(load("unknown-table", "[default]\nips = 600\n"), Err(ConfigError::Parse(_))));
+    let text = format!("[roms.{}]\nplatfrom = \"chip8\"\n", BRIX);
+    assert!(matches!(load("unknown-rom-setting", &text), Err(ConfigError::Parse(_))));
+}
+
+#[test]
+fn rom_sections_are_named_by_lowercase_sha1() {
+    let upper = BRIX.to_uppercase();
+    for name in ["brix", &BRIX[..39], upper.as_str()] {
+        let text = format!("[roms.{}]\nips = 600\n", name);
+        assert_eq!(invalid_section(load("sha1", &text).unwrap_err()), format!("roms.{}", name));
+    }
+}
+
+#[test]
+fn invalid_settings_name_their_section() {
+    assert_eq!(invalid_section(load("defaults", "[defaults]\nplatform = \"gameboy\"\n").unwrap_err()), "defaults");
+    for (setting, name) in [("ips = 0", "ips"), ("scale = 65", "scale"), ("frame_rate = 0.5", "frame_rate"), ("quirks = \"warp=1\"", "warp"), ("palette = \"000000\"", "palette"), ("filter = \"blur\"", "filter")] {
+        let text = format!("[roms.{}]\n{}\n", BRIX, setting);
+        match load("rom", &text).unwrap_err() {
+            ConfigError::Invalid { section, message } => {
+                assert_eq!(section, format!("roms.{}", BRIX));
+                assert!(message.contains(name), "{}: {}", setting, message);
+            }
+            e => panic!("{}: {}", setting, e),
+        }
+    }
+}
+
+#[test]
+fn layers_stack_from_defaults_to_the_rom_section() {
+    let config = ConfigFile {
+        database: None,
+        defaults: Settings { ips: Some(600), seed: Some(1), scale: Some(8), platform: Some(String::from("schip")), ..Settings::default() },
+        roms: [(String::from(BRIX), Settings { seed: Some(3), quirks: Some(String::from("wrap=1")), ..Settings::default() })].into(),
+    };
+    //what the file asks for wins over the defaults
+    let file = Settings { platform: Some(String::from("xochip")), scale: Some(4), ..Settings::default() };
+    let settings = config.settings_for("0000000000000000000000000000000000000000", None, &file);
+    assert_eq!(settings, Settings { platform: Some(String::from("xochip")), ips: Some(600), seed: Some(1), scale: Some(4), ..Settings::default() });
+
+    //the ROM's own section wins over both
+    let settings = config.settings_for(BRIX, None, &file);
+    assert_eq!(settings.seed, Some(3));
+    assert_eq!(settings.quirks.as_deref(), Some("wrap=1"));
+    assert_eq!(settings.platform.as_deref(), Some("xochip"));
+}
+
+#[test]
+fn the_database_replaces_platform_quirks_and_ips() {
+    let mut info = brix_info();
+    let defaults = Settings {
+        platform: Some(String::from("schip")),
+        quirks: Some(String::from("shift=1")),
+        ips: Some(1200),
+        palette: Some(String::from("000000,ffffff")),
+        seed: Some(7),
+        ..Settings::default()
+    };
+    let mut config = ConfigFile { defaults, ..ConfigFile::default() };
+
+    //only what decides how the ROM runs goes, and the palette only when the database has colors
+    info.palette = None;
+    let settings = config.settings_for(BRIX, Some(&info), &Settings::default());
+    assert_eq!((settings.platform, settings.quirks, settings.ips), (None, None, None));
+    assert_eq!((settings.palette.as_deref(), settings.seed), (Some("000000,ffffff"), Some(7)));
+    info.palette = Some(Palette::default());
+    assert_eq!(config.settings_for(BRIX, Some(&info), &Settings::default()).palette, None);
+
+    //the file's own settings go as well, but the ROM's section still wins over the database
+    let file = Settings { platform: Some(String::from("xochip")), ..Settings::default() };
+    assert_eq!(config.settings_for(BRIX, Some(&info), &file).platform, None);
+    config.roms.insert(String::from(BRIX), Settings { ips: Some(300), ..Settings::default() });
+    assert_eq!(config.settings_for(BRIX, Some(&info), &file).ips, Some(300));
+}
+
+#[test]
+fn applying_sets_the_machine_up() {
+    let mut chip8 = Chip8::headless();
+    let settings = Settings {
+        platform: Some(String::from("chip8")),
+        quirks: Some(String::from("vblank=0")),
+        ips: Some(900),
+        frame_rate: Some(30.0),
+        seed: Some(5),
+        ..Settings::default()
+    };
+    settings.apply(&mut chip8).unwrap();
+    assert_eq!(chip8.quirks(), Quirks { vblank: false, ..Quirks::chip8() });
+    assert_eq!((chip8.speed(), chip8.frame_rate(), chip8.seed()), (30, 30.0, 5));
+
+    let bad = Settings { ips: Some(0), ..Settings::default() };
+    assert!(bad.apply(&mut chip8).is_err());
+}
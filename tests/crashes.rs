@@ -0,0 +1,158 @@
+// Programs that used to panic the interpreter, most of them found by the fuzz targets in fuzz/. Each one
+// has to run without panicking and leave the machine in the state noted.
+
+use rust_chip8_emulator::chip8::Chip8;
+use rust_chip8_emulator::quirks::Quirks;
+use rust_chip8_emulator::stack::StackError;
+
+fn machine(program: &[u16]) -> Chip8 {
+    let mut chip8 = Chip8::headless();
+    let bytes: Vec<u8> = program.iter().flat_map(|opcode| opcode.to_be_bytes()).collect();
+    chip8.write_memory(0x200, &bytes);
+    chip8
+}
+
+#[test]
+fn bcd_at_the_end_of_memory_wraps() {
+    let mut chip8 = machine(&[0xAFFE, 0x60FF, 0xF033]);
+    for _ in 0..3 {
+        chip8.step();
+    }
+    assert_eq!(&chip8.memory()[0xFFE..], &[2, 5]);
+    assert_eq!(chip8.memory()[0x000], 5);
+}
+
+#[test]
+fn register_store_and_load_past_the_end_of_memory_wrap() {
+    let mut chip8 = machine(&[0xAFFE, 0x6011, 0x6122, 0x6233, 0xF255]);
+    chip8.set_quirks(Quirks::chip8());
+    for _ in 0..5 {
+        chip8.step();
+    }
+    assert_eq!(&chip8.memory()[0xFFE..], &[0x11, 0x22]);
+    assert_eq!(chip8.memory()[0x000], 0x33);
+    assert_eq!(chip8.i_register(), 0x1001);
+
+    //FX1E can take I past the end of memory and past 0xFFFF
+    let mut chip8 = machine(&[0x60FF, 0xF01E, 0xF01E, 0xFF65]);
+    chip8.set_i_register(0xFFFF);
+    for _ in 0..4 {
+        chip8.step();
+    }
+    assert_eq!(chip8.i_register(), 0x01FD);
+}
+
+#[test]
+fn register_load_at_the_top_of_the_index_range() {
+    let mut chip8 = machine(&[0xFF65]);
+    chip8.set_i_register(0xFFF8);
+    chip8.step();
+    assert_eq!(chip8.registers()[0], chip8.memory()[0xFF8]);
+}
+
+#[test]
+fn sprite_at_the_end_of_memory_wraps() {
+    let mut chip8 = machine(&[0xD002]);
+    chip8.write_memory(0xFFF, &[0x80]);
+    chip8.write_memory(0x000, &[0x80]);
+    chip8.set_i_register(0xFFFF);
+    chip8.step();
+    assert!(chip8.display().frame_buffer[0][0]);
+    assert!(chip8.display().frame_buffer[1][0]);
+}
+
+#[test]
+fn fetch_at_the_end_of_memory_wraps() {
+    let mut chip8 = Chip8::headless();
+    chip8.write_memory(0xFFE, &[0x60, 0x42]);
+    chip8.set_pc(0xFFE);
+    chip8.step();
+    assert_eq!(chip8.registers()[0], 0x42);
+    assert_eq!(chip8.pc(), 0x000);
+
+    //an odd pc reads its second byte from address 0
+    let mut chip8 = Chip8::headless();
+    chip8.write_memory(0xFFF, &[0x61]);
+    chip8.write_memory(0x000, &[0x07]);
+    chip8.set_pc(0xFFF);
+    chip8.step();
+    assert_eq!(chip8.registers()[1], 0x07);
+    assert_eq!(chip8.pc(), 0x001);
+}
+
+#[test]
+fn skip_at_the_end_of_memory_wraps() {
+    let mut chip8 = Chip8::headless();
+    chip8.write_memory(0xFFD, &[0x30, 0x00]);
+    chip8.set_pc(0xFFD);
+    chip8.step();
+    assert_eq!(chip8.pc(), 0x001);
+}
+
+#[test]
+fn jump_with_offset_past_the_end_of_memory_wraps() {
+    let mut chip8 = machine(&[0x60FF, 0xBFFF]);
+    chip8.step();
+    chip8.step();
+    assert_eq!(chip8.pc(), 0x0FE);
+    chip8.step();
+}
+
+#[test]
+fn wait_for_key_at_address_zero() {
+    let mut chip8 = Chip8::headless();
+    chip8.write_memory(0x000, &[0xF0, 0x0A]);
+    chip8.set_pc(0x000);
+    chip8.step();
+    assert_eq!(chip8.pc(), 0x000);
+}
+
+#[test]
+fn stack_overflow_stops_the_machine() {
+    //a subroutine that calls itself
+    let mut chip8 = machine(&[0x2200]);
+    for _ in 0..100 {
+        chip8.step();
+    }
+    assert_eq!(chip8.fault(), Some(StackError::Overflow));
+    assert_eq!(chip8.stack().len(), 16);
+    assert_eq!(chip8.pc(), 0x200);
+    assert!(chip8.is_halted());
+
+    chip8.reset();
+    assert_eq!(chip8.fault(), None);
+}
+
+#[test]
+fn stack_underflow_stops_the_machine() {
+    let mut chip8 = machine(&[0x6001, 0x00EE, 0x6002]);
+    for _ in 0..10 {
+        chip8.step_frame();
+    }
+    assert_eq!(chip8.fault(), Some(StackError::Underflow));
+    assert_eq!(chip8.pc(), 0x202);
+    assert_eq!(chip8.registers()[0], 1);
+    assert!(chip8.is_halted());
+}
+
+#[test]
+fn fault_is_part_of_a_save_state() {
+    let mut chip8 = machine(&[0x00EE]);
+    let state = chip8.save_state();
+    chip8.step();
+    let faulted = chip8.save_state();
+    chip8.load_state(&state);
+    assert_eq!(chip8.fault(), None);
+    chip8.load_state(&faulted);
+    assert_eq!(chip8.fault(), Some(StackError::Underflow));
+}
+
+#[test]
+fn rom_filling_all_of_memory() {
+    let mut chip8 = Chip8::headless();
+    let rom = [0xFF; 4096 - 0x200];
+    chip8.load_rom_bytes(&rom, "full").unwrap();
+    for _ in 0..60 {
+        chip8.step_frame();
+    }
+}
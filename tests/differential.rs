@@ -0,0 +1,184 @@
+// Differential tests: random programs and machine states run on the interpreter and on the reference
+// model in tests/reference, one instruction at a time under every quirk preset, and the two have to
+// agree on registers, memory, the stack and the screen after every step. PROPTEST_CASES raises the
+// number of programs tried.
+
+mod reference;
+
+use proptest::collection::vec;
+use proptest::prelude::*;
+use reference::{Reference, MEMORY_SIZE};
+use rust_chip8_emulator::chip8::Chip8;
+use rust_chip8_emulator::quirks::Quirks;
+use std::fmt;
+
+const STEPS: usize = 200;
+const ROM_STEPS: usize = 20_000;
+
+// every documented opcode as the fixed bits and the mask of its operand bits
+const OPCODES: [(u16, u16); 36] = [
+    (0x00E0, 0x000), (0x00EE, 0x000), (0x1000, 0xFFF), (0x2000, 0xFFF), (0x3000, 0xFFF), (0x4000, 0xFFF),
+    (0x5000, 0xFF0), (0x6000, 0xFFF), (0x7000, 0xFFF), (0x8000, 0xFF0), (0x8001, 0xFF0), (0x8002, 0xFF0),
+    (0x8003, 0xFF0), (0x8004, 0xFF0), (0x8005, 0xFF0), (0x8006, 0xFF0), (0x8007, 0xFF0), (0x800E, 0xFF0),
+    (0x9000, 0xFF0), (0xA000, 0xFFF), (0xB000, 0xFFF), (0xC000, 0xFFF), (0xD000, 0xFFF), (0xE09E, 0xF00),
+    (0xE0A1, 0xF00), (0xF007, 0xF00), (0xF00A, 0xF00), (0xF015, 0xF00), (0xF018, 0xF00), (0xF01E, 0xF00),
+    (0xF029, 0xF00), (0xF033, 0xF00), (0xF055, 0xF00), (0xF065, 0xF00), (0xF075, 0xF00), (0xF085, 0xF00),
+];
+
+fn opcode() -> impl Strategy<Value = u16> {
+    prop_oneof![
+        (0..OPCODES.len(), any::<u16>()).prop_map(|(index, operand)| OPCODES[index].0 | operand & OPCODES[index].1),
+        //jumps and calls back into the program, so it runs for longer than one pass
+        (0..3u16, 0..64u16).prop_map(|(kind, offset)| [0x1200, 0x2200, 0xB200][kind as usize] + offset * 2),
+    ]
+}
+
+#[derive(Clone)]
+struct Setup {
+    memory: Vec<u8>,
+    program: Vec<u16>,
+    registers: [u8; 16],
+    i_register: u16,
+    keys: [bool; 16],
+    seed: u64,
+}
+
+// failing cases print memory as the 16-byte lines that aren't all zero, which is what shrinking leaves
+impl fmt::Debug for Setup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "program: {:04X?}", self.program)?;
+        writeln!(f, "registers: {:02X?}, I: {:04X}, keys: {:?}, seed: {}", self.registers, self.i_register, self.keys, self.seed)?;
+        for (line, bytes) in self.memory.chunks(16).enumerate().filter(|(_, bytes)| bytes.iter().any(|&byte| byte != 0)) {
+            writeln!(f, "{:03X}: {:02X?}", line * 16, bytes)?;
+        }
+        Ok(())
+    }
+}
+
+fn setup() -> impl Strategy<Value = Setup> {
+    (vec(any::<u8>(), MEMORY_SIZE), vec(opcode(), 1..64), any::<[u8; 16]>(), any::<u16>(), any::<[bool; 16]>(), any::<u64>())
+        .prop_map(|(memory, program, registers, i_register, keys, seed)| Setup { memory, program, registers, i_register, keys, seed })
+}
+
+fn machines(setup: &Setup, quirks: Quirks) -> (Chip8, Reference) {
+    let mut memory = [0; MEMORY_SIZE];
+    memory.copy_from_slice(&setup.memory);
+    for (index, opcode) in setup.program.iter().enumerate() {
+        memory[0x200 + index * 2..0x202 + index * 2].copy_from_slice(&opcode.to_be_bytes());
+    }
+
+    let mut chip8 = Chip8::headless();
+    chip8.set_quirks(quirks);
+    chip8.set_seed(setup.seed);
+    chip8.write_memory(0, &memory);
+    chip8.set_pc(0x200);
+    chip8.set_i_register(setup.i_register);
+    for (x, &value) in setup.registers.iter().enumerate() {
+        chip8.set_register(x, value);
+    }
+    for (key, &pressed) in setup.keys.iter().enumerate() {
+        if pressed {
+            chip8.press_key(key as u8);
+        } else {
+            chip8.release_key(key as u8);
+        }
+    }
+    chip8.apply_keys();
+
+    let mut reference = Reference::new(memory, quirks, setup.seed);
+    reference.v = setup.registers;
+    reference.i = setup.i_register;
+    reference.keys = setup.keys;
+    (chip8, reference)
+}
+
+// every way the interpreter's state differs from the reference, empty when they agree
+fn differences(chip8: &Chip8, reference: &Reference) -> Vec<String> {
+    let mut differences = Vec::new();
+    for (x, (&value, &expected)) in chip8.registers().iter().zip(&reference.v).enumerate() {
+        if value != expected {
+            differences.push(format!("V{:X} is {:02X}, expected {:02X}", x, value, expected));
+        }
+    }
+    let values = [
+        ("I", chip8.i_register(), reference.i),
+        ("pc", chip8.pc(), reference.pc),
+        ("the delay timer", chip8.delay_timer() as u16, reference.delay_timer as u16),
+        ("the sound timer", chip8.sound_timer() as u16, reference.sound_timer as u16),
+    ];
+    for (name, value, expected) in values {
+        if value != expected {
+            differences.push(format!("{} is {:03X}, expected {:03X}", name, value, expected));
+        }
+    }
+    if chip8.stack() != reference.stack.as_slice() {
+        differences.push(format!("the stack is {:03X?}, expected {:03X?}", chip8.stack(), reference.stack));
+    }
+    if chip8.flags() != reference.flags {
+        differences.push(format!("the flags are {:02X?}, expected {:02X?}", chip8.flags(), reference.flags));
+    }
+    let fault = chip8.fault().map(|fault| fault.to_string());
+    if fault.as_deref() != reference.fault {
+        differences.push(format!("the fault is {:?}, expected {:?}", fault, reference.fault));
+    }
+    if chip8.memory() != reference.memory.as_slice() {
+        let memory: Vec<String> = (0..MEMORY_SIZE)
+            .filter(|&address| chip8.memory()[address] != reference.memory[address])
+            .map(|address| format!("{:03X}: {:02X}, expected {:02X}", address, chip8.memory()[address], reference.memory[address]))
+            .collect();
+        differences.push(format!("memory differs at {}", memory.join(", ")));
+    }
+    if chip8.display().frame_buffer != reference.screen {
+        let pixels: Vec<String> = (0..reference::HEIGHT * reference::WIDTH)
+            .map(|pixel| (pixel % reference::WIDTH, pixel / reference::WIDTH))
+            .filter(|&(x, y)| chip8.display().frame_buffer[y][x] != reference.screen[y][x])
+            .map(|(x, y)| format!("({}, {})", x, y))
+            .collect();
+        differences.push(format!("{} pixels differ: {}", pixels.len(), pixels.join(" ")));
+    }
+    differences
+}
+
+// runs both machines and returns where they first diverge
+fn divergence(setup: &Setup, preset: &str, steps: usize) -> Option<String> {
+    let (mut chip8, mut reference) = machines(setup, Quirks::preset(preset).unwrap());
+    for step in 1..=steps {
+        let (pc, opcode) = (reference.pc, reference.opcode());
+        chip8.step();
+        reference.step();
+        let differences = differences(&chip8, &reference);
+        if !differences.is_empty() {
+            return Some(format!("{} diverged at step {} ({:04X} at {:03X}):\n  {}", preset, step, opcode, pc, differences.join("\n  ")));
+        }
+    }
+    None
+}
+
+proptest! {
+    #[test]
+    fn interpreter_matches_the_reference(setup in setup()) {
+        for preset in Quirks::PRESETS {
+            if let Some(report) = divergence(&setup, preset, STEPS) {
+                return Err(TestCaseError::fail(report));
+            }
+        }
+    }
+}
+
+#[test]
+fn reference_runs_the_bundled_roms_like_the_interpreter() {
+    let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("roms");
+    for entry in std::fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        let rom = std::fs::read(&path).unwrap();
+        let mut memory = vec![0; MEMORY_SIZE];
+        memory[0x200..0x200 + rom.len()].copy_from_slice(&rom);
+        memory[0x050..0x0A0].copy_from_slice(&Chip8::headless().memory()[0x050..0x0A0]);
+        let setup = Setup { memory, program: Vec::new(), registers: [0; 16], i_register: 0, keys: [false; 16], seed: 0 };
+        for preset in Quirks::PRESETS {
+            if let Some(report) = divergence(&setup, preset, ROM_STEPS) {
+                panic!("{}: {}", path.display(), report);
+            }
+        }
+    }
+}
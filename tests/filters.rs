@@ -0,0 +1,67 @@
+// The scaling filters: the size each one scales to, and the pixel-art scalers worked through by hand on
+// small patterns, drawn as text with X for lit pixels.
+
+use rust_chip8_emulator::filters::{self, Filter};
+
+const LIT: u32 = 0xFFFFFFFF;
+const UNLIT: u32 = 0xFF000000;
+
+fn image(rows: &[&str]) -> (Vec<u32>, usize, usize) {
+    let pixels = rows.iter().flat_map(|row| row.chars().map(|c| if c == 'X' { LIT } else { UNLIT })).collect();
+    (pixels, rows[0].len(), rows.len())
+}
+
+fn text((pixels, width, _): (Vec<u32>, usize, usize)) -> Vec<String> {
+    pixels.chunks(width).map(|row| row.iter().map(|&pixel| if pixel == LIT { 'X' } else { '.' }).collect()).collect()
+}
+
+#[test]
+fn filters_scale_by_their_factor() {
+    let (pixels, width, height) = image(&["X.X.", ".X.X", "XX.."]);
+    for filter in Filter::ALL {
+        let factor = match filter {
+            Filter::Nearest => 1,
+            Filter::Scale3x | Filter::CrtMask => 3,
+            _ => 2,
+        };
+        let (out, out_width, out_height) = filter.apply(&pixels, width, height);
+        assert_eq!((out_width, out_height), (width * factor, height * factor), "{}", filter.name());
+        assert_eq!(out.len(), out_width * out_height, "{}", filter.name());
+    }
+}
+
+#[test]
+fn scale2x_smooths_diagonals() {
+    let (pixels, width, height) = image(&["X.", ".X"]);
+    assert_eq!(text(filters::scale2x(&pixels, width, height)), ["XX..", "X.X.", ".X.X", "..XX"]);
+    //a straight edge stays as it is
+    let (pixels, width, height) = image(&["XX", ".."]);
+    assert_eq!(text(filters::scale2x(&pixels, width, height)), ["XXXX", "XXXX", "....", "...."]);
+}
+
+#[test]
+fn epx_matches_scale2x() {
+    let patterns: [&[&str]; 3] = [&["X.", ".X"], &["XX", "X."], &["X..X.", ".XX..", "..X.X", "X...X"]];
+    for rows in patterns {
+        let (pixels, width, height) = image(rows);
+        assert_eq!(filters::epx(&pixels, width, height), filters::scale2x(&pixels, width, height), "{:?}", rows);
+    }
+}
+
+#[test]
+fn eagle_fills_inner_corners() {
+    let (pixels, width, height) = image(&["XX", "X."]);
+    assert_eq!(text(filters::eagle(&pixels, width, height)), ["XXXX", "XXXX", "XXX.", "XX.."]);
+    //a lone diagonal has no corner three neighbors agree on
+    let (pixels, width, height) = image(&["X.", ".X"]);
+    assert_eq!(text(filters::eagle(&pixels, width, height)), ["XX..", "XX..", "..XX", "..XX"]);
+}
+
+#[test]
+fn names_round_trip() {
+    for filter in Filter::ALL {
+        assert_eq!(Filter::from_name(filter.name()), Some(filter));
+    }
+    assert_eq!(Filter::from_name("crt_mask"), Some(Filter::CrtMask));
+    assert_eq!(Filter::CrtMask.next(), Filter::Nearest);
+}
@@ -0,0 +1,58 @@
+// Golden-image tests for the bundled ROMs: each runs headless with a fixed seed and scripted keys, and
+// the final screen has to match tests/snapshots/<rom>.txt. Run with UPDATE_SNAPSHOTS=1 to rewrite the
+// snapshots after an intended change, then review the diff.
+
+use rust_chip8_emulator::chip8::Chip8;
+use rust_chip8_emulator::testing::{self, Comparison, TestRun};
+use std::env;
+use std::path::Path;
+
+fn check(rom: &str, frames: u64, keys: &str) {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let mut chip8 = Chip8::headless();
+    chip8.load_rom(dir.join("roms").join(format!("{}.ch8", rom))).unwrap();
+    chip8.set_seed(0);
+    chip8.reset();
+
+    let run = TestRun { frames, keys: testing::parse_keys(keys).unwrap(), until_halt: true };
+    let result = run.run(&mut chip8);
+    let path = dir.join("tests").join("snapshots").join(format!("{}.txt", rom));
+    let update = env::var_os("UPDATE_SNAPSHOTS").is_some();
+    match testing::compare(&result.snapshot, &path, update).unwrap() {
+        Comparison::Match | Comparison::Updated => {}
+        Comparison::Missing => panic!("no snapshot at {}, run with UPDATE_SNAPSHOTS=1 to write it", path.display()),
+        Comparison::Mismatch { pixels } => {
+            panic!("{} pixels differ from {} after {} frames, the screen was:\n{}", pixels, path.display(), result.frames, result.snapshot)
+        }
+    }
+}
+
+#[test]
+fn astro_dodge() {
+    check("astro_dodge", 600, "60:5+ 70:5- 200:4+ 260:4- 300:6+ 330:6-");
+}
+
+#[test]
+fn blitz() {
+    check("blitz", 600, "60:5+ 70:5-");
+}
+
+#[test]
+fn brix() {
+    check("brix", 600, "100:4+ 160:4- 200:6+ 240:6-");
+}
+
+#[test]
+fn nim() {
+    check("nim", 600, "60:1+ 70:1- 120:f+ 130:f-");
+}
+
+#[test]
+fn pong() {
+    check("pong", 600, "30:1+ 90:1- 150:4+ 200:4-");
+}
+
+#[test]
+fn space_invaders() {
+    check("space_invaders", 600, "60:5+ 70:5- 120:4+ 180:4- 200:5+ 210:5-");
+}
@@ -0,0 +1,176 @@
+// The gym environments: frame skip, rewards and episode ends read from memory, seeded resets, and the
+// vectorized wrapper stepping its instances exactly like Envs stepped one after another.
+
+use rust_chip8_emulator::gym::{Counter, Env, EnvConfig, Reward, VecEnv, OBSERVATION_SIZE};
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+
+fn pong_rom() -> Vec<u8> {
+    fs::read(Path::new(env!("CARGO_MANIFEST_DIR")).join("roms").join("pong.ch8")).unwrap()
+}
+
+// the left paddle, scored on the BCD digits pong draws its score from
+fn pong_config() -> EnvConfig {
+    EnvConfig {
+        actions: vec![vec![], vec![1], vec![4]],
+        rewards: vec![Reward { counter: Counter::Byte(0x2F3), weight: 1.0 }, Reward { counter: Counter::Byte(0x2F4), weight: -1.0 }],
+        ..EnvConfig::default()
+    }
+}
+
+// a fixed but varied action for each step and instance
+fn action(step: usize, instance: usize, actions: usize) -> usize {
+    (step / 7 + instance * 3) % actions
+}
+
+#[test]
+fn counters_read_memory() {
+    let mut memory = vec![0; 4096];
+    memory[0x300..0x303].copy_from_slice(&[1, 2, 3]);
+    memory[0xFFF] = 0x12;
+    memory[0x000] = 0x34;
+    assert_eq!(Counter::Byte(0x301).read(&memory), 2);
+    assert_eq!(Counter::Word(0x301).read(&memory), 0x0203);
+    assert_eq!(Counter::Bcd(0x300, 3).read(&memory), 123);
+    assert_eq!(Counter::Word(0xFFF).read(&memory), 0x1234);
+}
+
+#[test]
+fn steps_hold_the_action_for_frame_skip_frames() {
+    let mut env = Env::new(&pong_rom(), "pong", EnvConfig { frame_skip: 3, ..pong_config() }).unwrap();
+    let observation = env.reset();
+    assert_eq!(observation.len(), OBSERVATION_SIZE);
+    let step = env.step(1);
+    assert_eq!(env.chip8().frame(), 3);
+    assert!(env.chip8().keypad().is_pressed(1));
+    assert_eq!(step.observation, env.observation());
+    assert_eq!(step.observation.iter().filter(|&&pixel| pixel == 1).count(), env.chip8().display().frame_buffer.iter().flatten().filter(|&&lit| lit).count());
+    env.step(0);
+    assert!(!env.chip8().keypad().is_pressed(1));
+}
+
+#[test]
+fn rewards_follow_the_score() {
+    let mut env = Env::new(&pong_rom(), "pong", pong_config()).unwrap();
+    env.reset();
+    let (mut total, mut points) = (0.0, 0);
+    for step in 0..2000 {
+        let step = env.step(action(step, 0, 3));
+        total += step.reward;
+        points += (step.reward != 0.0) as usize;
+    }
+    let memory = env.chip8().memory();
+    assert!(points > 0, "nobody scored");
+    assert_eq!(total, memory[0x2F3] as f64 - memory[0x2F4] as f64);
+}
+
+#[test]
+fn episodes_end_on_counters_halts_and_max_frames() {
+    let config = EnvConfig { end: vec![(Counter::Bcd(0x2F3, 2), 1)], ..pong_config() };
+    let mut env = Env::new(&pong_rom(), "pong", config).unwrap();
+    env.reset();
+    let step = (0..2000).map(|_| env.step(0)).find(|step| step.done()).expect("the episode never ended");
+    assert!(step.terminated && !step.truncated);
+
+    let config = EnvConfig { max_frames: 10, ..pong_config() };
+    let mut env = Env::new(&pong_rom(), "pong", config).unwrap();
+    env.reset();
+    assert!(!env.step(0).done());
+    assert!(!env.step(0).done());
+    let step = env.step(0);
+    assert!(step.truncated && !step.terminated);
+    assert_eq!(env.chip8().frame(), 10);
+
+    //a program that jumps to itself is over as soon as it gets there
+    let mut env = Env::new(&[0x60, 0x01, 0x12, 0x02], "halt", EnvConfig::default()).unwrap();
+    env.reset();
+    assert!(env.step(0).terminated);
+}
+
+#[test]
+fn actions_default_to_the_database_keys() {
+    //pong is in the database with keys for both paddles
+    let env = Env::new(&pong_rom(), "pong", EnvConfig::default()).unwrap();
+    let info = env.chip8().rom_info().unwrap();
+    assert_eq!(env.actions().len(), info.keys.len() + 1);
+    assert!(env.actions()[0].is_empty());
+
+    let env = Env::new(&[0x12, 0x00], "unknown", EnvConfig::default()).unwrap();
+    assert_eq!(env.actions().len(), 17);
+}
+
+#[test]
+fn bad_configs_are_errors() {
+    assert!(Env::new(&pong_rom(), "pong", EnvConfig { frame_skip: 0, ..pong_config() }).is_err());
+    assert!(Env::new(&pong_rom(), "pong", EnvConfig { actions: vec![vec![16]], ..pong_config() }).is_err());
+    assert!(Env::new(&[], "empty", EnvConfig::default()).is_err());
+    assert!(VecEnv::new(&pong_rom(), "pong", pong_config(), 0, 1).is_err());
+}
+
+#[test]
+fn out_of_range_actions_panic_without_stopping_the_workers() {
+    let mut env = Env::new(&pong_rom(), "pong", pong_config()).unwrap();
+    let error = panic::catch_unwind(AssertUnwindSafe(|| env.step(3))).unwrap_err();
+    assert_eq!(error.downcast_ref::<String>().map(String::as_str), Some("action 3 is out of range, there are 3 actions"));
+
+    let mut vec_env = VecEnv::new(&pong_rom(), "pong", pong_config(), 2, 2).unwrap();
+    vec_env.reset();
+    let error = panic::catch_unwind(AssertUnwindSafe(|| vec_env.step(&[0, 7]))).unwrap_err();
+    assert_eq!(error.downcast_ref::<String>().map(String::as_str), Some("action 7 is out of range, there are 3 actions"));
+    assert_eq!(vec_env.step(&[1, 2]).len(), 2);
+}
+
+#[test]
+fn resets_reseed_each_episode_the_same_way_every_run() {
+    let episode = |env: &mut Env| {
+        let mut observations = vec![env.reset()];
+        observations.extend((0..300).map(|step| env.step(action(step, 0, 3)).observation));
+        observations
+    };
+    let mut env = Env::new(&pong_rom(), "pong", pong_config()).unwrap();
+    let (first, second) = (episode(&mut env), episode(&mut env));
+    assert!(first != second, "both episodes served the ball the same way");
+
+    let mut again = Env::new(&pong_rom(), "pong", pong_config()).unwrap();
+    assert!(episode(&mut again) == first, "the first episode played out differently");
+    assert!(episode(&mut again) == second, "the second episode played out differently");
+}
+
+#[test]
+fn vec_env_steps_like_separate_envs() {
+    const INSTANCES: usize = 5;
+    let config = EnvConfig { max_frames: 200, ..pong_config() };
+    let mut vec_env = VecEnv::new(&pong_rom(), "pong", config.clone(), INSTANCES, 2).unwrap();
+    let mut envs: Vec<Env> = (0..INSTANCES as u64)
+        .map(|instance| {
+            let mut config = config.clone();
+            config.settings.seed = Some(instance);
+            Env::new(&pong_rom(), "pong", config).unwrap()
+        })
+        .collect();
+
+    let expected: Vec<Vec<u8>> = envs.iter_mut().map(Env::reset).collect();
+    assert!(vec_env.reset() == expected, "the first observations differ");
+    let mut done = [false; INSTANCES];
+    for step in 0..400 {
+        let actions: Vec<usize> = (0..INSTANCES).map(|instance| action(step, instance, 3)).collect();
+        //finished episodes start over before the next step
+        let expected: Vec<_> = envs
+            .iter_mut()
+            .zip(&mut done)
+            .zip(&actions)
+            .map(|((env, done), &action)| {
+                if *done {
+                    env.reset();
+                }
+                let step = env.step(action);
+                *done = step.done();
+                step
+            })
+            .collect();
+        for (instance, (result, expected)) in vec_env.step(&actions).iter().zip(&expected).enumerate() {
+            assert!(result == expected, "instance {} differed on step {}", instance, step);
+        }
+    }
+}
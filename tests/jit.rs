@@ -0,0 +1,177 @@
+// The JIT has to be invisible: the same machine run with and without it, frame by frame, must end every
+// frame in the same state. Only built with the jit feature, e.g. cargo test --features jit.
+#![cfg(feature = "jit")]
+
+use proptest::collection::vec;
+use proptest::prelude::*;
+use rust_chip8_emulator::chip8::Chip8;
+use rust_chip8_emulator::flags::NUM_FLAGS;
+use rust_chip8_emulator::movie::{Movie, MovieEvent};
+use rust_chip8_emulator::quirks::Quirks;
+
+const FRAMES: usize = 50;
+const ROM_FRAMES: usize = 3000;
+
+fn machines(memory: &[u8], preset: &str, speed: usize) -> (Chip8, Chip8) {
+    let machine = |jit: bool| {
+        let mut chip8 = Chip8::headless();
+        chip8.set_quirks(Quirks::preset(preset).unwrap());
+        chip8.set_seed(0);
+        chip8.set_speed(speed);
+        chip8.write_memory(0, memory);
+        chip8.set_pc(0x200);
+        chip8.set_jit(jit).unwrap();
+        chip8
+    };
+    (machine(false), machine(true))
+}
+
+// every way the JIT machine's state differs from the interpreter's, empty when they agree
+fn differences(interpreter: &Chip8, jit: &Chip8) -> Vec<String> {
+    let values = [
+        ("the registers", format!("{:02X?}", interpreter.registers()), format!("{:02X?}", jit.registers())),
+        ("I", format!("{:03X}", interpreter.i_register()), format!("{:03X}", jit.i_register())),
+        ("pc", format!("{:03X}", interpreter.pc()), format!("{:03X}", jit.pc())),
+        ("the cycle", interpreter.cycle().to_string(), jit.cycle().to_string()),
+        ("the timers", format!("{} {}", interpreter.delay_timer(), interpreter.sound_timer()), format!("{} {}", jit.delay_timer(), jit.sound_timer())),
+        ("the stack", format!("{:03X?}", interpreter.stack()), format!("{:03X?}", jit.stack())),
+        ("the fault", format!("{:?}", interpreter.fault()), format!("{:?}", jit.fault())),
+        ("the keys", format!("{:?}", interpreter.keypad().states()), format!("{:?}", jit.keypad().states())),
+    ];
+    let mut differences: Vec<String> = values
+        .into_iter()
+        .filter(|(_, expected, value)| expected != value)
+        .map(|(name, expected, value)| format!("{} are {}, expected {}", name, value, expected))
+        .collect();
+    if interpreter.memory() != jit.memory() {
+        differences.push("memory differs".to_string());
+    }
+    if interpreter.display().frame_buffer != jit.display().frame_buffer {
+        differences.push("the screen differs".to_string());
+    }
+    differences
+}
+
+// runs both machines a frame at a time, pressing keys from the pattern, and returns where they first diverge
+fn divergence(interpreter: &mut Chip8, jit: &mut Chip8, frames: usize, keys: &[u8]) -> Option<String> {
+    for frame in 0..frames {
+        let key = keys[frame % keys.len()];
+        for chip8 in [&mut *interpreter, &mut *jit] {
+            if key < 16 {
+                chip8.press_key(key);
+            } else {
+                (0..16).for_each(|key| chip8.release_key(key));
+            }
+            chip8.step_frame();
+        }
+        let differences = differences(interpreter, jit);
+        if !differences.is_empty() {
+            return Some(format!("diverged on frame {}:\n  {}", frame, differences.join("\n  ")));
+        }
+    }
+    None
+}
+
+fn program() -> impl Strategy<Value = Vec<u16>> {
+    let opcode = prop_oneof![
+        //the instructions the JIT compiles, several times as likely as the rest so blocks get long
+        4 => 0x6000..=0x7FFFu16,
+        4 => (0..16u16, 0..16u16, prop::sample::select(vec![0, 1, 2, 3, 4, 5, 6, 7, 0xE]))
+            .prop_map(|(x, y, n)| 0x8000 | x << 8 | y << 4 | n),
+        2 => 0x3000..=0x4FFFu16,
+        1 => (0..16u16, 0..16u16, prop::sample::select(vec![0x5000, 0x9000])).prop_map(|(x, y, base)| base | x << 8 | y << 4),
+        1 => 0xA000..=0xAFFFu16,
+        1 => (0..16u16, prop::sample::select(vec![0x07, 0x15, 0x18, 0x1E, 0x29, 0x65])).prop_map(|(x, nn)| 0xF000 | x << 8 | nn),
+        //the ones it leaves to the interpreter, including memory writes into the program
+        1 => (0..16u16, prop::sample::select(vec![0x0A, 0x33, 0x55, 0x75, 0x85])).prop_map(|(x, nn)| 0xF000 | x << 8 | nn),
+        1 => prop::sample::select(vec![0x00E0, 0x00EE, 0xC0FF, 0xD125, 0xE09E, 0xE1A1]),
+        //jumps and calls back into the program
+        2 => (0..3usize, 0..48u16).prop_map(|(kind, offset)| [0x1200, 0x2200, 0xB200][kind] + offset * 2),
+    ];
+    vec(opcode, 1..48)
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn jit_matches_the_interpreter(
+        program in program(),
+        data in vec(any::<u8>(), 16),
+        speed in 1..40usize,
+        keys in vec(0..20u8, 1..8),
+    ) {
+        let mut memory = vec![0; 4096];
+        memory[0x050..0x0A0].copy_from_slice(&Chip8::headless().memory()[0x050..0x0A0]);
+        for (index, opcode) in program.iter().enumerate() {
+            memory[0x200 + index * 2..0x202 + index * 2].copy_from_slice(&opcode.to_be_bytes());
+        }
+        memory[0x300..0x310].copy_from_slice(&data);
+        for preset in Quirks::PRESETS {
+            let (mut interpreter, mut jit) = machines(&memory, preset, speed);
+            if let Some(report) = divergence(&mut interpreter, &mut jit, FRAMES, &keys) {
+                return Err(TestCaseError::fail(format!("{}: {}", preset, report)));
+            }
+        }
+    }
+}
+
+#[test]
+fn jit_runs_the_bundled_roms_like_the_interpreter() {
+    let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("roms");
+    for entry in std::fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        let rom = std::fs::read(&path).unwrap();
+        let mut memory = Chip8::headless().memory().to_vec();
+        memory[0x200..0x200 + rom.len()].copy_from_slice(&rom);
+        for preset in Quirks::PRESETS {
+            let (mut interpreter, mut jit) = machines(&memory, preset, 30);
+            //holds a few keys for a while each, so the games get past their title screens
+            let keys: Vec<u8> = [4, 6, 5, 16, 1, 12, 16].iter().flat_map(|&key| [key; 20]).collect();
+            if let Some(report) = divergence(&mut interpreter, &mut jit, ROM_FRAMES, &keys) {
+                panic!("{} with {}: {}", path.display(), preset, report);
+            }
+            assert_eq!(jit.jit_failures(), 0, "{} with {}", path.display(), preset);
+        }
+    }
+}
+
+#[test]
+fn rewritten_blocks_run_the_new_code() {
+    //each pass FX55 writes V0 over the NN of the 7XNN at 0x202, so V1 sums the pass counts
+    let program: [u16; 5] = [0x7001, 0x7100, 0xA203, 0xF055, 0x1200];
+    let bytes: Vec<u8> = program.iter().flat_map(|opcode| opcode.to_be_bytes()).collect();
+    let mut memory = vec![0; 4096];
+    memory[0x200..0x200 + bytes.len()].copy_from_slice(&bytes);
+    for preset in Quirks::PRESETS {
+        let (mut interpreter, mut jit) = machines(&memory, preset, 7);
+        if let Some(report) = divergence(&mut interpreter, &mut jit, 100, &[16]) {
+            panic!("{}: {}", preset, report);
+        }
+    }
+}
+
+#[test]
+fn blocks_stop_short_of_key_events_inside_a_frame() {
+    //a 22-cycle loop starting with a 20-instruction block, run 1010 instructions a frame, so frames 0 and
+    //11 end on the block's last instruction; a key event 5 cycles before then must not wait for the next frame
+    let mut program = vec![0x7001; 20];
+    program.extend([0xE39E, 0x1200, 0x1200]);
+    let rom: Vec<u8> = program.iter().flat_map(|opcode: &u16| opcode.to_be_bytes()).collect();
+    let machine = |jit: bool| {
+        let mut chip8 = Chip8::headless();
+        chip8.load_rom_bytes(&rom, "keys").unwrap();
+        chip8.set_jit(jit).unwrap();
+        let events = [0, 11].iter().map(|&frame| MovieEvent { frame, cycle: (frame + 1) * 1010 - 5, key: 0, pressed: frame == 0 }).collect();
+        let movie = Movie { rom_sha1: chip8.rom_sha1().to_string(), rom_name: "keys".to_string(), seed: 0, speed: 1010, quirks: Quirks::default(), flags: [0; NUM_FLAGS], frames: 20, events };
+        chip8.play_movie(&movie).unwrap();
+        chip8
+    };
+    let (mut interpreter, mut jit) = (machine(false), machine(true));
+    for frame in 0..20 {
+        interpreter.step_frame();
+        jit.step_frame();
+        let differences = differences(&interpreter, &jit);
+        assert!(differences.is_empty(), "diverged on frame {}:\n  {}", frame, differences.join("\n  "));
+    }
+}
@@ -0,0 +1,129 @@
+// keymap.toml: key names, several host keys per CHIP-8 key, hotkeys, per-ROM overrides by SHA-1 and
+// the errors for names that don't exist.
+
+use minifb::Key;
+use rust_chip8_emulator::keymap::{self, Hotkey, Keymap, KeymapError, KeymapFile};
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+const PONG: &str = "607c4f7f4e4dce9f99d96b3182bfe7e88bb090ee";
+const BRIX: &str = "f13766c14aeb02ad8d4d103cb5eadd282d20cddc";
+
+fn keymap_path(name: &str, contents: &str) -> PathBuf {
+    let path = env::temp_dir().join(format!("chip8-keymap-{}-{}.toml", std::process::id(), name));
+    fs::write(&path, contents).unwrap();
+    path
+}
+
+fn load(name: &str, contents: &str) -> Result<KeymapFile, KeymapError> {
+    let path = keymap_path(name, contents);
+    let file = KeymapFile::load(&path);
+    fs::remove_file(path).unwrap();
+    file
+}
+
+#[test]
+fn key_names_are_case_insensitive_and_digits_work_bare() {
+    assert_eq!(keymap::parse_key("up"), Some(Key::Up));
+    assert_eq!(keymap::parse_key("NUMPAD5"), Some(Key::NumPad5));
+    assert_eq!(keymap::parse_key("7"), Some(Key::Key7));
+    assert_eq!(keymap::parse_key("q"), Some(Key::Q));
+    assert_eq!(keymap::parse_key("Key7"), Some(Key::Key7));
+    assert_eq!(keymap::parse_key("Hyper"), None);
+    assert_eq!(keymap::key_name(Key::NumPad5), "NumPad5");
+}
+
+#[test]
+fn a_missing_file_keeps_the_defaults() {
+    let path = env::temp_dir().join(format!("chip8-keymap-{}-missing.toml", std::process::id()));
+    assert_eq!(KeymapFile::load(&path).unwrap().keymap_for(PONG).unwrap(), Keymap::default());
+}
+
+#[test]
+fn several_host_keys_press_one_chip8_key() {
+    let file = load("several", "[keypad]\n\"4\" = [\"Left\", \"H\"]\n\"0xc\" = [\"Up\"]\n").unwrap();
+    let keymap = file.keymap_for(PONG).unwrap();
+    assert_eq!(keymap.keypad[0x4], vec![Key::Left, Key::H]);
+    assert_eq!(keymap.keypad[0xC], vec![Key::Up]);
+    //keys the file leaves out keep their default binding
+    assert_eq!(keymap.keypad[0x5], Keymap::default().keypad[0x5]);
+
+    for pressed in [[Key::Left], [Key::H]] {
+        let states = keymap.keypad_states(&pressed);
+        assert!(states[0x4]);
+        assert_eq!(states.iter().filter(|&&down| down).count(), 1);
+    }
+    assert_eq!(keymap.keypad_index(Key::H), Some(0x4));
+    assert_eq!(keymap.keypad_index(Key::Q), None);
+}
+
+#[test]
+fn hotkeys_are_bound_by_name() {
+    let file = load("hotkeys", "[hotkeys]\npause = [\"P\", \"Pause\"]\nquit = []\n").unwrap();
+    let keymap = file.keymap_for(PONG).unwrap();
+    assert_eq!(keymap.hotkey_keys(Hotkey::Pause), [Key::P, Key::Pause]);
+    assert_eq!(keymap.hotkey_for(Key::Pause), Some(Hotkey::Pause));
+    assert!(keymap.hotkey_keys(Hotkey::Quit).is_empty());
+    assert_eq!(keymap.hotkey_keys(Hotkey::Reset), [Key::F5]);
+}
+
+#[test]
+fn rom_sections_override_the_global_tables_for_their_rom() {
+    let text = format!(
+        "[keypad]\n\"1\" = [\"W\"]\n\"4\" = [\"S\"]\n\n[roms.{}.keypad]\n\"1\" = [\"Up\"]\n\n[roms.{}.hotkeys]\npause = [\"Space\"]\n",
+        PONG, PONG
+    );
+    let file = load("roms", &text).unwrap();
+
+    let pong = file.keymap_for(PONG).unwrap();
+    assert_eq!((pong.keypad[0x1].as_slice(), pong.keypad[0x4].as_slice()), ([Key::Up].as_slice(), [Key::S].as_slice()));
+    assert_eq!(pong.hotkey_keys(Hotkey::Pause), [Key::Space]);
+
+    let brix = file.keymap_for(BRIX).unwrap();
+    assert_eq!(brix.keypad[0x1], vec![Key::W]);
+    assert_eq!(brix.hotkey_keys(Hotkey::Pause), [Key::P]);
+}
+
+#[test]
+fn rom_sections_are_named_by_lowercase_sha1() {
+    for name in ["pong", &PONG[..39], &PONG.to_uppercase()] {
+        let text = format!("[roms.{}.keypad]\n\"1\" = [\"Up\"]\n", name);
+        match load("sha1", &text) {
+            Err(KeymapError::InvalidRomSection(section)) => assert_eq!(section, name),
+            other => panic!("{}: {:?}", name, other),
+        }
+    }
+}
+
+#[test]
+fn unknown_names_are_errors() {
+    let error = load("unknown-key", "[keypad]\n\"1\" = [\"Hyper\"]\n").unwrap().keymap_for(PONG).unwrap_err();
+    assert!(matches!(error, KeymapError::UnknownKey(ref name) if name == "Hyper"));
+    assert_eq!(error.to_string(), "unknown host key \"Hyper\"");
+
+    let error = load("keypad-key", "[keypad]\n\"G\" = [\"A\"]\n").unwrap().keymap_for(PONG).unwrap_err();
+    assert!(matches!(error, KeymapError::InvalidKeypadKey(ref name) if name == "G"));
+    let error = load("hotkey", "[hotkeys]\nwarp = [\"W\"]\n").unwrap().keymap_for(PONG).unwrap_err();
+    assert!(matches!(error, KeymapError::UnknownHotkey(ref name) if name == "warp"));
+
+    //a bad binding in another ROM's section doesn't stop this one loading
+    let text = format!("[roms.{}.keypad]\n\"1\" = [\"Hyper\"]\n", BRIX);
+    let file = load("other-rom", &text).unwrap();
+    assert!(file.keymap_for(PONG).is_ok());
+    assert!(file.keymap_for(BRIX).is_err());
+
+    assert!(matches!(load("parse", "[keypad]\n\"1\" = \"A\"\n"), Err(KeymapError::Parse(_))));
+}
+
+#[test]
+fn a_saved_keypad_loads_back() {
+    let mut keymap = Keymap::default();
+    keymap.keypad[0x1] = vec![Key::Up, Key::K];
+    let mut file = KeymapFile::default();
+    file.set_keypad(&keymap);
+    let path = keymap_path("save", "");
+    file.save(&path).unwrap();
+    assert_eq!(KeymapFile::load(&path).unwrap().keymap_for(PONG).unwrap(), keymap);
+    fs::remove_file(path).unwrap();
+}
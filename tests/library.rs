@@ -0,0 +1,102 @@
+// The game library: scanning a directory tree with archives in it, searching the entries, and the recent
+// and favorite games kept in library.toml.
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use rust_chip8_emulator::library::{self, LibraryError, LibraryFile};
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use zip::write::SimpleFileOptions;
+use zip::ZipWriter;
+
+const PROGRAM: [u8; 2] = [0x12, 0x00];
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("chip8-library-{}-{}", std::process::id(), name));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+// astro_dodge.ch8 copied under another name, so its title can only come from the ROM database
+fn write_library(dir: &Path) {
+    fs::create_dir_all(dir.join("sub")).unwrap();
+    fs::copy("roms/astro_dodge.ch8", dir.join("sub").join("ad.ch8")).unwrap();
+    fs::write(dir.join("zeta.ch8"), PROGRAM).unwrap();
+    fs::write(dir.join("notes.txt"), "not a ROM").unwrap();
+
+    let mut zip = ZipWriter::new(fs::File::create(dir.join("pack.zip")).unwrap());
+    for name in ["games/alpha.sc8", "readme.txt"] {
+        zip.start_file(name, SimpleFileOptions::default()).unwrap();
+        zip.write_all(&PROGRAM).unwrap();
+    }
+    zip.finish().unwrap();
+
+    let mut gz = GzEncoder::new(fs::File::create(dir.join("beta.ch8.gz")).unwrap(), Compression::default());
+    gz.write_all(&PROGRAM).unwrap();
+    gz.finish().unwrap();
+}
+
+#[test]
+fn scanning_finds_roms_in_subdirectories_and_archives() {
+    let dir = temp_dir("scan");
+    write_library(&dir);
+    let entries = library::scan(&[dir.clone(), dir.join("missing"), dir.join("sub")]);
+
+    let found: Vec<(String, PathBuf)> = entries.iter().map(|entry| (entry.title(), entry.path.clone())).collect();
+    assert_eq!(
+        found,
+        vec![
+            (String::from("alpha"), dir.join("pack.zip").join("games/alpha.sc8")),
+            (String::from("Astro Dodge"), dir.join("sub").join("ad.ch8")),
+            (String::from("beta"), dir.join("beta.ch8.gz")),
+            (String::from("zeta"), dir.join("zeta.ch8")),
+        ]
+    );
+    assert!(entries[1].info.is_some());
+    assert!(entries.iter().filter(|entry| entry.path != entries[1].path).all(|entry| entry.info.is_none()));
+    let _ = fs::remove_dir_all(dir);
+}
+
+#[test]
+fn search_matches_titles_paths_and_authors() {
+    let dir = temp_dir("search");
+    write_library(&dir);
+    let entries = library::scan(std::slice::from_ref(&dir));
+    let search = |query: &str| entries.iter().filter(|entry| entry.matches(query)).map(|entry| entry.title()).collect::<Vec<_>>();
+
+    assert_eq!(search("ASTRO"), vec!["Astro Dodge"]);
+    assert_eq!(search("pack.zip"), vec!["alpha"]);
+    assert_eq!(search("revival"), vec!["Astro Dodge"]);
+    assert!(search("nothing like it").is_empty());
+    let _ = fs::remove_dir_all(dir);
+}
+
+#[test]
+fn recents_and_favorites_are_kept_in_the_file() {
+    let dir = temp_dir("file");
+    let path = dir.join("config").join("library.toml");
+    assert_eq!(LibraryFile::load(&path).unwrap(), LibraryFile::default());
+
+    let mut library = LibraryFile { directories: vec![dir.clone()], ..LibraryFile::default() };
+    for n in 0..12 {
+        library.add_recent(Path::new(&format!("{}.ch8", n)));
+    }
+    library.add_recent(Path::new("5.ch8"));
+    let recent: Vec<String> = library.recent.iter().map(|path| path.display().to_string()).collect();
+    assert_eq!(recent, ["5", "11", "10", "9", "8", "7", "6", "4", "3", "2"].map(|n| format!("{}.ch8", n)));
+
+    assert!(library.toggle_favorite(Path::new("pong.ch8")));
+    assert!(library.toggle_favorite(Path::new("brix.ch8")));
+    assert!(!library.toggle_favorite(Path::new("pong.ch8")));
+    assert!(library.is_favorite(Path::new("brix.ch8")) && !library.is_favorite(Path::new("pong.ch8")));
+
+    library.save(&path).unwrap();
+    assert_eq!(LibraryFile::load(&path).unwrap(), library);
+
+    fs::write(&path, "recent = 5").unwrap();
+    assert!(matches!(LibraryFile::load(&path), Err(LibraryError::Parse(_))));
+    let _ = fs::remove_dir_all(dir);
+}
@@ -0,0 +1,81 @@
+// ROM identification against a chip-8-database checkout on disk, for ROMs the bundled copy doesn't list.
+
+use rust_chip8_emulator::metadata::{self, Database, DatabaseError};
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+// a ROM that isn't in roms/, so only the database on disk knows it
+const ROM: [u8; 6] = [0x60, 0x05, 0xF0, 0x29, 0x12, 0x04];
+
+fn database_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("chip8-database-{}-{}", std::process::id(), name));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let sha1 = metadata::sha1_hex(&ROM);
+    let programs = format!(
+        r##"[{{
+            "title": "Digit",
+            "authors": ["Someone"],
+            "release": "2024",
+            "description": "Shows a 5.",
+            "roms": {{
+                "{}": {{
+                    "file": "digit.ch8",
+                    "embeddedTitle": "digit",
+                    "platforms": ["superchip", "originalChip8"],
+                    "tickrate": 30,
+                    "colors": {{ "pixels": ["#101010", "#e0e0e0"], "buzzer": "#ffaa00" }},
+                    "keys": {{ "a": 5 }}
+                }}
+            }}
+        }}]"##,
+        sha1
+    );
+    let platforms = r#"[
+        { "id": "originalChip8", "name": "Cosmac VIP CHIP-8", "defaultTickrate": 15,
+          "displayResolutions": ["64x32"], "quirks": { "shift": false, "vblank": true } }
+    ]"#;
+    fs::write(dir.join("programs.json"), programs).unwrap();
+    fs::write(dir.join("sha1-hashes.json"), format!(r#"{{ "{}": 0 }}"#, sha1)).unwrap();
+    fs::write(dir.join("platforms.json"), platforms).unwrap();
+    dir
+}
+
+#[test]
+fn a_database_on_disk_knows_roms_the_bundled_copy_doesnt() {
+    let dir = database_dir("lookup");
+    let sha1 = metadata::sha1_hex(&ROM);
+    assert!(Database::bundled().lookup(&sha1).is_none());
+
+    let database = Database::load(&dir).unwrap();
+    let info = database.lookup(&sha1).unwrap();
+    assert_eq!(info.title, "Digit");
+    assert_eq!(info.authors, vec![String::from("Someone")]);
+    //superchip isn't listed, so the next platform is used
+    assert_eq!(info.platform, "originalChip8");
+    assert_eq!(info.speed, 30);
+    assert!(info.quirks.vblank && !info.quirks.shift);
+    assert_eq!(info.palette.map(|palette| (palette.background, palette.foreground)), Some((0x101010, 0xe0e0e0)));
+    assert_eq!(info.keys.get("a"), Some(&5));
+
+    //once installed it's what every ROM is identified with
+    assert!(metadata::identify(&ROM).is_none());
+    database.install().unwrap();
+    assert_eq!(metadata::identify(&ROM).map(|info| info.title), Some(String::from("Digit")));
+    assert!(matches!(Database::load(&dir).unwrap().install(), Err(DatabaseError::AlreadyInstalled)));
+    let _ = fs::remove_dir_all(dir);
+}
+
+#[test]
+fn missing_and_broken_files_name_the_file() {
+    let dir = database_dir("broken");
+    fs::write(dir.join("platforms.json"), "{").unwrap();
+    let error = Database::load(&dir).unwrap_err();
+    assert!(matches!(error, DatabaseError::Parse(ref path, _) if path.ends_with("platforms.json")), "{}", error);
+
+    fs::remove_file(dir.join("sha1-hashes.json")).unwrap();
+    let error = Database::load(&dir).unwrap_err();
+    assert!(matches!(error, DatabaseError::Io(ref path, _) if path.ends_with("sha1-hashes.json")), "{}", error);
+    let _ = fs::remove_dir_all(dir);
+}
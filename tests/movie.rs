@@ -0,0 +1,189 @@
+// Movies replay the run they recorded: the same screen on every frame, a recording in progress isn't
+// thrown away by a replay, and the machine's own settings come back when the replay ends. The user flags
+// the run started with travel in the file, so the flags another machine keeps on disk neither change
+// the replay nor get written over by it.
+
+use rust_chip8_emulator::chip8::Chip8;
+use rust_chip8_emulator::flags::{self, NUM_FLAGS};
+use rust_chip8_emulator::metadata;
+use rust_chip8_emulator::movie::{Movie, MovieError};
+use rust_chip8_emulator::quirks::Quirks;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+// waits for a key, then draws its digit at a random spot
+const DIGITS: [u16; 6] = [0xF10A, 0xC23F, 0xC31F, 0xF129, 0xD235, 0x1200];
+
+// draws a random digit in the corner every frame
+const RANDOM: [u16; 5] = [0x00E0, 0xC10F, 0xF129, 0xD005, 0x1200];
+
+// adds every key pressed to the total in flag 0, which it starts from
+const TOTAL: [u16; 5] = [0xF085, 0xF10A, 0x8014, 0xF075, 0x1202];
+
+fn bytes(opcodes: &[u16]) -> Vec<u8> {
+    opcodes.iter().flat_map(|opcode| opcode.to_be_bytes()).collect()
+}
+
+// a headless machine running the program from a file of its own
+fn load(name: &str, opcodes: &[u16], seed: u64) -> Chip8 {
+    let path = env::temp_dir().join(format!("chip8-movie-{}-{}.ch8", std::process::id(), name));
+    fs::write(&path, bytes(opcodes)).unwrap();
+    let mut chip8 = Chip8::headless();
+    chip8.load_rom(path.to_str().unwrap()).unwrap();
+    fs::remove_file(path).unwrap();
+    chip8.set_seed(seed);
+    chip8.reset();
+    chip8
+}
+
+// records frames of the program with a key pressed and let go every few frames, and the screen after each
+fn record(chip8: &mut Chip8, frames: u64) -> (Movie, Vec<[[bool; 64]; 32]>) {
+    chip8.start_movie();
+    let mut screens = Vec::new();
+    for frame in 0..frames {
+        match frame % 8 {
+            2 => chip8.press_key((frame * 5 % 16) as u8),
+            5 => (0..16).for_each(|key| chip8.release_key(key)),
+            _ => {}
+        }
+        chip8.step_frame();
+        screens.push(chip8.display().frame_buffer);
+    }
+    (chip8.stop_movie().unwrap(), screens)
+}
+
+#[test]
+fn replays_match_the_recording_frame_for_frame() {
+    let mut recorder = load("digits-recorder", &DIGITS, 3);
+    let (movie, screens) = record(&mut recorder, 120);
+    assert_eq!(movie.frames, 120);
+    assert_eq!(movie.events.len(), 30);
+    let movie = Movie::parse(&movie.to_string()).unwrap();
+
+    //live input and another seed make no difference
+    let mut player = load("digits-player", &DIGITS, 9);
+    player.play_movie(&movie).unwrap();
+    for (frame, screen) in screens.iter().enumerate() {
+        player.press_key(0);
+        player.step_frame();
+        assert!(player.display().frame_buffer == *screen, "the screen differs on frame {}", frame);
+    }
+    assert_eq!((player.frame(), player.cycle()), (recorder.frame(), recorder.cycle()));
+    assert!(!player.is_replaying());
+}
+
+#[test]
+fn a_recording_is_not_thrown_away_by_a_replay() {
+    let mut chip8 = load("recording", &DIGITS, 0);
+    let (movie, _) = record(&mut chip8, 20);
+    chip8.start_movie();
+    chip8.step_frame();
+    assert!(matches!(chip8.play_movie(&movie), Err(MovieError::Recording)));
+    assert!(chip8.is_recording_movie() && !chip8.is_replaying());
+    assert_eq!(chip8.stop_movie().unwrap().frames, 1);
+    chip8.play_movie(&movie).unwrap();
+}
+
+#[test]
+fn replays_put_back_the_live_settings() {
+    let mut recorder = load("settings-recorder", &RANDOM, 0);
+    recorder.set_speed(5);
+    recorder.set_quirks(Quirks { wrap: !Quirks::default().wrap, ..Quirks::default() });
+    let (movie, _) = record(&mut recorder, 10);
+
+    let mut player = load("settings-player", &RANDOM, 1);
+    player.set_speed(20);
+    let quirks = player.quirks();
+    //ends on its own
+    player.play_movie(&movie).unwrap();
+    assert_eq!((player.speed(), player.seed(), player.quirks()), (5, 0, movie.quirks));
+    for _ in 0..movie.frames {
+        player.step_frame();
+    }
+    assert!(!player.is_replaying());
+    assert_eq!((player.speed(), player.seed(), player.quirks()), (20, 1, quirks));
+
+    //cancelled part way, with the random numbers coming from the live seed again
+    player.set_speed(5);
+    player.play_movie(&movie).unwrap();
+    player.step_frame();
+    player.stop_replay();
+    assert!(!player.is_replaying());
+    assert_eq!((player.speed(), player.seed(), player.quirks()), (5, 1, quirks));
+    let mut live = load("settings-live", &RANDOM, 1);
+    live.set_speed(5);
+    for frame in 0..8 {
+        player.step_frame();
+        live.step_frame();
+        assert!(player.display().frame_buffer == live.display().frame_buffer, "the random digit differs on frame {}", frame);
+    }
+}
+
+// a flags directory of its own for each machine, holding `first` as the ROM's flag 0
+fn machine(name: &str, first: u8) -> (Chip8, PathBuf) {
+    let dir = env::temp_dir().join(format!("chip8-movie-{}-{}", std::process::id(), name));
+    let _ = fs::remove_dir_all(&dir);
+    let mut flags = [0; NUM_FLAGS];
+    flags[0] = first;
+    let mut chip8 = Chip8::headless();
+    chip8.set_seed(0);
+    flags::save(&dir, &metadata::sha1_hex(&bytes(&TOTAL)), &flags).unwrap();
+    chip8.set_flags_dir(Some(dir.clone()));
+    chip8.load_rom_bytes(&bytes(&TOTAL), "total").unwrap();
+    (chip8, dir)
+}
+
+#[test]
+fn replays_start_from_the_recorded_flags() {
+    let (mut recorder, recorder_dir) = machine("recorder", 5);
+    assert_eq!(recorder.flags()[0], 5);
+    recorder.start_movie();
+    for frame in 0..30 {
+        match frame {
+            4 => recorder.press_key(3),
+            8 | 18 => (0..16).for_each(|key| recorder.release_key(key)),
+            12 => recorder.press_key(2),
+            _ => {}
+        }
+        recorder.step_frame();
+    }
+    let movie = recorder.stop_movie().unwrap();
+    assert_eq!(recorder.registers()[0], 10);
+    assert_eq!(movie.flags[0], 5);
+    let movie = Movie::parse(&movie.to_string()).unwrap();
+    assert_eq!(movie.flags[0], 5);
+
+    let (mut player, player_dir) = machine("player", 40);
+    player.play_movie(&movie).unwrap();
+    for _ in 1..movie.frames {
+        player.step_frame();
+    }
+    assert_eq!(player.flags(), recorder.flags());
+    player.step_frame();
+    assert_eq!(player.registers(), recorder.registers());
+    //once the replay is over the player's own flags are back, and stay as they were on disk
+    assert!(!player.is_replaying());
+    assert_eq!(player.flags()[0], 40);
+    player.save_flags().unwrap();
+    assert_eq!(flags::load(&player_dir, player.rom_sha1()).unwrap()[0], 40);
+
+    //and the same when the replay is cancelled part way
+    player.play_movie(&movie).unwrap();
+    assert_eq!(player.flags()[0], 5);
+    player.step_frame();
+    player.stop_replay();
+    assert_eq!(player.flags()[0], 40);
+
+    for dir in [recorder_dir, player_dir] {
+        let _ = fs::remove_dir_all(dir);
+    }
+}
+
+#[test]
+fn movies_without_flags_start_from_zero() {
+    let text = "chip8-movie 1\nrom_sha1 abc\nrom_name total\nseed 0\nspeed 10\nframes 0\n";
+    assert_eq!(Movie::parse(text).unwrap().flags, [0; NUM_FLAGS]);
+    let text = "chip8-movie 1\nrom_sha1 abc\nflags 01 02\n";
+    assert!(Movie::parse(text).is_err());
+}
@@ -0,0 +1,191 @@
+// The Octo compiler and cartridge GIFs: what each control structure compiles to, checked opcode by
+// opcode, and a cartridge built here decoding back to its program and options.
+
+use rust_chip8_emulator::cartridge::Cartridge;
+use rust_chip8_emulator::octo;
+use rust_chip8_emulator::quirks::Quirks;
+
+// the program as opcodes, starting with the jump to main
+fn opcodes(source: &str) -> Vec<u16> {
+    let bytes = octo::compile(source).unwrap_or_else(|e| panic!("{}", e));
+    bytes.chunks(2).map(|pair| u16::from_be_bytes([pair[0], *pair.get(1).unwrap_or(&0)])).collect()
+}
+
+fn error(source: &str) -> String {
+    octo::compile(source).expect_err("compiled").to_string()
+}
+
+#[test]
+fn comparisons_subtract_in_vf() {
+    assert_eq!(opcodes(": main if v3 > 5 then v0 := 1"), [0x1202, 0x6F05, 0x8F35, 0x3F01, 0x6001]);
+    assert_eq!(opcodes(": main if v3 < 5 then v0 := 1"), [0x1202, 0x6F05, 0x8F37, 0x3F01, 0x6001]);
+    assert_eq!(opcodes(": main if v3 >= v4 then v0 := 1"), [0x1202, 0x8F40, 0x8F37, 0x4F01, 0x6001]);
+    assert_eq!(opcodes(": main if v3 == 5 then v0 := 1"), [0x1202, 0x4305, 0x6001]);
+    assert_eq!(opcodes(": main if v3 -key then v0 := 1"), [0x1202, 0xE39E, 0x6001]);
+}
+
+#[test]
+fn if_begin_else_end_jumps_around_each_branch() {
+    let source = "
+        : main
+            if v0 == 1 begin
+                v1 := 2
+            else
+                v1 := 3
+            end";
+    //the negated test skips the jump to else, and the end of the first branch jumps past the second
+    assert_eq!(opcodes(source), [0x1202, 0x3001, 0x120A, 0x6102, 0x120C, 0x6103]);
+    assert_eq!(opcodes(": main if v0 != v1 begin v2 := 0 end"), [0x1202, 0x9010, 0x1208, 0x6200]);
+}
+
+#[test]
+fn while_jumps_out_of_the_loop() {
+    let source = "
+        : main
+            loop
+                v0 += 1
+                while v0 != 10
+                v1 := 0
+            again";
+    assert_eq!(opcodes(source), [0x1202, 0x7001, 0x400A, 0x120C, 0x6100, 0x1202]);
+}
+
+#[test]
+fn labels_can_be_used_before_they_are_defined() {
+    let source = "
+        : main
+            i := data
+            sub
+            jump main
+        : sub
+            return
+        : data 0xAB 0xCD";
+    assert_eq!(opcodes(source), [0x1202, 0xA20A, 0x2208, 0x1202, 0x00EE, 0xABCD]);
+    assert_eq!(error(": main jump nowhere"), "line 1: undefined name \"nowhere\"");
+}
+
+#[test]
+fn next_labels_the_operand_of_the_next_instruction() {
+    let source = "
+        : main
+            :next counter
+            v0 := 0
+            v0 += 1
+            i := counter
+            save v0
+            jump main";
+    assert_eq!(opcodes(source), [0x1202, 0x6000, 0x7001, 0xA203, 0xF055, 0x1202]);
+}
+
+#[test]
+fn unpack_loads_a_nibble_and_an_address_into_v0_and_v1() {
+    let source = "
+        : data 0x12 0x34
+        : main
+            :unpack 0xA data
+            :unpack 0xB later
+        : later";
+    assert_eq!(opcodes(source), [0x1204, 0x1234, 0x60A2, 0x6102, 0x60B2, 0x610C]);
+}
+
+#[test]
+fn calc_groups_to_the_right() {
+    let source = "
+        :calc width { 8 * 4 + 1 }
+        :calc half { width / 2 }
+        : main
+            v0 := width
+            v1 := half
+            :byte { 1 << 4 }
+            :byte { ( 8 * 4 ) + 1 }";
+    assert_eq!(opcodes(source), [0x1202, 0x6028, 0x6114, 0x1021]);
+}
+
+#[test]
+fn calc_reports_shifts_it_cant_do() {
+    assert_eq!(error(":calc x { 1 << 64 }\n: main"), "line 1: can't shift by 64 in :calc");
+    assert_eq!(error(": main\n:calc x { 1 >> -1 }"), "line 2: can't shift by -1 in :calc");
+    assert_eq!(error(":calc x { 1 + }\n: main"), "line 1: :calc expression ends early");
+}
+
+#[test]
+fn macros_substitute_their_arguments() {
+    let source = "
+        :macro set reg value { reg := value }
+        :macro twice reg { set reg 1 set reg 2 }
+        : main twice v3";
+    assert_eq!(opcodes(source), [0x1202, 0x6301, 0x6302]);
+}
+
+#[test]
+fn macros_that_use_themselves_stop() {
+    let expected = "line 2: macro \"m\" is nested more than 64 deep, does it use itself?";
+    assert_eq!(error(":macro m { m }\n: main m"), expected);
+    assert_eq!(error(":macro m { m m }\n: main m"), expected);
+}
+
+#[test]
+fn structures_have_to_be_closed() {
+    assert_eq!(error(": main loop v0 += 1"), "line 1: a loop is missing its again");
+    assert_eq!(error(": main if v0 == 1 begin v0 := 2"), "line 1: an if ... begin is missing its end");
+    assert_eq!(error(": main again"), "line 1: again without a loop");
+    assert_eq!(error("v0 := 1"), "line 1: the program has no main label to start at");
+}
+
+// an image the way Octo saves cartridges: every pixel's color index carries two bits of the payload in
+// its low bits, on top of an image drawn with the rest
+fn gif_holding(payload: &[u8]) -> Vec<u8> {
+    const WIDTH: usize = 64;
+    let height = (payload.len() * 4).div_ceil(WIDTH);
+    let mut pixels: Vec<u8> = payload.iter().flat_map(|&byte| [6, 4, 2, 0].map(|shift| byte >> shift & 3)).collect();
+    pixels.resize(WIDTH * height, 0);
+    for (index, pixel) in pixels.iter_mut().enumerate() {
+        *pixel |= (index % 7 * 4) as u8;
+    }
+
+    let palette: Vec<u8> = (0..=255).flat_map(|index: u8| [index, index, index]).collect();
+    let mut gif = Vec::new();
+    let mut encoder = gif::Encoder::new(&mut gif, WIDTH as u16, height as u16, &palette).unwrap();
+    encoder.write_frame(&gif::Frame::from_indexed_pixels(WIDTH as u16, height as u16, pixels, None)).unwrap();
+    drop(encoder);
+    gif
+}
+
+// the JSON after its 32-bit length
+fn cartridge_gif(json: &str) -> Vec<u8> {
+    let mut payload = (json.len() as u32).to_be_bytes().to_vec();
+    payload.extend(json.as_bytes());
+    gif_holding(&payload)
+}
+
+#[test]
+fn cartridges_decode_to_their_program_and_options() {
+    let json = r##"{"program": ": main\n  v0 := 7\n  jump main", "options": {"tickrate": 20, "fillColor": "#FFCC00", "backgroundColor": "#996600", "shiftQuirks": true, "clipQuirks": true, "vBlankQuirks": true}}"##;
+    let cartridge = Cartridge::decode(cartridge_gif(json).as_slice()).unwrap();
+    assert_eq!(cartridge.program, ": main\n  v0 := 7\n  jump main");
+    assert_eq!(cartridge.compile().unwrap(), [0x12, 0x02, 0x60, 0x07, 0x12, 0x02]);
+
+    let settings = cartridge.settings();
+    assert_eq!(settings.ips, Some(20 * 60));
+    assert_eq!(settings.palette.as_deref(), Some("#996600,#FFCC00"));
+    let quirks: Quirks = settings.quirks.unwrap().parse().unwrap();
+    assert!(quirks.shift && !quirks.wrap && quirks.vblank);
+    assert!(!quirks.jump && !quirks.logic && !quirks.memory_leave_i_unchanged);
+}
+
+#[test]
+fn images_without_a_payload_are_not_cartridges() {
+    let error = |gif: Vec<u8>| Cartridge::decode(gif.as_slice()).unwrap_err().to_string();
+    assert!(error(cartridge_gif("not json")).starts_with("not an Octo cartridge"));
+    //a length far past the end of the image
+    let mut payload = 4000u32.to_be_bytes().to_vec();
+    payload.extend(b"{}");
+    assert!(error(gif_holding(&payload)).starts_with("not an Octo cartridge: it should hold 4000 bytes"));
+    assert!(Cartridge::decode(&b"GIF89a"[..]).is_err());
+}
+
+#[test]
+fn tickrates_too_large_for_ips_are_left_out() {
+    let json = format!(r#"{{"program": ": main", "options": {{"tickrate": {}}}}}"#, usize::MAX);
+    assert_eq!(Cartridge::decode(cartridge_gif(&json).as_slice()).unwrap().settings().ips, None);
+}
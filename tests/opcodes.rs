@@ -0,0 +1,606 @@
+// Conformance tests for every opcode the interpreter handles. Each test writes a few instructions at
+// 0x200 of a headless machine and steps through them; where a quirk changes the result, the expected
+// value is listed for every preset.
+
+use rust_chip8_emulator::chip8::Chip8;
+use rust_chip8_emulator::quirks::Quirks;
+
+const START: u16 = 0x200;
+
+fn machine(preset: &str) -> Chip8 {
+    let mut chip8 = Chip8::headless();
+    chip8.set_quirks(Quirks::preset(preset).unwrap());
+    chip8.set_seed(0);
+    chip8
+}
+
+// loads program at 0x200 and steps until pc leaves it, so skipped instructions aren't run
+fn run(chip8: &mut Chip8, program: &[u16]) {
+    let bytes: Vec<u8> = program.iter().flat_map(|opcode| opcode.to_be_bytes()).collect();
+    chip8.write_memory(START as usize, &bytes);
+    chip8.set_pc(START);
+    let end = START + bytes.len() as u16;
+    for _ in 0..1000 {
+        if !(START..end).contains(&chip8.pc()) {
+            return;
+        }
+        chip8.step();
+    }
+    panic!("the program didn't finish, pc is {:#05X}", chip8.pc());
+}
+
+fn lit_pixels(chip8: &Chip8) -> Vec<(usize, usize)> {
+    let mut pixels = Vec::new();
+    for (y, row) in chip8.display().frame_buffer.iter().enumerate() {
+        for (x, &lit) in row.iter().enumerate() {
+            if lit {
+                pixels.push((x, y));
+            }
+        }
+    }
+    pixels
+}
+
+#[test]
+fn clear_screen() {
+    let mut chip8 = machine("default");
+    run(&mut chip8, &[0xA050, 0xD005, 0x00E0]);
+    assert!(lit_pixels(&chip8).is_empty());
+}
+
+#[test]
+fn jump() {
+    let mut chip8 = machine("default");
+    chip8.write_memory(0x200, &[0x13, 0x45]);
+    chip8.step();
+    assert_eq!(chip8.pc(), 0x345);
+}
+
+#[test]
+fn call_and_return() {
+    let mut chip8 = machine("default");
+    chip8.write_memory(0x200, &[0x23, 0x00]);
+    chip8.write_memory(0x300, &[0x00, 0xEE]);
+    chip8.step();
+    assert_eq!(chip8.pc(), 0x300);
+    assert_eq!(chip8.stack(), &[0x202]);
+    chip8.step();
+    assert_eq!(chip8.pc(), 0x202);
+    assert!(chip8.stack().is_empty());
+}
+
+#[test]
+fn stack_holds_sixteen_calls() {
+    let mut chip8 = machine("default");
+    //each subroutine calls the next, the last one unwinds them all
+    for depth in 0..16u16 {
+        let address = 0x300 + depth * 4;
+        let opcode = if depth == 15 { 0x00EE } else { 0x2000 | (address + 4) };
+        chip8.write_memory(address as usize, &opcode.to_be_bytes());
+    }
+    chip8.write_memory(0x200, &[0x23, 0x00]);
+    for _ in 0..16 {
+        chip8.step();
+    }
+    assert_eq!(chip8.stack().len(), 16);
+    assert_eq!(chip8.stack()[0], 0x202);
+    assert_eq!(chip8.stack()[15], 0x33A);
+    assert_eq!(chip8.pc(), 0x33C);
+    chip8.step();
+    assert_eq!(chip8.pc(), 0x33A);
+    assert_eq!(chip8.stack().len(), 15);
+}
+
+#[test]
+fn skips() {
+    //each case skips the 6101 that follows it or not
+    let cases = [
+        (0x3012, true),  // V0 == 0x12
+        (0x3013, false),
+        (0x4012, false), // V0 != 0x12
+        (0x4013, true),
+        (0x5020, true),  // V0 == V2
+        (0x5030, false),
+        (0x9020, false), // V0 != V2
+        (0x9030, true),
+    ];
+    for (opcode, skipped) in cases {
+        let mut chip8 = machine("default");
+        chip8.set_register(0, 0x12);
+        chip8.set_register(2, 0x12);
+        chip8.set_register(3, 0x34);
+        run(&mut chip8, &[opcode, 0x6101]);
+        assert_eq!(chip8.registers()[1], if skipped { 0 } else { 1 }, "{:04X}", opcode);
+    }
+}
+
+#[test]
+fn load_and_add_immediate() {
+    let mut chip8 = machine("default");
+    chip8.set_register(15, 7);
+    run(&mut chip8, &[0x63FF, 0x7302, 0x6410, 0x7420]);
+    assert_eq!(chip8.registers()[3], 0x01);
+    assert_eq!(chip8.registers()[4], 0x30);
+    assert_eq!(chip8.registers()[15], 7, "7XNN has no carry flag");
+}
+
+#[test]
+fn register_copy() {
+    let mut chip8 = machine("default");
+    chip8.set_register(2, 0xAB);
+    run(&mut chip8, &[0x8120]);
+    assert_eq!(chip8.registers()[1], 0xAB);
+}
+
+#[test]
+fn logic_operations() {
+    for (preset, resets_vf) in [("default", false), ("chip8", true), ("schip", false), ("xochip", false)] {
+        for (opcode, expected) in [(0x8011, 0b1110), (0x8012, 0b1000), (0x8013, 0b0110)] {
+            let mut chip8 = machine(preset);
+            chip8.set_register(0, 0b1100);
+            chip8.set_register(1, 0b1010);
+            chip8.set_register(15, 5);
+            run(&mut chip8, &[opcode]);
+            assert_eq!(chip8.registers()[0], expected, "{} {:04X}", preset, opcode);
+            assert_eq!(chip8.registers()[15], if resets_vf { 0 } else { 5 }, "{} {:04X}", preset, opcode);
+        }
+    }
+}
+
+#[test]
+fn add_registers() {
+    for (x, y, sum, carry) in [(0x10, 0x20, 0x30, 0), (0xFF, 0x01, 0x00, 1), (0xF0, 0x20, 0x10, 1), (0x80, 0x7F, 0xFF, 0)] {
+        let mut chip8 = machine("default");
+        chip8.set_register(0, x);
+        chip8.set_register(1, y);
+        run(&mut chip8, &[0x8014]);
+        assert_eq!(chip8.registers()[0], sum);
+        assert_eq!(chip8.registers()[15], carry);
+    }
+}
+
+#[test]
+fn subtract_registers() {
+    //8XY5 is VX - VY, 8XY7 is VY - VX; VF is 1 when there is no borrow, including when they are equal
+    for (x, y, difference, flag) in [(0x30, 0x10, 0x20, 1), (0x10, 0x30, 0xE0, 0), (0x42, 0x42, 0x00, 1)] {
+        let mut chip8 = machine("default");
+        chip8.set_register(0, x);
+        chip8.set_register(1, y);
+        run(&mut chip8, &[0x8015]);
+        assert_eq!(chip8.registers()[0], difference);
+        assert_eq!(chip8.registers()[15], flag);
+
+        let mut chip8 = machine("default");
+        chip8.set_register(0, y);
+        chip8.set_register(1, x);
+        run(&mut chip8, &[0x8017]);
+        assert_eq!(chip8.registers()[0], difference);
+        assert_eq!(chip8.registers()[15], flag);
+    }
+}
+
+#[test]
+fn flag_wins_when_vf_is_the_destination() {
+    //the result is written first and then overwritten by the flag
+    for (opcode, vf, vy, flag) in [(0x8F14, 0xFF, 0x02, 1), (0x8F14, 0x01, 0x02, 0), (0x8F15, 0x01, 0x02, 0), (0x8F15, 0x05, 0x02, 1), (0x8F17, 0x05, 0x02, 0), (0x8F17, 0x01, 0x02, 1)] {
+        let mut chip8 = machine("default");
+        chip8.set_register(15, vf);
+        chip8.set_register(1, vy);
+        run(&mut chip8, &[opcode]);
+        assert_eq!(chip8.registers()[15], flag, "{:04X} with VF={:02X} VY={:02X}", opcode, vf, vy);
+    }
+}
+
+#[test]
+fn vf_as_source_is_read_before_the_flag_is_set() {
+    let mut chip8 = machine("default");
+    chip8.set_register(0, 0xFF);
+    chip8.set_register(15, 0x02);
+    run(&mut chip8, &[0x80F4]);
+    assert_eq!(chip8.registers()[0], 0x01);
+    assert_eq!(chip8.registers()[15], 1);
+
+    let mut chip8 = machine("default");
+    chip8.set_register(0, 0x01);
+    chip8.set_register(15, 0x03);
+    run(&mut chip8, &[0x80F5]);
+    assert_eq!(chip8.registers()[0], 0xFE);
+    assert_eq!(chip8.registers()[15], 0);
+
+    let mut chip8 = machine("default");
+    chip8.set_register(0, 0x01);
+    chip8.set_register(15, 0x03);
+    run(&mut chip8, &[0x80F7]);
+    assert_eq!(chip8.registers()[0], 0x02);
+    assert_eq!(chip8.registers()[15], 1);
+}
+
+#[test]
+fn shifts() {
+    //V0 = 0b1000_0001, V1 = 0b0100_0010; with the shift quirk VX is shifted, otherwise VY is shifted into VX
+    let presets = [
+        ("default", (0b0100_0000, 1), (0b0000_0010, 1)),
+        ("chip8", (0b0010_0001, 0), (0b1000_0100, 0)),
+        ("schip", (0b0100_0000, 1), (0b0000_0010, 1)),
+        ("xochip", (0b0010_0001, 0), (0b1000_0100, 0)),
+    ];
+    for (preset, right, left) in presets {
+        for (opcode, (value, flag)) in [(0x8016, right), (0x801E, left)] {
+            let mut chip8 = machine(preset);
+            chip8.set_register(0, 0b1000_0001);
+            chip8.set_register(1, 0b0100_0010);
+            run(&mut chip8, &[opcode]);
+            assert_eq!(chip8.registers()[0], value, "{} {:04X}", preset, opcode);
+            assert_eq!(chip8.registers()[1], 0b0100_0010, "{} {:04X}", preset, opcode);
+            assert_eq!(chip8.registers()[15], flag, "{} {:04X}", preset, opcode);
+        }
+    }
+}
+
+#[test]
+fn shift_into_vf_keeps_the_flag() {
+    for preset in Quirks::PRESETS {
+        let mut chip8 = machine(preset);
+        chip8.set_register(15, 0b1000_0001);
+        chip8.set_register(1, 0b1000_0001);
+        run(&mut chip8, &[0x8F16]);
+        assert_eq!(chip8.registers()[15], 1, "{}", preset);
+
+        let mut chip8 = machine(preset);
+        chip8.set_register(15, 0b0000_0001);
+        chip8.set_register(1, 0b0000_0001);
+        run(&mut chip8, &[0x8F1E]);
+        assert_eq!(chip8.registers()[15], 0, "{}", preset);
+    }
+}
+
+#[test]
+fn set_index() {
+    let mut chip8 = machine("default");
+    run(&mut chip8, &[0xA123]);
+    assert_eq!(chip8.i_register(), 0x123);
+}
+
+#[test]
+fn jump_with_offset() {
+    //B234 with V0 = 1 and V2 = 0x10 jumps to 0x235, or to 0x244 with the jump quirk
+    for (preset, target) in [("default", 0x235), ("chip8", 0x235), ("schip", 0x244), ("xochip", 0x235)] {
+        let mut chip8 = machine(preset);
+        chip8.set_register(0, 0x01);
+        chip8.set_register(2, 0x10);
+        chip8.write_memory(0x200, &[0xB2, 0x34]);
+        chip8.step();
+        assert_eq!(chip8.pc(), target, "{}", preset);
+    }
+}
+
+#[test]
+fn random_is_masked_and_seeded() {
+    let mut first = machine("default");
+    let mut second = machine("default");
+    let program: Vec<u16> = (0..16).map(|x| 0xC00F | x << 8).collect();
+    run(&mut first, &program);
+    run(&mut second, &program);
+    assert!(first.registers().iter().all(|&value| value & 0xF0 == 0));
+    assert_eq!(first.registers(), second.registers());
+
+    let mut chip8 = machine("default");
+    chip8.set_register(0, 0xFF);
+    run(&mut chip8, &[0xC000]);
+    assert_eq!(chip8.registers()[0], 0);
+}
+
+#[test]
+fn draw_sets_and_clears_the_collision_flag() {
+    let mut chip8 = machine("default");
+    chip8.set_register(15, 1);
+    run(&mut chip8, &[0xA050, 0xD005]); // the 0 glyph at (0, 0)
+    assert_eq!(chip8.registers()[15], 0);
+    assert_eq!(lit_pixels(&chip8).len(), 14);
+
+    //drawing it again erases it and reports the collision
+    run(&mut chip8, &[0xD005]);
+    assert_eq!(chip8.registers()[15], 1);
+    assert!(lit_pixels(&chip8).is_empty());
+
+    //an overlap of a single pixel is a collision too, and the rest of the sprite is still drawn
+    run(&mut chip8, &[0xA050, 0xD005, 0x6103, 0xD115]);
+    assert_eq!(chip8.registers()[15], 1);
+}
+
+#[test]
+fn draw_with_vf_as_coordinate() {
+    let mut chip8 = machine("default");
+    chip8.set_register(15, 8);
+    run(&mut chip8, &[0xA050, 0xDFF1]);
+    assert_eq!(lit_pixels(&chip8), vec![(8, 8), (9, 8), (10, 8), (11, 8)]);
+    assert_eq!(chip8.registers()[15], 0);
+}
+
+#[test]
+fn draw_start_coordinates_wrap() {
+    //the start position always wraps, whatever the preset
+    for preset in Quirks::PRESETS {
+        let mut chip8 = machine(preset);
+        chip8.write_memory(0x300, &[0x80]);
+        chip8.set_register(0, 64 + 5);
+        chip8.set_register(1, 32 + 3);
+        run(&mut chip8, &[0xA300, 0xD011]);
+        assert_eq!(lit_pixels(&chip8), vec![(5, 3)], "{}", preset);
+    }
+}
+
+#[test]
+fn draw_at_the_right_edge() {
+    //a full row at x = 60 has its last four pixels clipped, or wrapped to the left edge with the wrap quirk
+    for (preset, wraps) in [("default", false), ("chip8", false), ("schip", false), ("xochip", true)] {
+        let mut chip8 = machine(preset);
+        chip8.write_memory(0x300, &[0xFF]);
+        chip8.set_register(0, 60);
+        run(&mut chip8, &[0xA300, 0xD011]);
+        let mut expected = vec![(60, 0), (61, 0), (62, 0), (63, 0)];
+        if wraps {
+            expected.splice(0..0, [(0, 0), (1, 0), (2, 0), (3, 0)]);
+        }
+        assert_eq!(lit_pixels(&chip8), expected, "{}", preset);
+    }
+}
+
+#[test]
+fn draw_at_the_bottom_edge() {
+    //four rows at y = 30 lose the last two, or draw them at the top with the wrap quirk
+    for (preset, wraps) in [("default", false), ("chip8", false), ("schip", false), ("xochip", true)] {
+        let mut chip8 = machine(preset);
+        chip8.write_memory(0x300, &[0x80, 0x80, 0x80, 0x80]);
+        chip8.set_register(1, 30);
+        run(&mut chip8, &[0xA300, 0xD014]);
+        let mut expected = vec![(0, 30), (0, 31)];
+        if wraps {
+            expected.splice(0..0, [(0, 0), (0, 1)]);
+        }
+        assert_eq!(lit_pixels(&chip8), expected, "{}", preset);
+    }
+}
+
+#[test]
+fn draw_at_the_top_left_corner() {
+    let mut chip8 = machine("default");
+    chip8.write_memory(0x300, &[0x81, 0x00, 0x00, 0x81]);
+    run(&mut chip8, &[0xA300, 0xD004]);
+    assert_eq!(lit_pixels(&chip8), vec![(0, 0), (7, 0), (0, 3), (7, 3)]);
+}
+
+#[test]
+fn clipped_pixels_do_not_collide() {
+    let mut chip8 = machine("default");
+    chip8.write_memory(0x300, &[0xFF, 0x80]);
+    chip8.set_register(0, 60);
+    chip8.set_register(1, 31);
+    //the second row and the right half of the first fall off the screen
+    run(&mut chip8, &[0xA300, 0xD012, 0xA301, 0x6200, 0x6300, 0xD231, 0xA300, 0xD012]);
+    assert_eq!(chip8.registers()[15], 1);
+    assert_eq!(lit_pixels(&chip8), vec![(0, 0)]);
+
+    let mut chip8 = machine("default");
+    chip8.write_memory(0x300, &[0x0F]);
+    chip8.set_register(0, 60);
+    run(&mut chip8, &[0xA300, 0xD011, 0xD011]);
+    assert_eq!(chip8.registers()[15], 0, "nothing on screen was drawn over");
+}
+
+#[test]
+fn draw_ends_the_frame_with_the_vblank_quirk() {
+    for (preset, instructions) in [("default", 10), ("chip8", 2), ("schip", 10), ("xochip", 10)] {
+        let mut chip8 = machine(preset);
+        let mut program = vec![0x00, 0xE0, 0xD0, 0x01];
+        program.extend([0x70, 0x01].repeat(20));
+        chip8.write_memory(0x200, &program);
+        chip8.step_frame();
+        assert_eq!(chip8.cycle(), instructions, "{}", preset);
+    }
+}
+
+#[test]
+fn key_skips() {
+    //EX9E skips when the key in VX is held, EXA1 when it isn't
+    for (opcode, held, skipped) in [(0xE09E, true, true), (0xE09E, false, false), (0xE0A1, true, false), (0xE0A1, false, true)] {
+        let mut chip8 = machine("default");
+        chip8.set_register(0, 0xB);
+        if held {
+            chip8.press_key(0xB);
+        }
+        chip8.press_key(0x3);
+        chip8.apply_keys();
+        run(&mut chip8, &[opcode, 0x6101]);
+        assert_eq!(chip8.registers()[1], if skipped { 0 } else { 1 }, "{:04X} with the key held: {}", opcode, held);
+    }
+}
+
+#[test]
+fn key_skips_use_the_low_nibble() {
+    let mut chip8 = machine("default");
+    chip8.set_register(0, 0x1B);
+    chip8.press_key(0xB);
+    chip8.apply_keys();
+    run(&mut chip8, &[0xE09E, 0x6101]);
+    assert_eq!(chip8.registers()[1], 0);
+}
+
+#[test]
+fn wait_for_key_blocks_until_release() {
+    let mut chip8 = machine("default");
+    chip8.write_memory(0x200, &[0xF3, 0x0A]);
+    for _ in 0..3 {
+        chip8.step();
+        assert_eq!(chip8.pc(), 0x200);
+    }
+
+    //a press alone doesn't finish the wait
+    chip8.press_key(0x7);
+    chip8.apply_keys();
+    chip8.step();
+    chip8.step();
+    assert_eq!(chip8.pc(), 0x200);
+
+    chip8.release_key(0x7);
+    chip8.apply_keys();
+    chip8.step();
+    assert_eq!(chip8.pc(), 0x202);
+    assert_eq!(chip8.registers()[3], 0x7);
+}
+
+#[test]
+fn wait_for_key_ignores_keys_held_before_it_started() {
+    let mut chip8 = machine("default");
+    chip8.press_key(0x2);
+    chip8.apply_keys();
+    chip8.write_memory(0x200, &[0xF0, 0x0A]);
+    chip8.step();
+    chip8.release_key(0x2);
+    chip8.apply_keys();
+    chip8.step();
+    assert_eq!(chip8.pc(), 0x200);
+}
+
+#[test]
+fn wait_for_key_takes_the_first_key_pressed() {
+    let mut chip8 = machine("default");
+    chip8.write_memory(0x200, &[0xF0, 0x0A]);
+    chip8.step();
+    //9 goes down just before 2 within the same instruction, so it's the key even though 2 is lower
+    chip8.press_key(0x9);
+    chip8.press_key(0x2);
+    chip8.apply_keys();
+    chip8.step();
+    chip8.release_key(0x9);
+    chip8.release_key(0x2);
+    chip8.apply_keys();
+    chip8.step();
+    assert_eq!(chip8.pc(), 0x202);
+    assert_eq!(chip8.registers()[0], 0x9);
+}
+
+#[test]
+fn timers() {
+    let mut chip8 = machine("default");
+    chip8.set_register(0, 5);
+    chip8.set_register(1, 9);
+    run(&mut chip8, &[0xF015, 0xF118, 0xF207]);
+    assert_eq!(chip8.delay_timer(), 5);
+    assert_eq!(chip8.sound_timer(), 9);
+    assert_eq!(chip8.registers()[2], 5);
+
+    //each frame takes one off either timer, down to 0
+    chip8.set_speed(1);
+    chip8.write_memory(0x206, &[0x12, 0x06]);
+    for _ in 0..6 {
+        chip8.step_frame();
+    }
+    assert_eq!(chip8.delay_timer(), 0);
+    assert_eq!(chip8.sound_timer(), 3);
+}
+
+#[test]
+fn add_to_index() {
+    let mut chip8 = machine("default");
+    chip8.set_register(15, 9);
+    chip8.set_register(0, 0x20);
+    run(&mut chip8, &[0xAFF0, 0xF01E]);
+    assert_eq!(chip8.i_register(), 0x1010);
+    assert_eq!(chip8.registers()[15], 9, "FX1E leaves VF alone");
+}
+
+#[test]
+fn font_characters() {
+    for digit in 0..16u16 {
+        let mut chip8 = machine("default");
+        chip8.set_register(4, 0xF0 | digit as u8); //only the low nibble counts
+        run(&mut chip8, &[0xF429]);
+        assert_eq!(chip8.i_register(), 0x050 + digit * 5);
+    }
+    let chip8 = machine("default");
+    assert_eq!(&chip8.memory()[0x050..0x055], &[0xF0, 0x90, 0x90, 0x90, 0xF0]);
+}
+
+#[test]
+fn binary_coded_decimal() {
+    for (value, digits) in [(0, [0, 0, 0]), (9, [0, 0, 9]), (10, [0, 1, 0]), (99, [0, 9, 9]), (100, [1, 0, 0]), (128, [1, 2, 8]), (255, [2, 5, 5])] {
+        let mut chip8 = machine("default");
+        chip8.set_register(6, value);
+        chip8.write_memory(0x400, &[0xAA; 4]);
+        run(&mut chip8, &[0xA400, 0xF633]);
+        assert_eq!(&chip8.memory()[0x400..0x404], &[digits[0], digits[1], digits[2], 0xAA], "{}", value);
+        assert_eq!(chip8.i_register(), 0x400, "FX33 leaves I alone");
+    }
+}
+
+#[test]
+fn store_and_load_registers() {
+    //where I ends up after FX55/FX65 with X = 3, starting at 0x400
+    for (preset, i) in [("default", 0x400), ("chip8", 0x404), ("schip", 0x400), ("xochip", 0x404)] {
+        let mut chip8 = machine(preset);
+        for x in 0..16 {
+            chip8.set_register(x, 0x10 + x as u8);
+        }
+        run(&mut chip8, &[0xA400, 0xF355]);
+        assert_eq!(&chip8.memory()[0x400..0x405], &[0x10, 0x11, 0x12, 0x13, 0x00], "{}", preset);
+        assert_eq!(chip8.i_register(), i, "{}", preset);
+
+        let mut chip8 = machine(preset);
+        chip8.write_memory(0x400, &[1, 2, 3, 4, 5]);
+        chip8.set_register(4, 0xEE);
+        run(&mut chip8, &[0xA400, 0xF365]);
+        assert_eq!(&chip8.registers()[..5], &[1, 2, 3, 4, 0xEE], "{}", preset);
+        assert_eq!(chip8.i_register(), i, "{}", preset);
+    }
+}
+
+#[test]
+fn memory_increment_by_x() {
+    let mut chip8 = Chip8::headless();
+    chip8.set_quirks(Quirks { memory_increment_by_x: true, memory_leave_i_unchanged: false, ..Quirks::chip8() });
+    run(&mut chip8, &[0xA400, 0xF355]);
+    assert_eq!(chip8.i_register(), 0x403);
+}
+
+#[test]
+fn user_flags() {
+    let mut chip8 = machine("schip");
+    for x in 0..16 {
+        chip8.set_register(x, x as u8 + 1);
+    }
+    run(&mut chip8, &[0xF775]);
+    assert_eq!(&chip8.flags()[..9], &[1, 2, 3, 4, 5, 6, 7, 8, 0]);
+
+    for x in 0..16 {
+        chip8.set_register(x, 0);
+    }
+    run(&mut chip8, &[0xF385]);
+    assert_eq!(&chip8.registers()[..5], &[1, 2, 3, 4, 0]);
+}
+
+#[test]
+fn unknown_opcodes_are_ignored() {
+    let mut chip8 = machine("default");
+    run(&mut chip8, &[0x0123, 0x8018, 0xE0FF, 0xF0FF, 0x6101]);
+    assert_eq!(chip8.registers()[1], 1);
+    assert!(chip8.stack().is_empty());
+}
+
+#[test]
+fn self_modifying_code_runs_as_written() {
+    //the loop runs 7301 once, then stores 7305 over it, so the second pass has to see the new instruction
+    let program = [0xA208, 0x6073, 0x6105, 0x7401, 0x7301, 0xF155, 0x3402, 0x1206];
+    for cache in [true, false] {
+        let mut chip8 = machine("default");
+        chip8.set_instruction_cache(cache);
+        run(&mut chip8, &program);
+        assert_eq!(chip8.registers()[3], 6, "with the instruction cache: {}", cache);
+    }
+
+    //FX33 writes count too: 255 stores 02 05 05 over the 7301 and the start of itself, which both do nothing after
+    let mut chip8 = machine("default");
+    run(&mut chip8, &[0xA208, 0x60FF, 0x7401, 0x6500, 0x7301, 0xF033, 0x3402, 0x1204]);
+    assert_eq!(chip8.registers()[3], 1);
+    assert_eq!(&chip8.memory()[0x208..0x20B], &[0x02, 0x05, 0x05]);
+}
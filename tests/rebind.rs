@@ -0,0 +1,55 @@
+// Rebinding the keypad from the window: one host key per CHIP-8 key in keypad order, Backspace keeps a
+// binding, Escape cancels and puts back every binding made so far, and a finished keypad is saved.
+
+use minifb::Key;
+use rust_chip8_emulator::chip8::Chip8;
+use rust_chip8_emulator::keymap::{Keymap, KeymapFile, KEYPAD_LAYOUT};
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+fn machine(name: &str) -> (Chip8, PathBuf) {
+    let path = env::temp_dir().join(format!("chip8-rebind-{}-{}.toml", std::process::id(), name));
+    let _ = fs::remove_file(&path);
+    let mut chip8 = Chip8::headless();
+    chip8.set_keymap(Keymap::default(), Some(path.clone()));
+    (chip8, path)
+}
+
+#[test]
+fn cancelling_puts_back_the_old_bindings() {
+    let (mut chip8, path) = machine("cancel");
+    chip8.start_rebinding();
+    chip8.rebind_key(Key::Z);
+    chip8.rebind_key(Key::Backspace);
+    chip8.rebind_key(Key::M);
+    assert_eq!(chip8.keymap().keypad[KEYPAD_LAYOUT[0]], vec![Key::Z]);
+    assert_eq!(chip8.keymap().keypad[KEYPAD_LAYOUT[2]], vec![Key::M]);
+
+    chip8.rebind_key(Key::Escape);
+    assert!(!chip8.is_rebinding());
+    assert_eq!(chip8.keymap(), &Keymap::default());
+    assert!(!path.exists());
+}
+
+#[test]
+fn a_finished_keypad_is_saved() {
+    let (mut chip8, path) = machine("finish");
+    let keys = [
+        Key::A, Key::B, Key::C, Key::D, Key::E, Key::F, Key::G, Key::H,
+        Key::I, Key::J, Key::K, Key::L, Key::M, Key::N, Key::O, Key::P,
+    ];
+    chip8.start_rebinding();
+    for key in keys {
+        assert!(chip8.is_rebinding());
+        chip8.rebind_key(key);
+    }
+    assert!(!chip8.is_rebinding());
+    for (position, key) in keys.into_iter().enumerate() {
+        assert_eq!(chip8.keymap().keypad[KEYPAD_LAYOUT[position]], vec![key]);
+    }
+
+    let saved = KeymapFile::load(&path).unwrap().keymap_for("any").unwrap();
+    assert_eq!(saved.keypad, chip8.keymap().keypad);
+    fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,36 @@
+// ahead.ch8 recompiled to Rust by `rust-chip8-emulator recompile`. Each function is a basic block of the
+// ROM, translated for the quirks in PROGRAM; Chip8::set_recompiled(Some(&PROGRAM)) runs them in place
+// of the interpreter.
+
+use rust_chip8_emulator::chip8::Chip8;
+use rust_chip8_emulator::instruction::Instruction;
+use rust_chip8_emulator::quirks::Quirks;
+use rust_chip8_emulator::recompiler::{Block, Program};
+
+pub static PROGRAM: Program = Program {
+    sha1: "ab2f1575befdf383c1893c5b5629902c7d5fa39f",
+    quirks: Quirks { shift: true, memory_increment_by_x: false, memory_leave_i_unchanged: true, wrap: false, jump: false, vblank: false, logic: false },
+    blocks: &[
+        Block { start: 0x200, code: &[0x70, 0x01, 0xA2, 0x09, 0xF0, 0x55], run: block_200, draws: false },
+        Block { start: 0x206, code: &[0x82, 0x00, 0x71, 0x00, 0x12, 0x00], run: block_206, draws: false },
+    ],
+};
+
+fn block_200(c: &mut Chip8) {
+    // 200: 7001  ADD V0, 0x01
+    c.set_register(0x0, c.register(0x0).wrapping_add(0x01));
+    // 202: A209  LD I, 0x209
+    c.set_i_register(0x209);
+    // 204: F055  LD [I], V0
+    c.execute(Instruction::StoreRegisters(0x0));
+    c.set_pc(0x206);
+}
+
+fn block_206(c: &mut Chip8) {
+    // 206: 8200  LD V2, V0
+    c.set_register(0x2, c.register(0x0));
+    // 208: 7100  ADD V1, 0x00
+    c.set_register(0x1, c.register(0x1).wrapping_add(0x00));
+    // 20A: 1200  JP 0x200
+    c.set_pc(0x200);
+}
@@ -0,0 +1,526 @@
+// pong.ch8 recompiled to Rust by `rust-chip8-emulator recompile`. Each function is a basic block of the
+// ROM, translated for the quirks in PROGRAM; Chip8::set_recompiled(Some(&PROGRAM)) runs them in place
+// of the interpreter.
+
+use rust_chip8_emulator::chip8::Chip8;
+use rust_chip8_emulator::instruction::Instruction;
+use rust_chip8_emulator::quirks::Quirks;
+use rust_chip8_emulator::recompiler::{Block, Program};
+
+pub static PROGRAM: Program = Program {
+    sha1: "607c4f7f4e4dce9f99d96b3182bfe7e88bb090ee",
+    quirks: Quirks { shift: false, memory_increment_by_x: false, memory_leave_i_unchanged: false, wrap: false, jump: false, vblank: false, logic: false },
+    blocks: &[
+        Block { start: 0x200, code: &[0x6A, 0x02, 0x6B, 0x0C, 0x6C, 0x3F, 0x6D, 0x0C, 0xA2, 0xEA, 0xDA, 0xB6], run: block_200, draws: true },
+        Block { start: 0x20C, code: &[0xDC, 0xD6], run: block_20c, draws: true },
+        Block { start: 0x20E, code: &[0x6E, 0x00, 0x22, 0xD4], run: block_20e, draws: false },
+        Block { start: 0x212, code: &[0x66, 0x03, 0x68, 0x02], run: block_212, draws: false },
+        Block { start: 0x216, code: &[0x60, 0x60, 0xF0, 0x15], run: block_216, draws: false },
+        Block { start: 0x21A, code: &[0xF0, 0x07, 0x30, 0x00], run: block_21a, draws: false },
+        Block { start: 0x21E, code: &[0x12, 0x1A], run: block_21e, draws: false },
+        Block { start: 0x220, code: &[0xC7, 0x17, 0x77, 0x08, 0x69, 0xFF, 0xA2, 0xF0, 0xD6, 0x71], run: block_220, draws: true },
+        Block { start: 0x22A, code: &[0xA2, 0xEA, 0xDA, 0xB6], run: block_22a, draws: true },
+        Block { start: 0x22E, code: &[0xDC, 0xD6], run: block_22e, draws: true },
+        Block { start: 0x230, code: &[0x60, 0x01, 0xE0, 0xA1], run: block_230, draws: false },
+        Block { start: 0x234, code: &[0x7B, 0xFE], run: block_234, draws: false },
+        Block { start: 0x236, code: &[0x60, 0x04, 0xE0, 0xA1], run: block_236, draws: false },
+        Block { start: 0x23A, code: &[0x7B, 0x02], run: block_23a, draws: false },
+        Block { start: 0x23C, code: &[0x60, 0x1F, 0x8B, 0x02, 0xDA, 0xB6], run: block_23c, draws: true },
+        Block { start: 0x242, code: &[0x8D, 0x70, 0xC0, 0x0A, 0x7D, 0xFE, 0x40, 0x00], run: block_242, draws: false },
+        Block { start: 0x24A, code: &[0x7D, 0x02], run: block_24a, draws: false },
+        Block { start: 0x24C, code: &[0x60, 0x00, 0x60, 0x1F, 0x8D, 0x02, 0xDC, 0xD6], run: block_24c, draws: true },
+        Block { start: 0x254, code: &[0xA2, 0xF0, 0xD6, 0x71], run: block_254, draws: true },
+        Block { start: 0x258, code: &[0x86, 0x84, 0x87, 0x94, 0x60, 0x3F, 0x86, 0x02, 0x61, 0x1F, 0x87, 0x12, 0x46, 0x02], run: block_258, draws: false },
+        Block { start: 0x266, code: &[0x12, 0x78], run: block_266, draws: false },
+        Block { start: 0x268, code: &[0x46, 0x3F], run: block_268, draws: false },
+        Block { start: 0x26A, code: &[0x12, 0x82], run: block_26a, draws: false },
+        Block { start: 0x26C, code: &[0x47, 0x1F], run: block_26c, draws: false },
+        Block { start: 0x26E, code: &[0x69, 0xFF], run: block_26e, draws: false },
+        Block { start: 0x270, code: &[0x47, 0x00], run: block_270, draws: false },
+        Block { start: 0x272, code: &[0x69, 0x01], run: block_272, draws: false },
+        Block { start: 0x274, code: &[0xD6, 0x71], run: block_274, draws: true },
+        Block { start: 0x276, code: &[0x12, 0x2A], run: block_276, draws: false },
+        Block { start: 0x278, code: &[0x68, 0x02, 0x63, 0x01, 0x80, 0x70, 0x80, 0xB5, 0x12, 0x8A], run: block_278, draws: false },
+        Block { start: 0x282, code: &[0x68, 0xFE, 0x63, 0x0A, 0x80, 0x70, 0x80, 0xD5], run: block_282, draws: false },
+        Block { start: 0x28A, code: &[0x3F, 0x01], run: block_28a, draws: false },
+        Block { start: 0x28C, code: &[0x12, 0xA2], run: block_28c, draws: false },
+        Block { start: 0x28E, code: &[0x61, 0x02, 0x80, 0x15, 0x3F, 0x01], run: block_28e, draws: false },
+        Block { start: 0x294, code: &[0x12, 0xBA], run: block_294, draws: false },
+        Block { start: 0x296, code: &[0x80, 0x15, 0x3F, 0x01], run: block_296, draws: false },
+        Block { start: 0x29A, code: &[0x12, 0xC8], run: block_29a, draws: false },
+        Block { start: 0x29C, code: &[0x80, 0x15, 0x3F, 0x01], run: block_29c, draws: false },
+        Block { start: 0x2A0, code: &[0x12, 0xC2], run: block_2a0, draws: false },
+        Block { start: 0x2A2, code: &[0x60, 0x20, 0xF0, 0x18, 0x22, 0xD4], run: block_2a2, draws: false },
+        Block { start: 0x2A8, code: &[0x8E, 0x34, 0x22, 0xD4], run: block_2a8, draws: false },
+        Block { start: 0x2AC, code: &[0x66, 0x3E, 0x33, 0x01], run: block_2ac, draws: false },
+        Block { start: 0x2B0, code: &[0x66, 0x03], run: block_2b0, draws: false },
+        Block { start: 0x2B2, code: &[0x68, 0xFE, 0x33, 0x01], run: block_2b2, draws: false },
+        Block { start: 0x2B6, code: &[0x68, 0x02], run: block_2b6, draws: false },
+        Block { start: 0x2B8, code: &[0x12, 0x16], run: block_2b8, draws: false },
+        Block { start: 0x2BA, code: &[0x79, 0xFF, 0x49, 0xFE], run: block_2ba, draws: false },
+        Block { start: 0x2BE, code: &[0x69, 0xFF], run: block_2be, draws: false },
+        Block { start: 0x2C0, code: &[0x12, 0xC8], run: block_2c0, draws: false },
+        Block { start: 0x2C2, code: &[0x79, 0x01, 0x49, 0x02], run: block_2c2, draws: false },
+        Block { start: 0x2C6, code: &[0x69, 0x01], run: block_2c6, draws: false },
+        Block { start: 0x2C8, code: &[0x60, 0x04, 0xF0, 0x18, 0x76, 0x01, 0x46, 0x40], run: block_2c8, draws: false },
+        Block { start: 0x2D0, code: &[0x76, 0xFE], run: block_2d0, draws: false },
+        Block { start: 0x2D2, code: &[0x12, 0x6C], run: block_2d2, draws: false },
+        Block { start: 0x2D4, code: &[0xA2, 0xF2, 0xFE, 0x33], run: block_2d4, draws: false },
+        Block { start: 0x2D8, code: &[0xF2, 0x65, 0xF1, 0x29, 0x64, 0x14, 0x65, 0x00, 0xD4, 0x55], run: block_2d8, draws: true },
+        Block { start: 0x2E2, code: &[0x74, 0x15, 0xF2, 0x29, 0xD4, 0x55], run: block_2e2, draws: true },
+        Block { start: 0x2E8, code: &[0x00, 0xEE], run: block_2e8, draws: false },
+    ],
+};
+
+fn block_200(c: &mut Chip8) {
+    // 200: 6A02  LD VA, 0x02
+    c.set_register(0xA, 0x02);
+    // 202: 6B0C  LD VB, 0x0C
+    c.set_register(0xB, 0x0C);
+    // 204: 6C3F  LD VC, 0x3F
+    c.set_register(0xC, 0x3F);
+    // 206: 6D0C  LD VD, 0x0C
+    c.set_register(0xD, 0x0C);
+    // 208: A2EA  LD I, 0x2EA
+    c.set_i_register(0x2EA);
+    // 20A: DAB6  DRW VA, VB, 6
+    c.execute(Instruction::Draw(0xA, 0xB, 6));
+    c.set_pc(0x20C);
+}
+
+fn block_20c(c: &mut Chip8) {
+    // 20C: DCD6  DRW VC, VD, 6
+    c.execute(Instruction::Draw(0xC, 0xD, 6));
+    c.set_pc(0x20E);
+}
+
+fn block_20e(c: &mut Chip8) {
+    // 20E: 6E00  LD VE, 0x00
+    c.set_register(0xE, 0x00);
+    // 210: 22D4  CALL 0x2D4
+    c.set_pc(0x212);
+    c.execute(Instruction::Call(0x2D4));
+}
+
+fn block_212(c: &mut Chip8) {
+    // 212: 6603  LD V6, 0x03
+    c.set_register(0x6, 0x03);
+    // 214: 6802  LD V8, 0x02
+    c.set_register(0x8, 0x02);
+    c.set_pc(0x216);
+}
+
+fn block_216(c: &mut Chip8) {
+    // 216: 6060  LD V0, 0x60
+    c.set_register(0x0, 0x60);
+    // 218: F015  LD DT, V0
+    c.execute(Instruction::SetDelay(0x0));
+    c.set_pc(0x21A);
+}
+
+fn block_21a(c: &mut Chip8) {
+    // 21A: F007  LD V0, DT
+    c.execute(Instruction::ReadDelay(0x0));
+    // 21C: 3000  SE V0, 0x00
+    c.set_pc(if c.register(0x0) == 0x00 { 0x220 } else { 0x21E });
+}
+
+fn block_21e(c: &mut Chip8) {
+    // 21E: 121A  JP 0x21A
+    c.set_pc(0x21A);
+}
+
+fn block_220(c: &mut Chip8) {
+    // 220: C717  RND V7, 0x17
+    c.execute(Instruction::Random(0x7, 0x17));
+    // 222: 7708  ADD V7, 0x08
+    c.set_register(0x7, c.register(0x7).wrapping_add(0x08));
+    // 224: 69FF  LD V9, 0xFF
+    c.set_register(0x9, 0xFF);
+    // 226: A2F0  LD I, 0x2F0
+    c.set_i_register(0x2F0);
+    // 228: D671  DRW V6, V7, 1
+    c.execute(Instruction::Draw(0x6, 0x7, 1));
+    c.set_pc(0x22A);
+}
+
+fn block_22a(c: &mut Chip8) {
+    // 22A: A2EA  LD I, 0x2EA
+    c.set_i_register(0x2EA);
+    // 22C: DAB6  DRW VA, VB, 6
+    c.execute(Instruction::Draw(0xA, 0xB, 6));
+    c.set_pc(0x22E);
+}
+
+fn block_22e(c: &mut Chip8) {
+    // 22E: DCD6  DRW VC, VD, 6
+    c.execute(Instruction::Draw(0xC, 0xD, 6));
+    c.set_pc(0x230);
+}
+
+fn block_230(c: &mut Chip8) {
+    // 230: 6001  LD V0, 0x01
+    c.set_register(0x0, 0x01);
+    // 232: E0A1  SKNP V0
+    c.set_pc(if !c.keypad().is_pressed(c.register(0x0)) { 0x236 } else { 0x234 });
+}
+
+fn block_234(c: &mut Chip8) {
+    // 234: 7BFE  ADD VB, 0xFE
+    c.set_register(0xB, c.register(0xB).wrapping_add(0xFE));
+    c.set_pc(0x236);
+}
+
+fn block_236(c: &mut Chip8) {
+    // 236: 6004  LD V0, 0x04
+    c.set_register(0x0, 0x04);
+    // 238: E0A1  SKNP V0
+    c.set_pc(if !c.keypad().is_pressed(c.register(0x0)) { 0x23C } else { 0x23A });
+}
+
+fn block_23a(c: &mut Chip8) {
+    // 23A: 7B02  ADD VB, 0x02
+    c.set_register(0xB, c.register(0xB).wrapping_add(0x02));
+    c.set_pc(0x23C);
+}
+
+fn block_23c(c: &mut Chip8) {
+    // 23C: 601F  LD V0, 0x1F
+    c.set_register(0x0, 0x1F);
+    // 23E: 8B02  AND VB, V0
+    c.set_register(0xB, c.register(0xB) & c.register(0x0));
+    // 240: DAB6  DRW VA, VB, 6
+    c.execute(Instruction::Draw(0xA, 0xB, 6));
+    c.set_pc(0x242);
+}
+
+fn block_242(c: &mut Chip8) {
+    // 242: 8D70  LD VD, V7
+    c.set_register(0xD, c.register(0x7));
+    // 244: C00A  RND V0, 0x0A
+    c.execute(Instruction::Random(0x0, 0x0A));
+    // 246: 7DFE  ADD VD, 0xFE
+    c.set_register(0xD, c.register(0xD).wrapping_add(0xFE));
+    // 248: 4000  SNE V0, 0x00
+    c.set_pc(if c.register(0x0) != 0x00 { 0x24C } else { 0x24A });
+}
+
+fn block_24a(c: &mut Chip8) {
+    // 24A: 7D02  ADD VD, 0x02
+    c.set_register(0xD, c.register(0xD).wrapping_add(0x02));
+    c.set_pc(0x24C);
+}
+
+fn block_24c(c: &mut Chip8) {
+    // 24C: 6000  LD V0, 0x00
+    c.set_register(0x0, 0x00);
+    // 24E: 601F  LD V0, 0x1F
+    c.set_register(0x0, 0x1F);
+    // 250: 8D02  AND VD, V0
+    c.set_register(0xD, c.register(0xD) & c.register(0x0));
+    // 252: DCD6  DRW VC, VD, 6
+    c.execute(Instruction::Draw(0xC, 0xD, 6));
+    c.set_pc(0x254);
+}
+
+fn block_254(c: &mut Chip8) {
+    // 254: A2F0  LD I, 0x2F0
+    c.set_i_register(0x2F0);
+    // 256: D671  DRW V6, V7, 1
+    c.execute(Instruction::Draw(0x6, 0x7, 1));
+    c.set_pc(0x258);
+}
+
+fn block_258(c: &mut Chip8) {
+    // 258: 8684  ADD V6, V8
+    let (sum, carry) = c.register(0x6).overflowing_add(c.register(0x8));
+    c.set_register(0x6, sum);
+    c.set_register(0xF, carry as u8);
+    // 25A: 8794  ADD V7, V9
+    let (sum, carry) = c.register(0x7).overflowing_add(c.register(0x9));
+    c.set_register(0x7, sum);
+    c.set_register(0xF, carry as u8);
+    // 25C: 603F  LD V0, 0x3F
+    c.set_register(0x0, 0x3F);
+    // 25E: 8602  AND V6, V0
+    c.set_register(0x6, c.register(0x6) & c.register(0x0));
+    // 260: 611F  LD V1, 0x1F
+    c.set_register(0x1, 0x1F);
+    // 262: 8712  AND V7, V1
+    c.set_register(0x7, c.register(0x7) & c.register(0x1));
+    // 264: 4602  SNE V6, 0x02
+    c.set_pc(if c.register(0x6) != 0x02 { 0x268 } else { 0x266 });
+}
+
+fn block_266(c: &mut Chip8) {
+    // 266: 1278  JP 0x278
+    c.set_pc(0x278);
+}
+
+fn block_268(c: &mut Chip8) {
+    // 268: 463F  SNE V6, 0x3F
+    c.set_pc(if c.register(0x6) != 0x3F { 0x26C } else { 0x26A });
+}
+
+fn block_26a(c: &mut Chip8) {
+    // 26A: 1282  JP 0x282
+    c.set_pc(0x282);
+}
+
+fn block_26c(c: &mut Chip8) {
+    // 26C: 471F  SNE V7, 0x1F
+    c.set_pc(if c.register(0x7) != 0x1F { 0x270 } else { 0x26E });
+}
+
+fn block_26e(c: &mut Chip8) {
+    // 26E: 69FF  LD V9, 0xFF
+    c.set_register(0x9, 0xFF);
+    c.set_pc(0x270);
+}
+
+fn block_270(c: &mut Chip8) {
+    // 270: 4700  SNE V7, 0x00
+    c.set_pc(if c.register(0x7) != 0x00 { 0x274 } else { 0x272 });
+}
+
+fn block_272(c: &mut Chip8) {
+    // 272: 6901  LD V9, 0x01
+    c.set_register(0x9, 0x01);
+    c.set_pc(0x274);
+}
+
+fn block_274(c: &mut Chip8) {
+    // 274: D671  DRW V6, V7, 1
+    c.execute(Instruction::Draw(0x6, 0x7, 1));
+    c.set_pc(0x276);
+}
+
+fn block_276(c: &mut Chip8) {
+    // 276: 122A  JP 0x22A
+    c.set_pc(0x22A);
+}
+
+fn block_278(c: &mut Chip8) {
+    // 278: 6802  LD V8, 0x02
+    c.set_register(0x8, 0x02);
+    // 27A: 6301  LD V3, 0x01
+    c.set_register(0x3, 0x01);
+    // 27C: 8070  LD V0, V7
+    c.set_register(0x0, c.register(0x7));
+    // 27E: 80B5  SUB V0, VB
+    let (difference, borrow) = c.register(0x0).overflowing_sub(c.register(0xB));
+    c.set_register(0x0, difference);
+    c.set_register(0xF, !borrow as u8);
+    // 280: 128A  JP 0x28A
+    c.set_pc(0x28A);
+}
+
+fn block_282(c: &mut Chip8) {
+    // 282: 68FE  LD V8, 0xFE
+    c.set_register(0x8, 0xFE);
+    // 284: 630A  LD V3, 0x0A
+    c.set_register(0x3, 0x0A);
+    // 286: 8070  LD V0, V7
+    c.set_register(0x0, c.register(0x7));
+    // 288: 80D5  SUB V0, VD
+    let (difference, borrow) = c.register(0x0).overflowing_sub(c.register(0xD));
+    c.set_register(0x0, difference);
+    c.set_register(0xF, !borrow as u8);
+    c.set_pc(0x28A);
+}
+
+fn block_28a(c: &mut Chip8) {
+    // 28A: 3F01  SE VF, 0x01
+    c.set_pc(if c.register(0xF) == 0x01 { 0x28E } else { 0x28C });
+}
+
+fn block_28c(c: &mut Chip8) {
+    // 28C: 12A2  JP 0x2A2
+    c.set_pc(0x2A2);
+}
+
+fn block_28e(c: &mut Chip8) {
+    // 28E: 6102  LD V1, 0x02
+    c.set_register(0x1, 0x02);
+    // 290: 8015  SUB V0, V1
+    let (difference, borrow) = c.register(0x0).overflowing_sub(c.register(0x1));
+    c.set_register(0x0, difference);
+    c.set_register(0xF, !borrow as u8);
+    // 292: 3F01  SE VF, 0x01
+    c.set_pc(if c.register(0xF) == 0x01 { 0x296 } else { 0x294 });
+}
+
+fn block_294(c: &mut Chip8) {
+    // 294: 12BA  JP 0x2BA
+    c.set_pc(0x2BA);
+}
+
+fn block_296(c: &mut Chip8) {
+    // 296: 8015  SUB V0, V1
+    let (difference, borrow) = c.register(0x0).overflowing_sub(c.register(0x1));
+    c.set_register(0x0, difference);
+    c.set_register(0xF, !borrow as u8);
+    // 298: 3F01  SE VF, 0x01
+    c.set_pc(if c.register(0xF) == 0x01 { 0x29C } else { 0x29A });
+}
+
+fn block_29a(c: &mut Chip8) {
+    // 29A: 12C8  JP 0x2C8
+    c.set_pc(0x2C8);
+}
+
+fn block_29c(c: &mut Chip8) {
+    // 29C: 8015  SUB V0, V1
+    let (difference, borrow) = c.register(0x0).overflowing_sub(c.register(0x1));
+    c.set_register(0x0, difference);
+    c.set_register(0xF, !borrow as u8);
+    // 29E: 3F01  SE VF, 0x01
+    c.set_pc(if c.register(0xF) == 0x01 { 0x2A2 } else { 0x2A0 });
+}
+
+fn block_2a0(c: &mut Chip8) {
+    // 2A0: 12C2  JP 0x2C2
+    c.set_pc(0x2C2);
+}
+
+fn block_2a2(c: &mut Chip8) {
+    // 2A2: 6020  LD V0, 0x20
+    c.set_register(0x0, 0x20);
+    // 2A4: F018  LD ST, V0
+    c.execute(Instruction::SetSound(0x0));
+    // 2A6: 22D4  CALL 0x2D4
+    c.set_pc(0x2A8);
+    c.execute(Instruction::Call(0x2D4));
+}
+
+fn block_2a8(c: &mut Chip8) {
+    // 2A8: 8E34  ADD VE, V3
+    let (sum, carry) = c.register(0xE).overflowing_add(c.register(0x3));
+    c.set_register(0xE, sum);
+    c.set_register(0xF, carry as u8);
+    // 2AA: 22D4  CALL 0x2D4
+    c.set_pc(0x2AC);
+    c.execute(Instruction::Call(0x2D4));
+}
+
+fn block_2ac(c: &mut Chip8) {
+    // 2AC: 663E  LD V6, 0x3E
+    c.set_register(0x6, 0x3E);
+    // 2AE: 3301  SE V3, 0x01
+    c.set_pc(if c.register(0x3) == 0x01 { 0x2B2 } else { 0x2B0 });
+}
+
+fn block_2b0(c: &mut Chip8) {
+    // 2B0: 6603  LD V6, 0x03
+    c.set_register(0x6, 0x03);
+    c.set_pc(0x2B2);
+}
+
+fn block_2b2(c: &mut Chip8) {
+    // 2B2: 68FE  LD V8, 0xFE
+    c.set_register(0x8, 0xFE);
+    // 2B4: 3301  SE V3, 0x01
+    c.set_pc(if c.register(0x3) == 0x01 { 0x2B8 } else { 0x2B6 });
+}
+
+fn block_2b6(c: &mut Chip8) {
+    // 2B6: 6802  LD V8, 0x02
+    c.set_register(0x8, 0x02);
+    c.set_pc(0x2B8);
+}
+
+fn block_2b8(c: &mut Chip8) {
+    // 2B8: 1216  JP 0x216
+    c.set_pc(0x216);
+}
+
+fn block_2ba(c: &mut Chip8) {
+    // 2BA: 79FF  ADD V9, 0xFF
+    c.set_register(0x9, c.register(0x9).wrapping_add(0xFF));
+    // 2BC: 49FE  SNE V9, 0xFE
+    c.set_pc(if c.register(0x9) != 0xFE { 0x2C0 } else { 0x2BE });
+}
+
+fn block_2be(c: &mut Chip8) {
+    // 2BE: 69FF  LD V9, 0xFF
+    c.set_register(0x9, 0xFF);
+    c.set_pc(0x2C0);
+}
+
+fn block_2c0(c: &mut Chip8) {
+    // 2C0: 12C8  JP 0x2C8
+    c.set_pc(0x2C8);
+}
+
+fn block_2c2(c: &mut Chip8) {
+    // 2C2: 7901  ADD V9, 0x01
+    c.set_register(0x9, c.register(0x9).wrapping_add(0x01));
+    // 2C4: 4902  SNE V9, 0x02
+    c.set_pc(if c.register(0x9) != 0x02 { 0x2C8 } else { 0x2C6 });
+}
+
+fn block_2c6(c: &mut Chip8) {
+    // 2C6: 6901  LD V9, 0x01
+    c.set_register(0x9, 0x01);
+    c.set_pc(0x2C8);
+}
+
+fn block_2c8(c: &mut Chip8) {
+    // 2C8: 6004  LD V0, 0x04
+    c.set_register(0x0, 0x04);
+    // 2CA: F018  LD ST, V0
+    c.execute(Instruction::SetSound(0x0));
+    // 2CC: 7601  ADD V6, 0x01
+    c.set_register(0x6, c.register(0x6).wrapping_add(0x01));
+    // 2CE: 4640  SNE V6, 0x40
+    c.set_pc(if c.register(0x6) != 0x40 { 0x2D2 } else { 0x2D0 });
+}
+
+fn block_2d0(c: &mut Chip8) {
+    // 2D0: 76FE  ADD V6, 0xFE
+    c.set_register(0x6, c.register(0x6).wrapping_add(0xFE));
+    c.set_pc(0x2D2);
+}
+
+fn block_2d2(c: &mut Chip8) {
+    // 2D2: 126C  JP 0x26C
+    c.set_pc(0x26C);
+}
+
+fn block_2d4(c: &mut Chip8) {
+    // 2D4: A2F2  LD I, 0x2F2
+    c.set_i_register(0x2F2);
+    // 2D6: FE33  LD B, VE
+    c.execute(Instruction::Decimal(0xE));
+    c.set_pc(0x2D8);
+}
+
+fn block_2d8(c: &mut Chip8) {
+    // 2D8: F265  LD V2, [I]
+    c.execute(Instruction::LoadRegisters(0x2));
+    // 2DA: F129  LD F, V1
+    c.set_i_register(0x050 + (c.register(0x1) as u16 & 0xF) * 5);
+    // 2DC: 6414  LD V4, 0x14
+    c.set_register(0x4, 0x14);
+    // 2DE: 6500  LD V5, 0x00
+    c.set_register(0x5, 0x00);
+    // 2E0: D455  DRW V4, V5, 5
+    c.execute(Instruction::Draw(0x4, 0x5, 5));
+    c.set_pc(0x2E2);
+}
+
+fn block_2e2(c: &mut Chip8) {
+    // 2E2: 7415  ADD V4, 0x15
+    c.set_register(0x4, c.register(0x4).wrapping_add(0x15));
+    // 2E4: F229  LD F, V2
+    c.set_i_register(0x050 + (c.register(0x2) as u16 & 0xF) * 5);
+    // 2E6: D455  DRW V4, V5, 5
+    c.execute(Instruction::Draw(0x4, 0x5, 5));
+    c.set_pc(0x2E8);
+}
+
+fn block_2e8(c: &mut Chip8) {
+    // 2E8: 00EE  RET
+    c.set_pc(0x2EA);
+    c.execute(Instruction::Return);
+}
@@ -0,0 +1,40 @@
+// rewrite.ch8 recompiled to Rust by `rust-chip8-emulator recompile`. Each function is a basic block of the
+// ROM, translated for the quirks in PROGRAM; Chip8::set_recompiled(Some(&PROGRAM)) runs them in place
+// of the interpreter.
+
+use rust_chip8_emulator::chip8::Chip8;
+use rust_chip8_emulator::instruction::Instruction;
+use rust_chip8_emulator::quirks::Quirks;
+use rust_chip8_emulator::recompiler::{Block, Program};
+
+pub static PROGRAM: Program = Program {
+    sha1: "b9e5a8174f96dd3142bc8c95b70aa038b4aba876",
+    quirks: Quirks { shift: true, memory_increment_by_x: false, memory_leave_i_unchanged: true, wrap: false, jump: false, vblank: false, logic: false },
+    blocks: &[
+        Block { start: 0x200, code: &[0x70, 0x01, 0x71, 0x00, 0xA2, 0x03, 0xF0, 0x55], run: block_200, draws: false },
+        Block { start: 0x208, code: &[0x83, 0x00, 0x60, 0x00, 0x62, 0x00, 0xB2, 0x12], run: block_208, draws: false },
+    ],
+};
+
+fn block_200(c: &mut Chip8) {
+    // 200: 7001  ADD V0, 0x01
+    c.set_register(0x0, c.register(0x0).wrapping_add(0x01));
+    // 202: 7100  ADD V1, 0x00
+    c.set_register(0x1, c.register(0x1).wrapping_add(0x00));
+    // 204: A203  LD I, 0x203
+    c.set_i_register(0x203);
+    // 206: F055  LD [I], V0
+    c.execute(Instruction::StoreRegisters(0x0));
+    c.set_pc(0x208);
+}
+
+fn block_208(c: &mut Chip8) {
+    // 208: 8300  LD V3, V0
+    c.set_register(0x3, c.register(0x0));
+    // 20A: 6000  LD V0, 0x00
+    c.set_register(0x0, 0x00);
+    // 20C: 6200  LD V2, 0x00
+    c.set_register(0x2, 0x00);
+    // 20E: B212  JP V0, 0x212
+    c.set_pc(0x212 + c.register(0x0) as u16);
+}
@@ -0,0 +1,150 @@
+// Recompiled ROMs have to run exactly like the interpreter: the modules in tests/recompiled are checked
+// against what translate produces now, then run frame by frame next to the interpreter. Run with
+// UPDATE_SNAPSHOTS=1 to rewrite the modules after an intended change to the translation, then review
+// the diff.
+
+#[path = "recompiled/ahead.rs"]
+mod ahead;
+#[path = "recompiled/pong.rs"]
+mod pong;
+#[path = "recompiled/rewrite.rs"]
+mod rewrite;
+
+use rust_chip8_emulator::chip8::Chip8;
+use rust_chip8_emulator::quirks::Quirks;
+use rust_chip8_emulator::recompiler::{self, Program};
+use std::env;
+use std::fs;
+use std::path::Path;
+
+const FRAMES: usize = 3000;
+
+// a loop that rewrites its own code and takes a computed jump every pass: FX55 writes V0 over the NN of
+// the 7XNN at 0x202, so V1 sums the pass counts, and BNNN with V0 cleared lands on 0x212
+const REWRITE: [u16; 11] = [0x7001, 0x7100, 0xA203, 0xF055, 0x8300, 0x6000, 0x6200, 0xB212, 0x1210, 0x8030, 0x1200];
+
+// a loop that rewrites the code just ahead of it: FX55 writes V0 over the NN of the 7XNN at 0x208, in the
+// same stretch of straight-line code, so V1 sums the pass counts
+const AHEAD: [u16; 6] = [0x7001, 0xA209, 0xF055, 0x8200, 0x7100, 0x1200];
+
+fn pong_rom() -> Vec<u8> {
+    fs::read(Path::new(env!("CARGO_MANIFEST_DIR")).join("roms").join("pong.ch8")).unwrap()
+}
+
+fn rewrite_rom() -> Vec<u8> {
+    REWRITE.iter().flat_map(|opcode| opcode.to_be_bytes()).collect()
+}
+
+fn ahead_rom() -> Vec<u8> {
+    AHEAD.iter().flat_map(|opcode| opcode.to_be_bytes()).collect()
+}
+
+fn machines(rom: &[u8], program: &'static Program) -> (Chip8, Chip8) {
+    let machine = |recompiled: bool| {
+        let mut chip8 = Chip8::headless();
+        chip8.load_rom_bytes(rom, "recompiled").unwrap();
+        chip8.set_seed(0);
+        chip8.set_speed(30);
+        chip8.reset();
+        chip8.set_recompiled(recompiled.then_some(program));
+        chip8
+    };
+    (machine(false), machine(true))
+}
+
+// every way the recompiled machine's state differs from the interpreter's, empty when they agree
+fn differences(interpreter: &Chip8, recompiled: &Chip8) -> Vec<String> {
+    let values = [
+        ("the registers", format!("{:02X?}", interpreter.registers()), format!("{:02X?}", recompiled.registers())),
+        ("I", format!("{:03X}", interpreter.i_register()), format!("{:03X}", recompiled.i_register())),
+        ("pc", format!("{:03X}", interpreter.pc()), format!("{:03X}", recompiled.pc())),
+        ("the cycle", interpreter.cycle().to_string(), recompiled.cycle().to_string()),
+        ("the timers", format!("{} {}", interpreter.delay_timer(), interpreter.sound_timer()), format!("{} {}", recompiled.delay_timer(), recompiled.sound_timer())),
+        ("the stack", format!("{:03X?}", interpreter.stack()), format!("{:03X?}", recompiled.stack())),
+        ("the fault", format!("{:?}", interpreter.fault()), format!("{:?}", recompiled.fault())),
+    ];
+    let mut differences: Vec<String> = values
+        .into_iter()
+        .filter(|(_, expected, value)| expected != value)
+        .map(|(name, expected, value)| format!("{} are {}, expected {}", name, value, expected))
+        .collect();
+    if interpreter.memory() != recompiled.memory() {
+        differences.push("memory differs".to_string());
+    }
+    if interpreter.display().frame_buffer != recompiled.display().frame_buffer {
+        differences.push("the screen differs".to_string());
+    }
+    differences
+}
+
+// runs both machines a frame at a time, pressing keys from the pattern, and panics where they first diverge
+fn compare(interpreter: &mut Chip8, recompiled: &mut Chip8, frames: usize, keys: &[u8]) {
+    for frame in 0..frames {
+        let key = keys[frame % keys.len()];
+        for chip8 in [&mut *interpreter, &mut *recompiled] {
+            if key < 16 {
+                chip8.press_key(key);
+            } else {
+                (0..16).for_each(|key| chip8.release_key(key));
+            }
+            chip8.step_frame();
+        }
+        let differences = differences(interpreter, recompiled);
+        assert!(differences.is_empty(), "diverged on frame {}:\n  {}", frame, differences.join("\n  "));
+    }
+}
+
+fn check_module(rom: &[u8], file: &str) {
+    let mut chip8 = Chip8::headless();
+    chip8.load_rom_bytes(rom, "recompiled").unwrap();
+    let source = recompiler::translate(rom, file.replace(".rs", ".ch8").as_str(), chip8.rom_sha1(), chip8.quirks());
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("recompiled").join(file);
+    if env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        fs::write(&path, source).unwrap();
+        return;
+    }
+    let expected = fs::read_to_string(&path).unwrap_or_default();
+    assert!(source == expected, "{} is out of date, run with UPDATE_SNAPSHOTS=1 to rewrite it", path.display());
+}
+
+#[test]
+fn modules_match_the_translation() {
+    check_module(&pong_rom(), "pong.rs");
+    check_module(&rewrite_rom(), "rewrite.rs");
+    check_module(&ahead_rom(), "ahead.rs");
+}
+
+#[test]
+fn recompiled_pong_runs_like_the_interpreter() {
+    let (mut interpreter, mut recompiled) = machines(&pong_rom(), &pong::PROGRAM);
+    //holds both paddles' keys for a while each so the game gets played
+    let keys: Vec<u8> = [1, 4, 16, 12, 13, 16].iter().flat_map(|&key| [key; 20]).collect();
+    compare(&mut interpreter, &mut recompiled, FRAMES, &keys);
+}
+
+#[test]
+fn rewritten_code_and_computed_jumps_fall_back_to_the_interpreter() {
+    let (mut interpreter, mut recompiled) = machines(&rewrite_rom(), &rewrite::PROGRAM);
+    compare(&mut interpreter, &mut recompiled, 100, &[16]);
+    //the block at 0x200 has been written over, and the translation never follows BNNN to 0x212
+    assert!(rewrite::PROGRAM.block(0x200, recompiled.memory()).is_none());
+    assert!(rewrite::PROGRAM.block(0x212, recompiled.memory()).is_none());
+}
+
+#[test]
+fn code_written_ahead_in_a_block_falls_back_to_the_interpreter() {
+    let (mut interpreter, mut recompiled) = machines(&ahead_rom(), &ahead::PROGRAM);
+    compare(&mut interpreter, &mut recompiled, 100, &[16]);
+    //the write ends the block at 0x200, which still runs, and the rest after it has been written over
+    assert!(ahead::PROGRAM.block(0x200, recompiled.memory()).is_some());
+    assert!(ahead::PROGRAM.block(0x206, recompiled.memory()).is_none());
+}
+
+#[test]
+fn blocks_only_run_with_the_quirks_they_were_translated_for() {
+    let (mut interpreter, mut recompiled) = machines(&pong_rom(), &pong::PROGRAM);
+    for chip8 in [&mut interpreter, &mut recompiled] {
+        chip8.set_quirks(Quirks::preset("schip").unwrap());
+    }
+    compare(&mut interpreter, &mut recompiled, 600, &[16]);
+}
@@ -0,0 +1,221 @@
+// A specification interpreter, written from the opcode table rather than from execute_opcode, so the two
+// can be run side by side. It favors being obviously right over being fast: every opcode is matched in
+// full, one arm each, and anything else does nothing.
+//
+// Keys are fixed for a whole run, so FX0A, which needs a key to be pressed and released while it waits,
+// always blocks.
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rust_chip8_emulator::quirks::Quirks;
+
+pub const MEMORY_SIZE: usize = 4096;
+pub const STACK_SIZE: usize = 16;
+pub const WIDTH: usize = 64;
+pub const HEIGHT: usize = 32;
+
+#[derive(Clone)]
+pub struct Reference {
+    pub memory: [u8; MEMORY_SIZE],
+    pub v: [u8; 16],
+    pub i: u16,
+    pub pc: u16,
+    pub stack: Vec<u16>,
+    pub delay_timer: u8,
+    pub sound_timer: u8,
+    pub screen: [[bool; WIDTH]; HEIGHT],
+    pub keys: [bool; 16],
+    pub flags: [u8; 16],
+    pub fault: Option<&'static str>, // the machine stops on a stack fault until it is reset
+    pub quirks: Quirks,
+    rng: StdRng,
+}
+
+impl Reference {
+    pub fn new(memory: [u8; MEMORY_SIZE], quirks: Quirks, seed: u64) -> Self {
+        Reference {
+            memory,
+            v: [0; 16],
+            i: 0,
+            pc: 0x200,
+            stack: Vec::new(),
+            delay_timer: 0,
+            sound_timer: 0,
+            screen: [[false; WIDTH]; HEIGHT],
+            keys: [false; 16],
+            flags: [0; 16],
+            fault: None,
+            quirks,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    fn read(&self, address: u16) -> u8 {
+        self.memory[address as usize % MEMORY_SIZE]
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        self.memory[address as usize % MEMORY_SIZE] = value;
+    }
+
+    // addresses pc can hold wrap at the end of memory
+    fn advance(&mut self, by: i32) {
+        self.pc = (self.pc as i32 + by).rem_euclid(MEMORY_SIZE as i32) as u16;
+    }
+
+    pub fn opcode(&self) -> u16 {
+        u16::from_be_bytes([self.read(self.pc), self.read(self.pc.wrapping_add(1))])
+    }
+
+    pub fn step(&mut self) {
+        if self.fault.is_some() {
+            return;
+        }
+        let opcode = self.opcode();
+        self.advance(2);
+
+        let x = (opcode >> 8 & 0xF) as usize;
+        let y = (opcode >> 4 & 0xF) as usize;
+        let n = opcode & 0xF;
+        let nn = (opcode & 0xFF) as u8;
+        let nnn = opcode & 0xFFF;
+        let (vx, vy) = (self.v[x], self.v[y]);
+
+        match (opcode >> 12, n, nn) {
+            (0x0, _, _) if opcode == 0x00E0 => self.screen = [[false; WIDTH]; HEIGHT],
+            (0x0, _, _) if opcode == 0x00EE => match self.stack.pop() {
+                Some(address) => self.pc = address,
+                None => self.stop("stack underflow"),
+            },
+            (0x1, _, _) => self.pc = nnn,
+            (0x2, _, _) => {
+                if self.stack.len() == STACK_SIZE {
+                    self.stop("stack overflow");
+                } else {
+                    self.stack.push(self.pc);
+                    self.pc = nnn;
+                }
+            }
+            (0x3, _, _) => self.skip_if(vx == nn),
+            (0x4, _, _) => self.skip_if(vx != nn),
+            (0x5, 0x0, _) => self.skip_if(vx == vy),
+            (0x6, _, _) => self.v[x] = nn,
+            (0x7, _, _) => self.v[x] = vx.wrapping_add(nn),
+            (0x8, 0x0, _) => self.v[x] = vy,
+            (0x8, 0x1, _) => self.logic(x, vx | vy),
+            (0x8, 0x2, _) => self.logic(x, vx & vy),
+            (0x8, 0x3, _) => self.logic(x, vx ^ vy),
+            (0x8, 0x4, _) => self.arithmetic(x, vx as u16 + vy as u16 > 0xFF, vx.wrapping_add(vy)),
+            (0x8, 0x5, _) => self.arithmetic(x, vx >= vy, vx.wrapping_sub(vy)),
+            (0x8, 0x6, _) => {
+                let value = if self.quirks.shift { vx } else { vy };
+                self.arithmetic(x, value & 1 == 1, value >> 1);
+            }
+            (0x8, 0x7, _) => self.arithmetic(x, vy >= vx, vy.wrapping_sub(vx)),
+            (0x8, 0xE, _) => {
+                let value = if self.quirks.shift { vx } else { vy };
+                self.arithmetic(x, value & 0x80 == 0x80, value << 1);
+            }
+            (0x9, 0x0, _) => self.skip_if(vx != vy),
+            (0xA, _, _) => self.i = nnn,
+            (0xB, _, _) => {
+                let offset = if self.quirks.jump { vx } else { self.v[0] };
+                self.pc = (nnn + offset as u16) % MEMORY_SIZE as u16;
+            }
+            (0xC, _, _) => self.v[x] = self.rng.random::<u8>() & nn,
+            (0xD, _, _) => self.draw(vx as usize % WIDTH, vy as usize % HEIGHT, n),
+            (0xE, _, 0x9E) => self.skip_if(self.keys[vx as usize % 16]),
+            (0xE, _, 0xA1) => self.skip_if(!self.keys[vx as usize % 16]),
+            (0xF, _, 0x07) => self.v[x] = self.delay_timer,
+            (0xF, _, 0x0A) => self.advance(-2),
+            (0xF, _, 0x15) => self.delay_timer = vx,
+            (0xF, _, 0x18) => self.sound_timer = vx,
+            (0xF, _, 0x1E) => self.i = self.i.wrapping_add(vx as u16),
+            (0xF, _, 0x29) => self.i = 0x050 + (vx as u16 & 0xF) * 5,
+            (0xF, _, 0x33) => {
+                self.write(self.i, vx / 100);
+                self.write(self.i.wrapping_add(1), vx / 10 % 10);
+                self.write(self.i.wrapping_add(2), vx % 10);
+            }
+            (0xF, _, 0x55) => {
+                for register in 0..=x {
+                    self.write(self.i.wrapping_add(register as u16), self.v[register]);
+                }
+                self.advance_i(x);
+            }
+            (0xF, _, 0x65) => {
+                for register in 0..=x {
+                    self.v[register] = self.read(self.i.wrapping_add(register as u16));
+                }
+                self.advance_i(x);
+            }
+            (0xF, _, 0x75) => self.flags[..=x].copy_from_slice(&self.v[..=x]),
+            (0xF, _, 0x85) => {
+                let flags = self.flags;
+                self.v[..=x].copy_from_slice(&flags[..=x]);
+            }
+            _ => {}
+        }
+    }
+
+    fn stop(&mut self, fault: &'static str) {
+        self.advance(-2);
+        self.fault = Some(fault);
+    }
+
+    fn skip_if(&mut self, condition: bool) {
+        if condition {
+            self.advance(2);
+        }
+    }
+
+    fn logic(&mut self, x: usize, value: u8) {
+        self.v[x] = value;
+        if self.quirks.logic {
+            self.v[0xF] = 0;
+        }
+    }
+
+    // the flag is written after the result, so it is what's left in VF when X is F
+    fn arithmetic(&mut self, x: usize, flag: bool, value: u8) {
+        self.v[x] = value;
+        self.v[0xF] = flag as u8;
+    }
+
+    fn advance_i(&mut self, x: usize) {
+        if self.quirks.memory_leave_i_unchanged {
+            return;
+        }
+        let increment = if self.quirks.memory_increment_by_x { x } else { x + 1 };
+        self.i = self.i.wrapping_add(increment as u16);
+    }
+
+    fn draw(&mut self, left: usize, top: usize, rows: u16) {
+        self.v[0xF] = 0;
+        for row in 0..rows {
+            let mut y = top + row as usize;
+            if y >= HEIGHT {
+                if !self.quirks.wrap {
+                    return;
+                }
+                y %= HEIGHT;
+            }
+            let sprite = self.read(self.i.wrapping_add(row));
+            for column in 0..8 {
+                let mut x = left + column;
+                if x >= WIDTH {
+                    if !self.quirks.wrap {
+                        break;
+                    }
+                    x %= WIDTH;
+                }
+                if sprite & (0x80 >> column) != 0 {
+                    if self.screen[y][x] {
+                        self.v[0xF] = 1;
+                    }
+                    self.screen[y][x] = !self.screen[y][x];
+                }
+            }
+        }
+    }
+}
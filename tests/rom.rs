@@ -0,0 +1,138 @@
+// Reading ROMs: size limits, including on input that never ends, and programs in zip and gzip archives,
+// with a program in a zip named as if the zip were a directory.
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use rust_chip8_emulator::rom::{self, RomError, MAX_ROM_SIZE, MAX_SOURCE_SIZE};
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use zip::write::SimpleFileOptions;
+use zip::ZipWriter;
+
+const PROGRAM: [u8; 4] = [0x60, 0x01, 0x12, 0x02];
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("chip8-rom-{}-{}", std::process::id(), name));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn write_zip(path: &Path, entries: &[(&str, &[u8])]) {
+    let mut zip = ZipWriter::new(File::create(path).unwrap());
+    for (name, contents) in entries {
+        let options = SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+        zip.start_file(*name, options).unwrap();
+        zip.write_all(contents).unwrap();
+    }
+    zip.finish().unwrap();
+}
+
+fn write_gz(path: &Path, contents: &[u8]) {
+    let mut gz = GzEncoder::new(File::create(path).unwrap(), Compression::best());
+    gz.write_all(contents).unwrap();
+    gz.finish().unwrap();
+}
+
+#[test]
+fn roms_up_to_the_limit_read_whole() {
+    let data = vec![0xA5; MAX_ROM_SIZE];
+    assert_eq!(rom::read(data.as_slice()).unwrap(), data);
+    assert!(matches!(rom::read(io::empty()), Err(RomError::Empty)));
+}
+
+#[test]
+fn reading_stops_just_past_the_limit() {
+    //an endless stream would never finish if the rest were counted
+    let error = rom::read(io::repeat(0)).unwrap_err();
+    assert!(matches!(error, RomError::TooLarge { size: None, max: MAX_ROM_SIZE }));
+    assert_eq!(error.to_string(), "the ROM is more than the 3584 bytes that fit in memory after 0x200");
+
+    let mut stream = io::repeat(1).take(1 << 20);
+    assert!(rom::read(&mut stream).is_err());
+    assert_eq!(stream.limit(), (1 << 20) - MAX_ROM_SIZE as u64 - 1);
+}
+
+#[test]
+fn validating_gives_the_size() {
+    let error = rom::validate(&[0; MAX_ROM_SIZE + 2]).unwrap_err();
+    assert!(matches!(error, RomError::TooLarge { size: Some(3586), max: MAX_ROM_SIZE }));
+    assert_eq!(error.to_string(), "the ROM is 3586 bytes but only 3584 bytes fit in memory after 0x200");
+}
+
+#[test]
+fn programs_in_a_zip_are_named_through_it() {
+    let dir = temp_dir("zip");
+    let zip = dir.join("pack.zip");
+    write_zip(&zip, &[("games/brix.sc8", &PROGRAM), ("readme.txt", b"hello"), ("pong.ch8", &[0x12, 0x00])]);
+
+    let rom = rom::load(&zip.join("games").join("brix.sc8")).unwrap();
+    assert_eq!(rom.data, PROGRAM);
+    assert_eq!(rom.settings.platform.as_deref(), Some("schip"));
+    assert_eq!(rom::name(&rom.path), "brix");
+    assert!(rom::exists(&zip.join("games/brix.sc8")));
+    assert_eq!(rom::list_archive(&zip).unwrap(), vec![zip.join("games/brix.sc8"), zip.join("pong.ch8")]);
+
+    assert!(matches!(rom::load(&zip.join("games/missing.ch8")), Err(RomError::Archive(_))));
+    match rom::load(&zip) {
+        Err(RomError::SeveralPrograms(names)) => assert_eq!(names.len(), 2),
+        other => panic!("{:?}", other),
+    }
+    let _ = fs::remove_dir_all(dir);
+}
+
+#[test]
+fn a_zip_with_one_program_loads_by_its_own_path() {
+    let dir = temp_dir("single");
+    let single = dir.join("single.zip");
+    write_zip(&single, &[("only.ch8", &PROGRAM), ("notes.txt", b"not a program")]);
+    let rom = rom::load(&single).unwrap();
+    assert_eq!((rom.path, rom.data), (single.join("only.ch8"), PROGRAM.to_vec()));
+
+    let empty = dir.join("empty.zip");
+    write_zip(&empty, &[("notes.txt", b"not a program")]);
+    assert!(matches!(rom::load(&empty), Err(RomError::NoPrograms)));
+    let _ = fs::remove_dir_all(dir);
+}
+
+#[test]
+fn gzipped_roms_load_under_their_inner_name() {
+    let dir = temp_dir("gz");
+    let path = dir.join("brix.xo8.gz");
+    write_gz(&path, &PROGRAM);
+    let rom = rom::load(&path).unwrap();
+    assert_eq!(rom.data, PROGRAM);
+    assert_eq!(rom.settings.platform.as_deref(), Some("xochip"));
+    assert_eq!(rom::name(&path), "brix");
+    assert_eq!(rom::list_archive(&path).unwrap(), vec![path.clone()]);
+    let _ = fs::remove_dir_all(dir);
+}
+
+#[test]
+fn archives_that_unpack_too_far_stop_early() {
+    let dir = temp_dir("bombs");
+    let huge = vec![0; 4 << 20];
+
+    let gz = dir.join("bomb.ch8.gz");
+    write_gz(&gz, &huge);
+    assert!(matches!(rom::load(&gz), Err(RomError::TooLarge { size: None, max: MAX_ROM_SIZE })));
+
+    let zip = dir.join("bomb.zip");
+    write_zip(&zip, &[("bomb.ch8", &huge), ("big.ch8", &[0; MAX_ROM_SIZE + 1]), ("bomb.8o", &huge)]);
+    assert!(matches!(rom::load(&zip.join("bomb.ch8")), Err(RomError::TooLarge { size: Some(size), .. }) if size == huge.len()));
+    assert!(matches!(rom::load(&zip.join("big.ch8")), Err(RomError::TooLarge { size: Some(3585), .. })));
+    assert!(matches!(rom::load(&zip.join("bomb.8o")), Err(RomError::SourceTooLarge { max: MAX_SOURCE_SIZE })));
+    let _ = fs::remove_dir_all(dir);
+}
+
+#[test]
+fn octo_source_can_be_bigger_than_a_rom() {
+    let dir = temp_dir("source");
+    let source = format!("# {}\n: main v0 := 1", "-".repeat(MAX_ROM_SIZE));
+    let zip = dir.join("source.zip");
+    write_zip(&zip, &[("big.8o", source.as_bytes())]);
+    assert_eq!(rom::load(&zip).unwrap().data, [0x12, 0x02, 0x60, 0x01]);
+    let _ = fs::remove_dir_all(dir);
+}
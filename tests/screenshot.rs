@@ -0,0 +1,128 @@
+// Screenshots: PNGs are what the window shows, at the display scale with the palette and filter
+// applied, and PBMs are the raw frame buffer packed eight pixels to a byte.
+
+use rust_chip8_emulator::display::{Display, Palette};
+use rust_chip8_emulator::filters::Filter;
+use rust_chip8_emulator::screenshot::{self, ImageFormat};
+use std::env;
+use std::fs;
+use std::io::Cursor;
+
+const BACKGROUND: u32 = 0x102030;
+const FOREGROUND: u32 = 0x33FF66;
+
+// a display with the top-left pixel and a pixel at (5, 2) lit
+fn display(scale: usize, filter: Filter) -> Display {
+    let mut display = Display::headless(scale);
+    display.palette = Palette {
+        background: BACKGROUND,
+        foreground: FOREGROUND,
+    };
+    display.filter = filter;
+    display.frame_buffer[0][0] = true;
+    display.frame_buffer[2][5] = true;
+    display
+}
+
+// decodes a PNG into its size and 0RGB pixels
+fn decode(png: &[u8]) -> (Vec<u32>, usize, usize) {
+    let mut reader = png::Decoder::new(Cursor::new(png)).read_info().unwrap();
+    let mut data = vec![0; reader.output_buffer_size().unwrap()];
+    let info = reader.next_frame(&mut data).unwrap();
+    assert_eq!((info.color_type, info.bit_depth), (png::ColorType::Rgb, png::BitDepth::Eight));
+    let pixels = data[..info.buffer_size()]
+        .chunks(3)
+        .map(|rgb| ((rgb[0] as u32) << 16) | ((rgb[1] as u32) << 8) | rgb[2] as u32)
+        .collect();
+    (pixels, info.width as usize, info.height as usize)
+}
+
+fn png(display: &Display) -> (Vec<u32>, usize, usize) {
+    let mut bytes = Vec::new();
+    screenshot::write_png(display, &mut bytes).unwrap();
+    decode(&bytes)
+}
+
+#[test]
+fn pngs_are_scaled_and_use_the_palette() {
+    for scale in [1, 3, 8] {
+        let (pixels, width, height) = png(&display(scale, Filter::Nearest));
+        assert_eq!((width, height), (64 * scale, 32 * scale));
+        let at = |x: usize, y: usize| pixels[y * width + x];
+        assert_eq!(at(0, 0), FOREGROUND);
+        assert_eq!(at(scale - 1, scale - 1), FOREGROUND);
+        assert_eq!(at(scale, 0), BACKGROUND);
+        assert_eq!(at(5 * scale, 2 * scale), FOREGROUND);
+        assert_eq!(at(width - 1, height - 1), BACKGROUND);
+        assert_eq!(pixels.iter().filter(|&&pixel| pixel == FOREGROUND).count(), 2 * scale * scale);
+    }
+}
+
+#[test]
+fn pngs_apply_the_filter() {
+    for filter in [Filter::Scanlines, Filter::CrtMask] {
+        let display = display(6, filter);
+        let (pixels, width, height) = png(&display);
+        assert_eq!((width, height), (64 * 6, 32 * 6));
+        let (expected, _, _) = display.scaled_pixels();
+        assert!(pixels.iter().zip(&expected).all(|(&pixel, &expected)| pixel == expected & 0xFFFFFF));
+    }
+
+    //scanlines double the image and dim every other row, which a scale of 6 makes three rows tall
+    let (pixels, width, _) = png(&display(6, Filter::Scanlines));
+    let dim = |color: u32| {
+        let channel = |shift: u32| ((color >> shift) & 0xFF) * 128 / 255;
+        (channel(16) << 16) | (channel(8) << 8) | channel(0)
+    };
+    let column: Vec<u32> = (0..6).map(|y| pixels[y * width]).collect();
+    assert_eq!(column, [FOREGROUND, FOREGROUND, FOREGROUND, dim(FOREGROUND), dim(FOREGROUND), dim(FOREGROUND)]);
+    assert_eq!(pixels[3 * width + 6], dim(BACKGROUND));
+}
+
+#[test]
+fn pbms_pack_eight_pixels_to_a_byte() {
+    let display = display(4, Filter::CrtMask);
+    let mut bytes = Vec::new();
+    screenshot::write_pbm(&display, &mut bytes).unwrap();
+    let header = b"P4\n64 32\n";
+    assert_eq!(&bytes[..header.len()], header);
+    let rows = &bytes[header.len()..];
+    assert_eq!(rows.len(), 8 * 32);
+    assert_eq!(rows[0], 0b1000_0000);
+    assert_eq!(rows[2 * 8], 0b0000_0100);
+    assert_eq!(rows.iter().map(|byte| byte.count_ones()).sum::<u32>(), 2);
+}
+
+#[test]
+fn pbm_rows_are_padded_to_whole_bytes() {
+    //10 wide, so each row is two bytes and the last six bits are padding
+    let mut lit = vec![false; 10 * 3];
+    lit[0] = true;
+    lit[9] = true;
+    lit[10 + 8] = true;
+    lit[20..30].fill(true);
+    let mut bytes = Vec::new();
+    screenshot::write_pbm_pixels(&lit, 10, 3, &mut bytes).unwrap();
+    let mut expected = b"P4\n10 3\n".to_vec();
+    expected.extend_from_slice(&[0b1000_0000, 0b0100_0000, 0, 0b1000_0000, 0xFF, 0b1100_0000]);
+    assert_eq!(bytes, expected);
+}
+
+#[test]
+fn screenshots_are_named_after_the_rom() {
+    let dir = env::temp_dir().join(format!("chip8-screenshot-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    let display = display(2, Filter::Nearest);
+
+    let path = screenshot::save(&display, &dir, "PONG", ImageFormat::Png).unwrap();
+    let name = path.file_name().unwrap().to_str().unwrap();
+    assert!(name.starts_with("PONG_") && name.ends_with(".png"), "{}", name);
+    assert_eq!(png(&display).0, decode(&fs::read(&path).unwrap()).0);
+
+    let path = screenshot::save(&display, &dir, "", ImageFormat::Pbm).unwrap();
+    let name = path.file_name().unwrap().to_str().unwrap();
+    assert!(name.starts_with("chip8_") && name.ends_with(".pbm"), "{}", name);
+    assert!(fs::read(&path).unwrap().starts_with(b"P4\n64 32\n"));
+
+    fs::remove_dir_all(dir).unwrap();
+}
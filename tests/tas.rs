@@ -0,0 +1,132 @@
+// TAS mode: frame advance logs the input of every frame, and editing, truncating or re-recording the
+// log and seeking through keyframes ends up exactly where emulating straight through with that input
+// does.
+
+use rust_chip8_emulator::chip8::Chip8;
+use rust_chip8_emulator::tas::{self, KEYFRAME_INTERVAL};
+use std::env;
+use std::fs;
+
+// adds up the held keys and a random number every pass, and shows the total
+const COUNTER: [u16; 12] = [0x6100, 0xE1A1, 0x8214, 0x7101, 0x3110, 0x1202, 0xC30F, 0x8234, 0xF229, 0x00E0, 0xD005, 0x1200];
+
+const FRAMES: u64 = 200;
+const EDITED: u64 = 130;
+
+fn tas_machine(name: &str) -> Chip8 {
+    let path = env::temp_dir().join(format!("chip8-tas-{}-{}.ch8", std::process::id(), name));
+    fs::write(&path, COUNTER.iter().flat_map(|opcode| opcode.to_be_bytes()).collect::<Vec<u8>>()).unwrap();
+    let mut chip8 = Chip8::headless();
+    chip8.load_rom(path.to_str().unwrap()).unwrap();
+    fs::remove_file(path).unwrap();
+    chip8.set_seed(4);
+    chip8.set_speed(100);
+    chip8.start_tas();
+    chip8
+}
+
+// a key held for a few frames out of every ten, a different one each time
+fn input(frame: u64) -> u16 {
+    if frame % 10 < 4 {
+        1 << (frame / 10 % 16)
+    } else {
+        0
+    }
+}
+
+fn state(chip8: &Chip8) -> String {
+    format!("{:?}", chip8.save_state())
+}
+
+// emulates frame by frame with input, edited on EDITED, and the state at the start of every frame
+fn straight_through(name: &str, edit: u16) -> Vec<String> {
+    let mut chip8 = tas_machine(name);
+    let mut states = vec![state(&chip8)];
+    for frame in 0..FRAMES {
+        let input = if frame == EDITED { input(frame) ^ edit } else { input(frame) };
+        chip8.tas_advance(Some(tas::input_states(input)));
+        states.push(state(&chip8));
+    }
+    states
+}
+
+#[test]
+fn frame_advance_logs_the_input() {
+    let mut chip8 = tas_machine("advance");
+    assert!(chip8.is_tas() && chip8.frame() == 0);
+    chip8.tas_advance(Some(tas::input_states(0b1000)));
+    chip8.tas_advance(None);
+    assert_eq!(chip8.frame(), 2);
+    let log = chip8.tas().unwrap();
+    assert_eq!((log.len(), log.input(0), log.input(1)), (2, 0b1000, 0b1000));
+    assert_eq!(tas::input_name(log.input(1)), "3");
+
+    //inside the log the logged input is used, not the live keys
+    chip8.tas_seek(0);
+    chip8.tas_advance(Some(tas::input_states(0b1)));
+    assert_eq!(chip8.tas().unwrap().input(0), 0b1000);
+    assert_eq!(chip8.tas().unwrap().len(), 2);
+}
+
+#[test]
+fn editing_a_frame_replays_like_playing_it_that_way() {
+    let original = straight_through("original", 0);
+    let edited = straight_through("edited", 0b110);
+    assert!(original[FRAMES as usize] != edited[FRAMES as usize], "the edit made no difference");
+
+    let mut chip8 = tas_machine("editor");
+    for frame in 0..FRAMES {
+        chip8.tas_advance(Some(tas::input_states(input(frame))));
+    }
+    assert_eq!(chip8.tas().unwrap().keyframe_before(FRAMES).0, 3 * KEYFRAME_INTERVAL);
+
+    //seeking back loads the keyframe before the frame and emulates up to it
+    chip8.tas_seek(EDITED);
+    assert_eq!(state(&chip8), original[EDITED as usize]);
+    chip8.tas_toggle_key(1);
+    chip8.tas_toggle_key(2);
+    let log = chip8.tas().unwrap();
+    assert_eq!(log.input(EDITED), input(EDITED) ^ 0b110);
+    assert_eq!(log.len(), FRAMES);
+    //keyframes after the edit are stale and dropped
+    assert_eq!(log.keyframe_before(FRAMES).0, 2 * KEYFRAME_INTERVAL);
+
+    //the input after the edit is kept, so seeking forward plays out the edited run
+    chip8.tas_seek(FRAMES);
+    assert_eq!(state(&chip8), edited[FRAMES as usize]);
+    for frame in [0, 59, 60, 61, EDITED, EDITED + 1, FRAMES - 1] {
+        chip8.tas_seek(frame);
+        assert_eq!(state(&chip8), edited[frame as usize], "seeking to frame {}", frame);
+    }
+}
+
+#[test]
+fn truncating_rerecords_from_the_frame() {
+    let edited = straight_through("rerecord-edited", 0b100);
+    let mut chip8 = tas_machine("rerecord");
+    for frame in 0..FRAMES {
+        chip8.tas_advance(Some(tas::input_states(input(frame))));
+    }
+
+    chip8.tas_seek(EDITED);
+    chip8.tas_truncate();
+    assert_eq!(chip8.tas().unwrap().len(), EDITED);
+    assert_eq!(chip8.tas().unwrap().keyframe_before(FRAMES).0, 2 * KEYFRAME_INTERVAL);
+    for frame in EDITED..FRAMES {
+        let input = if frame == EDITED { input(frame) ^ 0b100 } else { input(frame) };
+        chip8.tas_advance(Some(tas::input_states(input)));
+    }
+    assert_eq!(state(&chip8), edited[FRAMES as usize]);
+
+    //the movie recorded alongside keeps only the input the run ended up with
+    let movie = chip8.stop_movie().unwrap();
+    let mut player = tas_machine("rerecord-player");
+    player.stop_tas();
+    player.stop_movie();
+    player.play_movie(&movie).unwrap();
+    for _ in 0..movie.frames {
+        player.step_frame();
+    }
+    assert!(player.display().frame_buffer == chip8.display().frame_buffer);
+    assert_eq!(player.cycle(), chip8.cycle());
+}
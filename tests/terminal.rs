@@ -0,0 +1,98 @@
+// Terminal frontend: the frame buffer maps onto half-block and Braille cells, only cells that changed are
+// drawn again, and without release events a key counts as held for HOLD_TIME after its last press.
+
+use rust_chip8_emulator::display::{Display, Palette};
+use rust_chip8_emulator::terminal::{self, Cell, CellCache, KeyHold, TerminalMode, HOLD_TIME};
+use std::time::{Duration, Instant};
+
+const BACKGROUND: u32 = 0x000000;
+const FOREGROUND: u32 = 0x33FF66;
+
+fn display(lit: &[(usize, usize)]) -> Display {
+    let mut display = Display::headless(1);
+    display.palette = Palette {
+        background: BACKGROUND,
+        foreground: FOREGROUND,
+    };
+    for &(x, y) in lit {
+        display.frame_buffer[y][x] = true;
+    }
+    display
+}
+
+#[test]
+fn half_blocks_color_the_top_and_bottom_pixel() {
+    let (cells, columns, rows) = terminal::cells(&display(&[(0, 0), (1, 1), (2, 0), (2, 1), (63, 31)]), TerminalMode::HalfBlocks);
+    assert_eq!((columns, rows, cells.len()), (64, 16, 64 * 16));
+    assert!(cells.iter().all(|cell| cell.symbol == '▀'));
+    let colors = |cell: &Cell| (cell.foreground, cell.background);
+    assert_eq!(colors(&cells[0]), (FOREGROUND, BACKGROUND));
+    assert_eq!(colors(&cells[1]), (BACKGROUND, FOREGROUND));
+    assert_eq!(colors(&cells[2]), (FOREGROUND, FOREGROUND));
+    assert_eq!(colors(&cells[3]), (BACKGROUND, BACKGROUND));
+    assert_eq!(colors(&cells[15 * 64 + 63]), (BACKGROUND, FOREGROUND));
+}
+
+#[test]
+fn braille_puts_two_by_four_pixels_in_a_cell() {
+    let mut lit = vec![(0, 0), (1, 3), (63, 31)];
+    //every dot of the second cell
+    lit.extend((2..4).flat_map(|x| (0..4).map(move |y| (x, y))));
+    let (cells, columns, rows) = terminal::cells(&display(&lit), TerminalMode::Braille);
+    assert_eq!((columns, rows, cells.len()), (32, 8, 32 * 8));
+    assert_eq!(cells[0].symbol, '⢁'); // dots 1 and 8
+    assert_eq!(cells[1].symbol, '⣿');
+    assert_eq!(cells[2].symbol, '⠀');
+    assert_eq!(cells[7 * 32 + 31].symbol, '⢀'); // dot 8, bottom right
+    assert!(cells.iter().all(|cell| (cell.foreground, cell.background) == (FOREGROUND, BACKGROUND)));
+
+    //dots are numbered down the left column and then the right, with the bottom row last
+    for (x, y, symbol) in [(0, 1, '⠂'), (0, 2, '⠄'), (1, 0, '⠈'), (1, 1, '⠐'), (1, 2, '⠠'), (0, 3, '⡀')] {
+        assert_eq!(terminal::cells(&display(&[(x, y)]), TerminalMode::Braille).0[0].symbol, symbol, "({}, {})", x, y);
+    }
+}
+
+#[test]
+fn only_changed_cells_are_drawn_again() {
+    let mut cache = CellCache::default();
+    let (cells, _, _) = terminal::cells(&display(&[]), TerminalMode::HalfBlocks);
+    assert_eq!(cache.changed(&cells).len(), cells.len());
+    assert!(cache.changed(&cells).is_empty());
+
+    let (cells, columns, _) = terminal::cells(&display(&[(5, 3), (10, 20)]), TerminalMode::HalfBlocks);
+    assert_eq!(cache.changed(&cells), [columns + 5, 10 * columns + 10]);
+    assert!(cache.changed(&cells).is_empty());
+
+    //another mode has another number of cells, so everything is drawn
+    let (braille, _, _) = terminal::cells(&display(&[]), TerminalMode::Braille);
+    assert_eq!(cache.changed(&braille).len(), braille.len());
+
+    cache.clear();
+    assert_eq!(cache.changed(&braille).len(), braille.len());
+}
+
+#[test]
+fn presses_are_held_for_the_hold_time_without_release_events() {
+    let start = Instant::now();
+    let mut keys = KeyHold::new(false);
+    keys.press(5, start);
+    assert!(keys.states(start)[5]);
+    assert!(keys.states(start + HOLD_TIME - Duration::from_millis(1))[5]);
+    assert!(!keys.states(start + HOLD_TIME)[5]);
+
+    //autorepeat keeps it held
+    keys.press(5, start + HOLD_TIME / 2);
+    assert!(keys.states(start + HOLD_TIME)[5]);
+    assert_eq!(keys.states(start + HOLD_TIME).iter().filter(|&&held| held).count(), 1);
+    assert!(!keys.states(start + HOLD_TIME / 2 + HOLD_TIME)[5]);
+}
+
+#[test]
+fn release_events_hold_a_key_until_it_is_let_go() {
+    let start = Instant::now();
+    let mut keys = KeyHold::new(true);
+    keys.press(0xA, start);
+    assert!(keys.states(start + HOLD_TIME * 10)[0xA]);
+    keys.release(0xA);
+    assert!(!keys.states(start)[0xA]);
+}
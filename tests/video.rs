@@ -0,0 +1,45 @@
+// Video capture: GIF frame timing and the Y4M stream layout, recorded from a headless display.
+
+use rust_chip8_emulator::display::{Display, HEIGHT, WIDTH};
+use rust_chip8_emulator::video::{VideoFormat, VideoRecorder};
+use std::fs;
+use std::path::PathBuf;
+
+fn record(format: VideoFormat, frames: usize, name: &str) -> Vec<u8> {
+    let path: PathBuf = std::env::temp_dir().join(format!("chip8-video-{}-{}.{}", std::process::id(), name, format.extension()));
+    let display = Display::headless(1);
+    let mut recorder = VideoRecorder::create(&path, format, &display, 2).unwrap();
+    for _ in 0..frames {
+        recorder.write_frame(&display).unwrap();
+    }
+    assert_eq!(recorder.frames(), frames as u64);
+    recorder.finish().unwrap();
+    let bytes = fs::read(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    bytes
+}
+
+#[test]
+fn gifs_keep_every_frame_at_exactly_60_fps() {
+    let bytes = record(VideoFormat::Gif, 12, "timing");
+    let mut decoder = gif::DecodeOptions::new().read_info(bytes.as_slice()).unwrap();
+    assert_eq!((decoder.width() as usize, decoder.height() as usize), (WIDTH * 2, HEIGHT * 2));
+
+    let mut delays = Vec::new();
+    while let Some(frame) = decoder.read_next_frame().unwrap() {
+        delays.push(frame.delay);
+    }
+    //every frame is there, and each three take 5/100 s
+    assert_eq!(delays.len(), 12);
+    assert_eq!(delays, [2, 2, 1].repeat(4));
+    assert_eq!(delays.iter().map(|&delay| delay as u32).sum::<u32>(), 20);
+}
+
+#[test]
+fn y4m_keeps_every_frame() {
+    let bytes = record(VideoFormat::Y4m, 3, "frames");
+    let header = format!("YUV4MPEG2 W{} H{} F60:1 Ip A1:1 C444\n", WIDTH * 2, HEIGHT * 2);
+    assert!(bytes.starts_with(header.as_bytes()));
+    let frame_size = b"FRAME\n".len() + WIDTH * 2 * HEIGHT * 2 * 3;
+    assert_eq!(bytes.len(), header.len() + 3 * frame_size);
+}